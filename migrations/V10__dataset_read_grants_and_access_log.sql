@@ -0,0 +1,23 @@
+-- managers explicitly granted read access to the partitions of confidential and restricted datasets
+CREATE TABLE IF NOT EXISTS dataset_read_grants (
+    dataset_id INTEGER NOT NULL REFERENCES datasets(dataset_id) ON DELETE CASCADE,
+    manager_id INTEGER NOT NULL REFERENCES managers(manager_id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (dataset_id, manager_id)
+);
+
+-- partition reads which were denied, kept independent of the datasets and managers involved so
+-- that entries outlive them
+CREATE TABLE IF NOT EXISTS access_log (
+    access_id BIGSERIAL PRIMARY KEY,
+    manager_id INTEGER,
+    remote_addr VARCHAR(255),
+    dataset_name VARCHAR(255) NOT NULL,
+    partition_name VARCHAR(255),
+    access_action VARCHAR(64) NOT NULL,
+    denial_reason VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS access_log_created_at_idx ON access_log (created_at);
+CREATE INDEX IF NOT EXISTS access_log_dataset_name_idx ON access_log (dataset_name);
@@ -0,0 +1,9 @@
+-- the template which partition names must match, e.g. "{yyyy}/{mm}/{dd}/{name}.{ext}"
+ALTER TABLE datasets ADD COLUMN IF NOT EXISTS dataset_partition_template VARCHAR(255);
+
+-- the structured key parsed from each partition's name by its dataset's partition template
+ALTER TABLE partitions ADD COLUMN IF NOT EXISTS partition_date DATE;
+ALTER TABLE partitions ADD COLUMN IF NOT EXISTS partition_hour SMALLINT;
+ALTER TABLE partitions ADD COLUMN IF NOT EXISTS partition_shard INTEGER;
+
+CREATE INDEX IF NOT EXISTS partitions_dataset_id_date_idx ON partitions (dataset_id, partition_date);
@@ -0,0 +1,6 @@
+-- the span of time covered by the data within each partition, which may differ from when the
+-- partition was registered, e.g. when data is backfilled
+ALTER TABLE partitions ADD COLUMN IF NOT EXISTS partition_data_start TIMESTAMPTZ;
+ALTER TABLE partitions ADD COLUMN IF NOT EXISTS partition_data_end TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS partitions_dataset_id_data_start_idx ON partitions (dataset_id, partition_data_start);
@@ -0,0 +1 @@
+ALTER TABLE managers ADD COLUMN IF NOT EXISTS is_disabled BOOLEAN DEFAULT FALSE NOT NULL;
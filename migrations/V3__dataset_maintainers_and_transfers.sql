@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS dataset_maintainers (
+    dataset_id INTEGER NOT NULL REFERENCES datasets(dataset_id) ON DELETE CASCADE,
+    manager_id INTEGER NOT NULL REFERENCES managers(manager_id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (dataset_id, manager_id)
+);
+
+-- a dataset may only have a single pending ownership transfer at a time
+CREATE TABLE IF NOT EXISTS dataset_transfers (
+    transfer_id SERIAL PRIMARY KEY,
+    dataset_id INTEGER UNIQUE NOT NULL REFERENCES datasets(dataset_id) ON DELETE CASCADE,
+    from_manager_id INTEGER NOT NULL REFERENCES managers(manager_id) ON DELETE CASCADE,
+    to_manager_id INTEGER NOT NULL REFERENCES managers(manager_id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (from_manager_id <> to_manager_id)
+);
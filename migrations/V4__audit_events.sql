@@ -0,0 +1,28 @@
+CREATE TABLE IF NOT EXISTS audit_events (
+    event_id BIGSERIAL PRIMARY KEY,
+    actor_type VARCHAR(16) NOT NULL,
+    actor_id VARCHAR(255),
+    event_action VARCHAR(64) NOT NULL,
+    target_type VARCHAR(32) NOT NULL,
+    target_name VARCHAR(512) NOT NULL,
+    snapshot_before JSONB,
+    snapshot_after JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS audit_events_created_at_idx ON audit_events (created_at);
+CREATE INDEX IF NOT EXISTS audit_events_target_idx ON audit_events (target_type, target_name);
+CREATE INDEX IF NOT EXISTS audit_events_actor_idx ON audit_events (actor_type, actor_id);
+
+-- function to reject any change to an existing audit event, keeping the table append-only
+CREATE OR REPLACE FUNCTION on_audit_event_modify_reject()
+RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_events is append-only';
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_events_append_only
+BEFORE UPDATE OR DELETE ON audit_events
+FOR EACH ROW
+EXECUTE PROCEDURE on_audit_event_modify_reject();
@@ -0,0 +1,7 @@
+-- soft delete datasets and partitions, so that they may be restored until purged
+ALTER TABLE datasets ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE partitions ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+-- only deleted rows are looked up by deletion time, when they are purged
+CREATE INDEX IF NOT EXISTS datasets_deleted_at_idx ON datasets (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX IF NOT EXISTS partitions_deleted_at_idx ON partitions (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,12 @@
+-- document searched by full-text dataset search, weighting the dataset name above its description
+-- and schema field names and types
+CREATE OR REPLACE FUNCTION dataset_search_document(dataset_name VARCHAR, dataset_desc TEXT, dataset_schema HSTORE)
+RETURNS TSVECTOR AS $$
+    SELECT setweight(to_tsvector('english', COALESCE(dataset_name, '')), 'A')
+        || setweight(to_tsvector('english', COALESCE(dataset_desc, '')), 'B')
+        || setweight(to_tsvector('english', COALESCE(array_to_string(akeys(dataset_schema), ' '), '')), 'C')
+        || setweight(to_tsvector('english', COALESCE(array_to_string(avals(dataset_schema), ' '), '')), 'D')
+$$ LANGUAGE SQL IMMUTABLE;
+
+CREATE INDEX IF NOT EXISTS datasets_search_idx ON datasets
+USING GIN (dataset_search_document(dataset_name, dataset_desc, dataset_schema));
@@ -0,0 +1,60 @@
+-- every schema a dataset has had, where the highest version is the dataset's current schema
+CREATE TABLE IF NOT EXISTS dataset_schema_versions (
+    dataset_id INTEGER NOT NULL REFERENCES datasets(dataset_id) ON DELETE CASCADE,
+    schema_version INTEGER NOT NULL,
+    dataset_schema hstore NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (dataset_id, schema_version)
+);
+
+-- the fields of each schema version, so that datasets can be found by field name and type
+CREATE TABLE IF NOT EXISTS dataset_schema_fields (
+    dataset_id INTEGER NOT NULL,
+    schema_version INTEGER NOT NULL,
+    field_name TEXT NOT NULL,
+    field_type TEXT,
+    PRIMARY KEY (dataset_id, schema_version, field_name),
+    FOREIGN KEY (dataset_id, schema_version)
+        REFERENCES dataset_schema_versions(dataset_id, schema_version) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS dataset_schema_fields_name_idx ON dataset_schema_fields (LOWER(field_name) text_pattern_ops);
+CREATE INDEX IF NOT EXISTS dataset_schema_fields_type_idx ON dataset_schema_fields (LOWER(field_type));
+
+-- function to record a new schema version when a dataset is created or its schema changes
+CREATE OR REPLACE FUNCTION on_dataset_schema_change_add_version()
+RETURNS TRIGGER AS $$
+DECLARE
+    version INTEGER;
+BEGIN
+    IF TG_OP = 'UPDATE' AND NEW.dataset_schema = OLD.dataset_schema THEN
+        RETURN NEW;
+    END IF;
+
+    SELECT COALESCE(MAX(schema_version), 0) + 1 INTO version
+    FROM dataset_schema_versions
+    WHERE dataset_id = NEW.dataset_id;
+
+    INSERT INTO dataset_schema_versions (dataset_id, schema_version, dataset_schema)
+    VALUES (NEW.dataset_id, version, NEW.dataset_schema);
+
+    INSERT INTO dataset_schema_fields (dataset_id, schema_version, field_name, field_type)
+    SELECT NEW.dataset_id, version, key, value FROM each(NEW.dataset_schema);
+
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER auto_add_schema_version
+AFTER INSERT OR UPDATE OF dataset_schema ON datasets
+FOR EACH ROW
+EXECUTE PROCEDURE on_dataset_schema_change_add_version();
+
+-- existing datasets start with their current schema as the first version
+INSERT INTO dataset_schema_versions (dataset_id, schema_version, dataset_schema, created_at)
+SELECT dataset_id, 1, dataset_schema, updated_at FROM datasets
+ON CONFLICT DO NOTHING;
+
+INSERT INTO dataset_schema_fields (dataset_id, schema_version, field_name, field_type)
+SELECT dataset_id, 1, fields.key, fields.value FROM datasets, each(dataset_schema) AS fields
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,10 @@
+-- free-form key/value labels used to group datasets, e.g. "team": "billing" or "tier": "1"
+CREATE TABLE IF NOT EXISTS dataset_labels (
+    dataset_id INTEGER NOT NULL REFERENCES datasets(dataset_id) ON DELETE CASCADE,
+    label_key VARCHAR(63) NOT NULL,
+    label_value VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (dataset_id, label_key)
+);
+
+CREATE INDEX IF NOT EXISTS dataset_labels_key_value_idx ON dataset_labels (label_key, label_value);
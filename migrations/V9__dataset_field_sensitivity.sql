@@ -0,0 +1,3 @@
+-- per-field sensitivity annotations, mapping schema field names to the kind of PII they hold,
+-- e.g. "customer_email": "email"
+ALTER TABLE datasets ADD COLUMN IF NOT EXISTS dataset_sensitivity JSONB NOT NULL DEFAULT '{}';
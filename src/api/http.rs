@@ -1,10 +1,22 @@
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::sync::Arc;
 
+use crate::api::{openapi, routes};
 use crate::bucket::BucketManager;
 use crate::db::Db;
-use crate::dict::{Attributes, Classification, Dataset, DatasetConfig, Manager, RangeParams};
-use crate::error::Error as DDError;
+use crate::dict::{parse_labels, validate_labels, validate_sensitivity};
+use crate::dict::{
+    AccessDenial, AccessFilter, Actor, Attributes, AuditEvent, AuditFilter, Classification,
+    Compression, Cursor, Dataset, DatasetConfig, DatasetFilter, DatasetLabels, DatasetSchema,
+    DatasetSensitivity, DatasetTransfer, FieldSearch, Format, Manager, NewPartition,
+    PartitionFilter, PartitionKey, RangeParams, SearchParams, Sensitivity, SortKey, SortOrder,
+};
+use crate::error::{Error as DDError, ErrorBody, ErrorKind};
+use crate::health::{self, Heartbeat};
+use crate::metrics;
+use crate::partition_template::PartitionTemplate;
+use crate::telemetry::{mask, Secret};
 
 use actix_http::Response;
 use actix_web::{
@@ -13,19 +25,25 @@ use actix_web::{
     web::{Data, HttpRequest, Json, Path, Query},
     Error, HttpResponse,
 };
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+// signed URLs expire after 15 minutes, unless a different TTL is requested
+const DEFAULT_SIGNED_URL_TTL_SECS: i64 = 900;
+
 #[derive(Clone)]
 pub struct Server {
     pub db: Db,
     pub bucket_manager: Arc<BucketManager>,
+    /// The heartbeat of the pubsub runtime, unless the pubsub feature is disabled.
+    pub pubsub: Option<Heartbeat>,
 }
 
 #[derive(Deserialize)]
 pub struct AuthManager {
     email: String,
-    password: String,
+    password: Secret<String>,
 }
 
 #[derive(Serialize)]
@@ -45,6 +63,44 @@ impl From<Manager> for RestrictedManager {
     }
 }
 
+pub async fn openapi_spec() -> Result<HttpResponse, Error> {
+    HttpResponse::build(StatusCode::OK)
+        .json(openapi::spec())
+        .await
+}
+
+/// Responds as long as the process is alive, without checking any of its dependencies.
+pub async fn healthz() -> Result<HttpResponse, Error> {
+    HttpResponse::Ok()
+        .json(serde_json::json!({ "status": "ok" }))
+        .await
+}
+
+/// Responds with whether the server is ready to serve requests, which fails with 503 Service
+/// Unavailable if any of its dependencies are not.
+pub async fn readyz(srv: Data<Server>) -> Result<HttpResponse, Error> {
+    let readiness = health::readiness(&srv.db, &srv.bucket_manager, srv.pubsub.as_ref()).await;
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    HttpResponse::build(status).json(readiness).await
+}
+
+/// Serves metrics in the Prometheus text format, which aren't authenticated so that they can be
+/// scraped.
+pub async fn metrics(srv: Data<Server>) -> Result<HttpResponse, Error> {
+    match metrics::gather(&srv.db).await {
+        Ok((content_type, body)) => Ok(HttpResponse::Ok().content_type(content_type).body(body)),
+        Err(e) => {
+            let resp = HttpResponse::build(StatusCode::OK);
+            error_message(resp, &e, "failed to gather metrics").await
+        }
+    }
+}
+
 pub async fn register_manager(
     srv: Data<Server>,
     params: Json<AuthManager>,
@@ -60,13 +116,9 @@ pub async fn register_manager(
         log::error!("{}: {}", msg, err);
 
         match err {
-            DDError::Sql(_) => json_message(resp, StatusCode::NOT_FOUND, msg).await,
+            DDError::Sql(_) => error_message(resp, &err, msg).await,
             DDError::InputValidation(msg) => {
-                log::info!(
-                    "request handled, input: {} {}",
-                    &params.email,
-                    &params.password
-                );
+                log::info!("request handled, input: {}", &params.email);
                 json_message(
                     resp,
                     StatusCode::BAD_REQUEST,
@@ -77,7 +129,7 @@ pub async fn register_manager(
                 )
                 .await
             }
-            _ => json_message(resp, StatusCode::INTERNAL_SERVER_ERROR, msg).await,
+            _ => error_message(resp, &err, msg).await,
         }
     }
 }
@@ -97,13 +149,9 @@ pub async fn login_manager(
         log::error!("{}: {}", msg, err);
 
         match err {
-            DDError::Sql(_) => json_message(resp, StatusCode::NOT_FOUND, msg).await,
+            DDError::Sql(_) => error_message(resp, &err, msg).await,
             DDError::InputValidation(msg) => {
-                log::info!(
-                    "request handled, input: {} {}",
-                    &params.email,
-                    &params.password
-                );
+                log::info!("request handled, input: {}", &params.email);
                 json_message(
                     resp,
                     StatusCode::BAD_REQUEST,
@@ -114,7 +162,7 @@ pub async fn login_manager(
                 )
                 .await
             }
-            _ => json_message(resp, StatusCode::INTERNAL_SERVER_ERROR, msg).await,
+            _ => error_message(resp, &err, msg).await,
         }
     }
 }
@@ -124,28 +172,76 @@ pub struct ListPartitions {
     dataset_name: String,
 }
 
+// partitions are filtered by the range of the data they cover, rather than when they were
+// registered, along with their name prefix and an inclusive range of sizes
+#[derive(Deserialize)]
+pub struct PartitionFilters {
+    data_start: Option<DateTime<Utc>>,
+    data_end: Option<DateTime<Utc>>,
+    prefix: Option<String>,
+    min_size: Option<i64>,
+    max_size: Option<i64>,
+}
+
+impl PartitionFilters {
+    fn filter(&self, key: PartitionKey) -> Result<PartitionFilter, DDError> {
+        if let (Some(min), Some(max)) = (self.min_size, self.max_size) {
+            if min > max {
+                return Err(DDError::InputValidation(
+                    "min_size must not be greater than max_size".into(),
+                ));
+            }
+        }
+
+        Ok(PartitionFilter {
+            key,
+            name_prefix: self.prefix.clone(),
+            min_size: self.min_size,
+            max_size: self.max_size,
+        })
+    }
+}
+
 pub async fn list_partitions(
     srv: Data<Server>,
     params: Path<ListPartitions>,
+    key: Query<PartitionKey>,
+    filters: Query<PartitionFilters>,
+    page: Query<Pagination>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let mut resp = HttpResponse::build(StatusCode::OK);
     let dataset = Dataset::find(&mut srv.db.clone(), &params.dataset_name).await;
     if let Ok(dataset) = dataset {
-        match dataset.partitions(&mut srv.db.clone(), None).await {
-            Ok(partitions) => resp.json(partitions).await,
+        if let Err(resp) = authorize_read(&srv, &req, &dataset, "list_partitions", None).await {
+            return Ok(resp);
+        }
+
+        let mut range_params = match RangeParams::try_from(&*page) {
+            Ok(range_params) => range_params,
+            Err(e) => return json_message(resp, StatusCode::BAD_REQUEST, e.to_string()).await,
+        };
+        range_params.data_start = filters.data_start;
+        range_params.data_end = filters.data_end;
+        let filter = match filters.filter(*key) {
+            Ok(filter) => filter,
+            Err(e) => return json_message(resp, StatusCode::BAD_REQUEST, e.to_string()).await,
+        };
+
+        match dataset
+            .partitions_page_filtered(&mut srv.db.clone(), &filter, &range_params)
+            .await
+        {
+            Ok(page) if unversioned(&req) => resp.json(page.partitions).await,
+            Ok(page) => resp.json(page).await,
             Err(e) => match e {
-                DDError::Sql(_) => {
-                    json_message(
-                        resp,
-                        StatusCode::NOT_FOUND,
-                        format!("no partitions found for dataset '{}'", params.dataset_name),
-                    )
-                    .await
+                DDError::InputValidation(_) => {
+                    json_message(resp, StatusCode::BAD_REQUEST, e.to_string()).await
                 }
                 _ => {
-                    json_message(
+                    error_message(
                         resp,
-                        StatusCode::INTERNAL_SERVER_ERROR,
+                        &e,
                         format!(
                             "failed to list parititions for dataset '{}'",
                             params.dataset_name
@@ -161,11 +257,11 @@ pub async fn list_partitions(
         log::error!("{}: {}", msg, err);
 
         match err {
-            DDError::Sql(_) => json_message(resp, StatusCode::NOT_FOUND, msg).await,
+            DDError::NotFound(_) => error_message(resp, &err, msg).await,
             _ => {
-                json_message(
+                error_message(
                     resp,
-                    StatusCode::INTERNAL_SERVER_ERROR,
+                    &err,
                     format!("failed to find dataset '{}'", params.dataset_name),
                 )
                 .await
@@ -183,29 +279,37 @@ pub struct FindPartition {
 pub async fn find_partition(
     srv: Data<Server>,
     params: Path<FindPartition>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let mut resp = HttpResponse::build(StatusCode::OK);
 
     let dataset = Dataset::find(&mut srv.db.clone(), &params.dataset_name).await;
     if let Ok(dataset) = dataset {
+        let partition_name = Some(params.partition_name.as_str());
+        if let Err(resp) =
+            authorize_read(&srv, &req, &dataset, "find_partition", partition_name).await
+        {
+            return Ok(resp);
+        }
+
         match dataset
             .partition(&mut srv.db.clone(), &params.partition_name)
             .await
         {
             Ok(partition) => resp.json(partition).await,
             Err(e) => match e {
-                DDError::Sql(_) => {
-                    json_message(
+                DDError::NotFound(_) => {
+                    error_message(
                         resp,
-                        StatusCode::NOT_FOUND,
+                        &e,
                         format!("no partition found with name '{}'", params.partition_name),
                     )
                     .await
                 }
                 _ => {
-                    json_message(
+                    error_message(
                         resp,
-                        StatusCode::INTERNAL_SERVER_ERROR,
+                        &e,
                         format!("failed to find paritition '{}'", params.partition_name),
                     )
                     .await
@@ -218,11 +322,11 @@ pub async fn find_partition(
         log::error!("{}: {}", msg, err);
 
         match err {
-            DDError::Sql(_) => json_message(resp, StatusCode::NOT_FOUND, msg).await,
+            DDError::NotFound(_) => error_message(resp, &err, msg).await,
             _ => {
-                json_message(
+                error_message(
                     resp,
-                    StatusCode::INTERNAL_SERVER_ERROR,
+                    &err,
                     format!("failed to find dataset '{}'", params.dataset_name),
                 )
                 .await
@@ -231,6 +335,196 @@ pub async fn find_partition(
     }
 }
 
+#[derive(Deserialize)]
+pub struct SignUrl {
+    ttl: Option<i64>,
+}
+
+pub async fn download_partition(
+    srv: Data<Server>,
+    params: Path<FindPartition>,
+    sign: Query<SignUrl>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    let dataset = match find_dataset_by_name(&srv, &params.dataset_name).await {
+        Ok(dataset) => dataset,
+        Err(resp) => return Ok(resp),
+    };
+    let partition_name = Some(params.partition_name.as_str());
+    if let Err(resp) =
+        authorize_read(&srv, &req, &dataset, "download_partition", partition_name).await
+    {
+        return Ok(resp);
+    }
+
+    // only sign URLs for partitions known to the dictionary
+    let partition = match dataset
+        .partition(&mut srv.db.clone(), &params.partition_name)
+        .await
+    {
+        Ok(partition) => partition,
+        Err(e) => {
+            log::error!(
+                "failed to find partition '{}' to download: {}",
+                params.partition_name,
+                e
+            );
+            return match e {
+                DDError::NotFound(_) => {
+                    error_message(
+                        resp,
+                        &e,
+                        format!("no partition found with name '{}'", params.partition_name),
+                    )
+                    .await
+                }
+                _ => {
+                    error_message(
+                        resp,
+                        &e,
+                        format!("failed to find partition '{}'", params.partition_name),
+                    )
+                    .await
+                }
+            };
+        }
+    };
+
+    let ttl = match signed_url_ttl(&dataset, sign.ttl) {
+        Ok(ttl) => ttl,
+        Err(e) => return error_message(resp, &e, "invalid signed URL ttl").await,
+    };
+    match srv
+        .bucket_manager
+        .signed_download_url(&dataset, &partition.name, ttl)
+    {
+        Ok(signed) => resp.json(signed).await,
+        Err(e) => {
+            let msg = format!(
+                "failed to sign download URL for partition '{}'",
+                params.partition_name
+            );
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+// Signed URLs are valid for between a second and the most that the dataset's classification allows.
+// The requested seconds are checked before they're made into a Duration, which panics when they're
+// out of its range.
+fn signed_url_ttl(dataset: &Dataset, ttl: Option<i64>) -> Result<Duration, DDError> {
+    let max_secs = dataset.classification.max_signed_url_ttl().num_seconds();
+    let secs = ttl.unwrap_or(DEFAULT_SIGNED_URL_TTL_SECS);
+    if !(1..=max_secs).contains(&secs) {
+        return Err(DDError::InputValidation(format!(
+            "ttl must be between 1 and {} seconds for {} datasets",
+            max_secs, dataset.classification
+        )));
+    }
+
+    Ok(Duration::seconds(secs))
+}
+
+pub async fn upload_partition(
+    srv: Data<Server>,
+    params: Path<FindPartition>,
+    sign: Query<SignUrl>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    let manager = match request_manager(&srv, &req).await {
+        Ok(manager) => manager,
+        Err(resp) => return Ok(resp),
+    };
+    let dataset = match find_dataset_by_name(&srv, &params.dataset_name).await {
+        Ok(dataset) => dataset,
+        Err(resp) => return Ok(resp),
+    };
+    if let Err(resp) = authorize_owner(&dataset, &manager) {
+        return Ok(resp);
+    }
+
+    // the partition is registered once the upload completes, by the bucket's pubsub notification
+    let ttl = match signed_url_ttl(&dataset, sign.ttl) {
+        Ok(ttl) => ttl,
+        Err(e) => return error_message(resp, &e, "invalid signed URL ttl").await,
+    };
+    match srv
+        .bucket_manager
+        .signed_upload_url(&dataset, &params.partition_name, ttl)
+    {
+        Ok(signed) => resp.json(signed).await,
+        Err(e) => {
+            let msg = format!(
+                "failed to sign upload URL for partition '{}'",
+                params.partition_name
+            );
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+// a year of hourly partitions can be registered at once
+const MAX_REGISTERED_PARTITIONS: usize = 10_000;
+
+#[derive(Deserialize)]
+pub struct RegisterPartitions {
+    partitions: Vec<NewPartition>,
+}
+
+pub async fn register_partitions(
+    srv: Data<Server>,
+    params: Path<FindDataset>,
+    body: Json<RegisterPartitions>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    let manager = match request_manager(&srv, &req).await {
+        Ok(manager) => manager,
+        Err(resp) => return Ok(resp),
+    };
+    let dataset = match find_dataset_by_name(&srv, &params.dataset_name).await {
+        Ok(dataset) => dataset,
+        Err(resp) => return Ok(resp),
+    };
+    if let Err(resp) = authorize_owner(&dataset, &manager) {
+        return Ok(resp);
+    }
+
+    if body.partitions.len() > MAX_REGISTERED_PARTITIONS {
+        return json_message(
+            resp,
+            StatusCode::BAD_REQUEST,
+            format!(
+                "at most {} partitions can be registered at once",
+                MAX_REGISTERED_PARTITIONS
+            ),
+        )
+        .await;
+    }
+
+    match dataset
+        .register_partitions(
+            &mut srv.db.with_actor(Actor::from(&manager)),
+            &body.partitions,
+        )
+        .await
+    {
+        Ok(partitions) => resp.json(partitions).await,
+        Err(e) => {
+            let msg = format!(
+                "failed to register partitions for dataset '{}'",
+                params.dataset_name
+            );
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct LatestPartition {
     dataset_name: String,
@@ -239,10 +533,15 @@ pub struct LatestPartition {
 pub async fn latest_partition(
     srv: Data<Server>,
     params: Path<LatestPartition>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let mut resp = HttpResponse::build(StatusCode::OK);
     let dataset = Dataset::find(&mut srv.db.clone(), &params.dataset_name).await;
     if let Ok(dataset) = dataset {
+        if let Err(resp) = authorize_read(&srv, &req, &dataset, "latest_partition", None).await {
+            return Ok(resp);
+        }
+
         match dataset.latest_partition(&mut srv.db.clone()).await {
             Ok(partition) => resp.json(partition).await,
             Err(e) => {
@@ -252,10 +551,10 @@ pub async fn latest_partition(
                     e
                 );
                 match e {
-                    DDError::Sql(_) => {
-                        json_message(
+                    DDError::NotFound(_) => {
+                        error_message(
                             resp,
-                            StatusCode::NOT_FOUND,
+                            &e,
                             format!(
                                 "no latest partition found for dataset '{}'",
                                 params.dataset_name
@@ -264,9 +563,9 @@ pub async fn latest_partition(
                         .await
                     }
                     _ => {
-                        json_message(
+                        error_message(
                             resp,
-                            StatusCode::INTERNAL_SERVER_ERROR,
+                            &e,
                             format!(
                                 "failed to find latest partition for dataset '{}'",
                                 params.dataset_name
@@ -286,15 +585,15 @@ pub async fn latest_partition(
         log::error!("{}: {}", msg, err);
 
         match err {
-            DDError::Sql(_) => {
-                json_message(
+            DDError::NotFound(_) => {
+                error_message(
                     resp,
-                    StatusCode::NOT_FOUND,
+                    &err,
                     format!("no dataset found with name '{}'", params.dataset_name),
                 )
                 .await
             }
-            _ => json_message(resp, StatusCode::INTERNAL_SERVER_ERROR, msg).await,
+            _ => error_message(resp, &err, msg).await,
         }
     }
 }
@@ -304,138 +603,217 @@ pub async fn register_dataset(
     config: Json<DatasetConfig>,
     req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
-    let invalid_api_key = |resp, req: HttpRequest| {
-        log::error!(
-            "failed to register dataset, invalid or missing API key, headers = {:?}",
-            req.headers()
-        );
-        Ok(json_message(
-            resp,
-            StatusCode::UNAUTHORIZED,
-            "invalid or missing API key",
-        ))
-    };
-
     let mut resp = HttpResponse::build(StatusCode::OK);
 
     // verify that dataset does not already exist
-    if let Ok(_) = Dataset::find(&mut srv.db.clone(), &config.name).await {
-        let msg = format!("a dataset with name '{}' already exists", &config.name);
-        log::error!("failed to register dataset, {}", &msg);
-        return json_message(resp, StatusCode::CONFLICT, msg).await;
-    }
-
-    let mut api_key = Uuid::default();
-    if let Some(value) = req.headers().get("Authorization") {
-        if let Ok(bearer) = value.to_str() {
-            if let Ok(uuid) = Uuid::parse_str(&trim_api_key(bearer)) {
-                api_key = uuid;
-            } else {
-                return invalid_api_key(resp, req);
-            }
+    match Dataset::find(&mut srv.db.clone(), &config.name).await {
+        Ok(_) => {
+            let msg = format!("a dataset with name '{}' already exists", &config.name);
+            log::error!("failed to register dataset, {}", &msg);
+            return json_message(resp, StatusCode::CONFLICT, msg).await;
+        }
+        Err(DDError::NotFound(_)) => {}
+        Err(e) => {
+            log::error!("failed to register dataset '{}': {}", &config.name, e);
+            return error_message(resp, &e, "failed to register dataset").await;
         }
-    } else {
-        return invalid_api_key(resp, req);
     }
 
-    let manager = Manager::find(&mut srv.db.clone(), api_key).await;
-    if let Ok(manager) = manager {
-        // upload the dataset configuration to GCS
-        if let Err(_) = &srv.bucket_manager.register_dataset(&config).await {
-            return json_message(
-                resp,
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "failed to upload dataset configuration",
-            )
-            .await;
-        }
-
-        // if successful, store the dataset config in the database
-        match manager
-            .register_dataset(
-                &mut srv.db.clone(),
-                &config.name,
-                config.compression.clone(),
-                config.format.clone(),
-                config.classification.clone(),
-                config.schema.to_owned(),
-                &config.description,
-            )
-            .await
-        {
-            Ok(dataset) => resp.json(dataset).await,
-            Err(e) => {
-                log::error!(
-                    "failed to register dataset '{}' from manager '{}': {}",
-                    config.name,
-                    api_key,
-                    e
-                );
-                return Ok(json_message(
-                    resp,
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("failed to register dataset '{}'", config.name),
-                ));
-            }
+    // a deleted dataset keeps its name until it is purged, and can be restored instead
+    match Dataset::find_deleted(&mut srv.db.clone(), &config.name).await {
+        Ok(_) => {
+            let msg = format!(
+                "a deleted dataset with name '{}' exists and must be restored or purged first",
+                &config.name
+            );
+            log::error!("failed to register dataset, {}", &msg);
+            return json_message(resp, StatusCode::CONFLICT, msg).await;
         }
-    } else {
-        let msg = format!("failed to find manager with API key '{}'", api_key);
-        let err = manager.err().expect("no manager error specified");
-        log::error!("{}: {}", msg, err);
+        Err(DDError::NotFound(_)) => {}
+        Err(e) => {
+            log::error!("failed to register dataset '{}': {}", &config.name, e);
+            return error_message(resp, &e, "failed to register dataset").await;
+        }
+    }
 
-        match err {
-            DDError::Sql(_) => {
-                json_message(
-                    resp,
-                    StatusCode::NOT_FOUND,
-                    format!("no manager found with API key '{}'", api_key),
-                )
-                .await
-            }
-            _ => json_message(resp, StatusCode::INTERNAL_SERVER_ERROR, msg).await,
+    if let Err(e) = validate_labels(&config.labels)
+        .and_then(|_| {
+            validate_sensitivity(&config.schema, &config.classification, &config.sensitivity)
+        })
+        .and_then(|_| validate_partition_template(&config.partition_template))
+    {
+        return json_message(resp, StatusCode::BAD_REQUEST, e.to_string()).await;
+    }
+
+    let manager = match request_manager(&srv, &req).await {
+        Ok(manager) => manager,
+        Err(resp) => return Ok(resp),
+    };
+
+    // store the dataset config in the database, followed by its field sensitivity, labels, and
+    // partition template, then upload it to GCS. These are committed together, and an uploaded
+    // config is deleted again if committing fails, so that no part of the dataset is left behind.
+    let api_key = manager.api_key;
+    let bucket_manager = srv.bucket_manager.clone();
+    let config = config.into_inner();
+    let name = config.name.clone();
+    let registered = srv
+        .db
+        .with_actor(Actor::from(&manager))
+        .transaction(move |tx| {
+            Box::pin(async move {
+                let mut dataset = manager
+                    .register_dataset(
+                        tx,
+                        &config.name,
+                        config.compression.clone(),
+                        config.format.clone(),
+                        config.classification.clone(),
+                        config.schema.to_owned(),
+                        &config.description,
+                    )
+                    .await?;
+                if !config.sensitivity.is_empty() {
+                    dataset = dataset.set_sensitivity(tx, &config.sensitivity).await?;
+                }
+                if !config.labels.is_empty() {
+                    dataset = dataset.set_labels(tx, &config.labels).await?;
+                }
+                if config.partition_template.is_some() {
+                    dataset = dataset
+                        .set_partition_template(tx, config.partition_template.as_deref())
+                        .await?;
+                }
+
+                bucket_manager.register_dataset(&config).await?;
+                tx.on_rollback(async move { bucket_manager.delete_dataset(&config).await });
+
+                Ok(dataset)
+            })
+        })
+        .await;
+
+    match registered {
+        Ok(dataset) => resp.json(dataset).await,
+        Err(e) => {
+            log::error!(
+                "failed to register dataset '{}' from manager '{}': {}",
+                name,
+                mask(api_key),
+                e
+            );
+            error_message(resp, &e, format!("failed to register dataset '{}'", name)).await
         }
     }
 }
 
+// the most results a single page may have
+const MAX_PAGE_SIZE: i32 = 1000;
+
+// Pages are found by their cursor, which is the `next` token of the page before them. The start and
+// end of the range filter on when results were created, and results are sorted by the sort key and
+// order, e.g. ?sort=name&order=desc
 #[derive(Deserialize)]
 pub struct Pagination {
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
     count: Option<i32>,
     offset: Option<i32>,
+    cursor: Option<String>,
+    sort: Option<SortKey>,
+    order: Option<SortOrder>,
 }
 
-impl From<Pagination> for RangeParams {
-    fn from(p: Pagination) -> Self {
-        Self {
-            start: None,
-            end: None,
+impl TryFrom<&Pagination> for RangeParams {
+    type Error = DDError;
+
+    fn try_from(p: &Pagination) -> Result<Self, Self::Error> {
+        if matches!(p.count, Some(count) if !(1..=MAX_PAGE_SIZE).contains(&count)) {
+            return Err(DDError::InputValidation(format!(
+                "count must be between 1 and {}",
+                MAX_PAGE_SIZE
+            )));
+        }
+        if matches!(p.offset, Some(offset) if offset < 0) {
+            return Err(DDError::InputValidation(
+                "offset must not be negative".into(),
+            ));
+        }
+        if p.cursor.is_some() && p.offset.is_some() {
+            return Err(DDError::InputValidation(
+                "cursor and offset can't be used together".into(),
+            ));
+        }
+
+        Ok(Self {
+            start: p.start,
+            end: p.end,
             count: p.count,
             offset: p.offset,
-        }
+            cursor: p.cursor.as_ref().map(Cursor::decode).transpose()?,
+            sort: p.sort,
+            order: p.order,
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DatasetFilters {
+    classification: Option<Classification>,
+    manager: Option<String>,
+    prefix: Option<String>,
+    labels: Option<String>,
+}
+
+impl TryFrom<&DatasetFilters> for DatasetFilter {
+    type Error = DDError;
+
+    fn try_from(f: &DatasetFilters) -> Result<Self, Self::Error> {
+        Ok(Self {
+            classification: f.classification.clone(),
+            manager_email: f.manager.clone(),
+            name_prefix: f.prefix.clone(),
+            // labels are provided as a selector, e.g. ?labels=team:billing,tier:1
+            labels: f.labels.as_deref().map(parse_labels).transpose()?,
+        })
     }
 }
 
 pub async fn list_datasets(
     srv: Data<Server>,
     params: Query<Pagination>,
+    filters: Query<DatasetFilters>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let mut resp = HttpResponse::build(StatusCode::OK);
 
-    let mut range_params: Option<RangeParams> = None;
-    if params.count.is_some() || params.offset.is_some() {
-        range_params = Some(params.0.into());
-    }
+    let filter = match DatasetFilter::try_from(&*filters) {
+        Ok(filter) => filter,
+        Err(e) => return json_message(resp, StatusCode::BAD_REQUEST, e.to_string()).await,
+    };
+    let range_params = match RangeParams::try_from(&*params) {
+        Ok(range_params) => range_params,
+        Err(e) => return json_message(resp, StatusCode::BAD_REQUEST, e.to_string()).await,
+    };
 
-    let datasets = Dataset::list(&mut srv.db.clone(), range_params).await;
+    let datasets = Dataset::page_filtered(&mut srv.db.clone(), &filter, &range_params).await;
     if let Ok(datasets) = datasets {
-        resp.json(datasets).await
+        if unversioned(&req) {
+            resp.json(datasets.datasets).await
+        } else {
+            resp.json(datasets).await
+        }
     } else {
         let msg = "failed to list datasets";
         let err = datasets.err().expect("no datasets error specified");
         log::error!("{}: {}", msg, err);
 
         match err {
-            DDError::Sql(_) => json_message(resp, StatusCode::NOT_FOUND, "no datasets found").await,
-            _ => json_message(resp, StatusCode::INTERNAL_SERVER_ERROR, msg).await,
+            DDError::InputValidation(_) => {
+                json_message(resp, StatusCode::BAD_REQUEST, err.to_string()).await
+            }
+            _ => error_message(resp, &err, msg).await,
         }
     }
 }
@@ -460,39 +838,156 @@ pub async fn find_dataset(
         log::error!("{}: {}", msg, err);
 
         match err {
-            DDError::Sql(_) => {
-                json_message(
+            DDError::NotFound(_) => {
+                error_message(
                     resp,
-                    StatusCode::NOT_FOUND,
+                    &err,
                     format!("no dataset found with name '{}'", params.dataset_name),
                 )
                 .await
             }
-            _ => json_message(resp, StatusCode::INTERNAL_SERVER_ERROR, msg).await,
+            _ => error_message(resp, &err, msg).await,
         }
     }
 }
 
 #[derive(Deserialize)]
 pub struct SearchDatasets {
-    term: String,
+    term: Option<String>,
+    classification: Option<Classification>,
+    format: Option<Format>,
+    compression: Option<Compression>,
+    manager: Option<String>,
+    updated_after: Option<DateTime<Utc>>,
+    updated_before: Option<DateTime<Utc>>,
+    labels: Option<String>,
+    count: Option<i32>,
+    offset: Option<i32>,
+}
+
+// Searches return a single page of matches, which is the most a page may have unless a smaller
+// count is asked for.
+impl TryFrom<SearchDatasets> for SearchParams {
+    type Error = DDError;
+
+    fn try_from(s: SearchDatasets) -> Result<Self, Self::Error> {
+        if matches!(s.count, Some(count) if count < 0) {
+            return Err(DDError::InputValidation(
+                "count must not be negative".into(),
+            ));
+        }
+        if matches!(s.offset, Some(offset) if offset < 0) {
+            return Err(DDError::InputValidation(
+                "offset must not be negative".into(),
+            ));
+        }
+
+        Ok(Self {
+            term: s.term,
+            classification: s.classification,
+            format: s.format,
+            compression: s.compression,
+            manager_email: s.manager,
+            updated_after: s.updated_after,
+            updated_before: s.updated_before,
+            labels: s.labels.as_deref().map(parse_labels).transpose()?,
+            offset: s.offset,
+            count: Some(
+                s.count
+                    .map_or(MAX_PAGE_SIZE, |count| count.min(MAX_PAGE_SIZE)),
+            ),
+        })
+    }
 }
 
 pub async fn search_datasets(
     srv: Data<Server>,
     params: Query<SearchDatasets>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     let mut resp = HttpResponse::build(StatusCode::OK);
 
-    let matches = Dataset::search(&mut srv.db.clone(), &params.term).await;
-    if let Ok(datasets) = matches {
-        resp.json(datasets).await
+    let params = match SearchParams::try_from(params.into_inner()) {
+        Ok(params) => params,
+        Err(e) => return json_message(resp, StatusCode::BAD_REQUEST, e.to_string()).await,
+    };
+    let matches = Dataset::search(&mut srv.db.clone(), &params).await;
+    if let Ok(results) = matches {
+        if unversioned(&req) {
+            resp.json(results.datasets).await
+        } else {
+            resp.json(results).await
+        }
     } else {
         let msg = "dataset search failure";
         let err = matches.err().expect("no dataset search error specified");
         log::error!("{}: {}", msg, err);
 
-        json_message(resp, StatusCode::INTERNAL_SERVER_ERROR, msg).await
+        error_message(resp, &err, msg).await
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FindFields {
+    name: Option<String>,
+    #[serde(rename = "type")]
+    field_type: Option<String>,
+    versions: Option<bool>,
+}
+
+impl From<FindFields> for FieldSearch {
+    fn from(f: FindFields) -> Self {
+        Self {
+            name: f.name,
+            field_type: f.field_type,
+            all_versions: f.versions.unwrap_or(false),
+        }
+    }
+}
+
+pub async fn find_fields(
+    srv: Data<Server>,
+    params: Query<FindFields>,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    if params.name.is_none() && params.field_type.is_none() {
+        return json_message(
+            resp,
+            StatusCode::BAD_REQUEST,
+            "a field name pattern and/or type is required",
+        )
+        .await;
+    }
+
+    let search = FieldSearch::from(params.into_inner());
+    match Dataset::find_fields(&mut srv.db.clone(), &search).await {
+        Ok(fields) => resp.json(fields).await,
+        Err(e) => {
+            let msg = "schema field search failure";
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FindPiiFields {
+    sensitivity: Option<Sensitivity>,
+}
+
+pub async fn list_pii_fields(
+    srv: Data<Server>,
+    params: Query<FindPiiFields>,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+
+    match Dataset::list_pii_fields(&mut srv.db.clone(), params.sensitivity).await {
+        Ok(fields) => resp.json(fields).await,
+        Err(e) => {
+            let msg = "failed to list PII fields";
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
     }
 }
 
@@ -509,22 +1004,1404 @@ pub async fn list_meta(srv: Data<Server>) -> Result<HttpResponse, Error> {
         let err = attrs.err().expect("failed to list attributes");
         log::error!("{}: {}", msg, err);
 
-        json_message(resp, StatusCode::INTERNAL_SERVER_ERROR, msg).await
+        error_message(resp, &err, msg).await
     }
 }
 
-fn json_message(
-    mut builder: HttpResponseBuilder,
-    status: StatusCode,
-    message: impl AsRef<str>,
-) -> Response {
-    builder.status(status).json(serde_json::json!({
-        "code": status.as_u16(),
-        "status": status.canonical_reason(),
-        "message": message.as_ref()
-    }))
+#[derive(Deserialize)]
+pub struct UpdateDataset {
+    description: Option<String>,
+    schema: Option<DatasetSchema>,
+    // an empty template removes the dataset's partition template
+    partition_template: Option<String>,
 }
 
-fn trim_api_key(bearer: &str) -> String {
-    bearer.replace("Bearer ", "").trim().into()
+pub async fn update_dataset(
+    srv: Data<Server>,
+    params: Path<FindDataset>,
+    update: Json<UpdateDataset>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    let manager = match request_manager(&srv, &req).await {
+        Ok(manager) => manager,
+        Err(resp) => return Ok(resp),
+    };
+    let dataset = match find_dataset_by_name(&srv, &params.dataset_name).await {
+        Ok(dataset) => dataset,
+        Err(resp) => return Ok(resp),
+    };
+    if let Err(resp) = authorize_maintainer(&srv, &dataset, &manager).await {
+        return Ok(resp);
+    }
+
+    let mut config = DatasetConfig::from(&dataset);
+    if let Some(description) = &update.description {
+        config.description = description.clone();
+    }
+    if let Some(schema) = &update.schema {
+        config.schema = schema.clone();
+    }
+    if let Some(template) = &update.partition_template {
+        config.partition_template = Some(template.clone()).filter(|t| !t.is_empty());
+    }
+    if let Err(e) =
+        validate_sensitivity(&config.schema, &config.classification, &config.sensitivity)
+            .and_then(|_| validate_partition_template(&config.partition_template))
+    {
+        return json_message(resp, StatusCode::BAD_REQUEST, e.to_string()).await;
+    }
+
+    // update the dataset and its partition template in the database, then upload its new
+    // configuration to GCS. These are committed together, and the previous configuration is
+    // uploaded again if committing fails, so that GCS stays consistent with the database.
+    let previous = DatasetConfig::from(&dataset);
+    let set_template = update.partition_template.is_some();
+    let bucket_manager = srv.bucket_manager.clone();
+    let updated = srv
+        .db
+        .with_actor(Actor::from(&manager))
+        .transaction(move |tx| {
+            Box::pin(async move {
+                let mut updated = dataset
+                    .update(tx, &config.description, config.schema.clone())
+                    .await?;
+                if set_template {
+                    updated = updated
+                        .set_partition_template(tx, config.partition_template.as_deref())
+                        .await?;
+                }
+
+                bucket_manager.register_dataset(&config).await?;
+                tx.on_rollback(async move { bucket_manager.register_dataset(&previous).await });
+
+                Ok(updated)
+            })
+        })
+        .await;
+
+    match updated {
+        Ok(dataset) => resp.json(dataset).await,
+        Err(e) => {
+            let msg = format!("failed to update dataset '{}'", params.dataset_name);
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+fn validate_partition_template(template: &Option<String>) -> Result<(), DDError> {
+    match template {
+        Some(template) => PartitionTemplate::parse(template).map(|_| ()),
+        None => Ok(()),
+    }
+}
+
+pub async fn update_sensitivity(
+    srv: Data<Server>,
+    params: Path<FindDataset>,
+    sensitivity: Json<DatasetSensitivity>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    let manager = match request_manager(&srv, &req).await {
+        Ok(manager) => manager,
+        Err(resp) => return Ok(resp),
+    };
+    let dataset = match find_dataset_by_name(&srv, &params.dataset_name).await {
+        Ok(dataset) => dataset,
+        Err(resp) => return Ok(resp),
+    };
+    if let Err(resp) = authorize_maintainer(&srv, &dataset, &manager).await {
+        return Ok(resp);
+    }
+    if let Err(e) = validate_sensitivity(&dataset.schema, &dataset.classification, &sensitivity) {
+        return json_message(resp, StatusCode::BAD_REQUEST, e.to_string()).await;
+    }
+
+    // update the field sensitivity in the database, then upload the new configuration to GCS,
+    // uploading the previous configuration again if committing fails
+    let previous = DatasetConfig::from(&dataset);
+    let mut config = previous.clone();
+    config.sensitivity = sensitivity.into_inner();
+    let bucket_manager = srv.bucket_manager.clone();
+    let updated = srv
+        .db
+        .with_actor(Actor::from(&manager))
+        .transaction(move |tx| {
+            Box::pin(async move {
+                let updated = dataset.set_sensitivity(tx, &config.sensitivity).await?;
+
+                bucket_manager.register_dataset(&config).await?;
+                tx.on_rollback(async move { bucket_manager.register_dataset(&previous).await });
+
+                Ok(updated)
+            })
+        })
+        .await;
+
+    match updated {
+        Ok(dataset) => resp.json(dataset).await,
+        Err(e) => {
+            let msg = format!(
+                "failed to update field sensitivity of dataset '{}'",
+                params.dataset_name
+            );
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+pub async fn update_labels(
+    srv: Data<Server>,
+    params: Path<FindDataset>,
+    labels: Json<DatasetLabels>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    if let Err(e) = validate_labels(&labels) {
+        return json_message(resp, StatusCode::BAD_REQUEST, e.to_string()).await;
+    }
+
+    let manager = match request_manager(&srv, &req).await {
+        Ok(manager) => manager,
+        Err(resp) => return Ok(resp),
+    };
+    let dataset = match find_dataset_by_name(&srv, &params.dataset_name).await {
+        Ok(dataset) => dataset,
+        Err(resp) => return Ok(resp),
+    };
+    if let Err(resp) = authorize_owner(&dataset, &manager) {
+        return Ok(resp);
+    }
+
+    // update the labels in the database, then upload the new configuration to GCS, uploading the
+    // previous configuration again if committing fails
+    let previous = DatasetConfig::from(&dataset);
+    let mut config = previous.clone();
+    config.labels = labels.into_inner();
+    let bucket_manager = srv.bucket_manager.clone();
+    let updated = srv
+        .db
+        .with_actor(Actor::from(&manager))
+        .transaction(move |tx| {
+            Box::pin(async move {
+                let updated = dataset.set_labels(tx, &config.labels).await?;
+
+                bucket_manager.register_dataset(&config).await?;
+                tx.on_rollback(async move { bucket_manager.register_dataset(&previous).await });
+
+                Ok(updated)
+            })
+        })
+        .await;
+
+    match updated {
+        Ok(dataset) => resp.json(dataset).await,
+        Err(e) => {
+            let msg = format!(
+                "failed to update labels of dataset '{}'",
+                params.dataset_name
+            );
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+pub async fn restore_dataset(
+    srv: Data<Server>,
+    params: Path<FindDataset>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    let manager = match request_manager(&srv, &req).await {
+        Ok(manager) => manager,
+        Err(resp) => return Ok(resp),
+    };
+    let dataset = match Dataset::find_deleted(&mut srv.db.clone(), &params.dataset_name).await {
+        Ok(dataset) => dataset,
+        Err(e) => {
+            log::error!(
+                "failed to find deleted dataset with name '{}': {}",
+                params.dataset_name,
+                e
+            );
+            return match e {
+                DDError::NotFound(_) => {
+                    error_message(
+                        resp,
+                        &e,
+                        format!(
+                            "no deleted dataset found with name '{}'",
+                            params.dataset_name
+                        ),
+                    )
+                    .await
+                }
+                _ => {
+                    error_message(
+                        resp,
+                        &e,
+                        format!("failed to find deleted dataset '{}'", params.dataset_name),
+                    )
+                    .await
+                }
+            };
+        }
+    };
+    if let Err(resp) = authorize_maintainer(&srv, &dataset, &manager).await {
+        return Ok(resp);
+    }
+
+    // the dataset configuration was removed from GCS when the dataset was deleted, so it's uploaded
+    // again once the dataset is restored, and deleted again if committing the restore fails
+    let bucket_manager = srv.bucket_manager.clone();
+    let restored = srv
+        .db
+        .with_actor(Actor::from(&manager))
+        .transaction(move |tx| {
+            Box::pin(async move {
+                let config = DatasetConfig::from(&dataset);
+                let restored = dataset.restore(tx).await?;
+
+                bucket_manager.register_dataset(&config).await?;
+                tx.on_rollback(async move { bucket_manager.delete_dataset(&config).await });
+
+                Ok(restored)
+            })
+        })
+        .await;
+
+    match restored {
+        Ok(dataset) => resp.json(dataset).await,
+        Err(e) => {
+            let msg = format!("failed to restore dataset '{}'", params.dataset_name);
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+pub async fn restore_partition(
+    srv: Data<Server>,
+    params: Path<FindPartition>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    let manager = match request_manager(&srv, &req).await {
+        Ok(manager) => manager,
+        Err(resp) => return Ok(resp),
+    };
+    let dataset = match find_dataset_by_name(&srv, &params.dataset_name).await {
+        Ok(dataset) => dataset,
+        Err(resp) => return Ok(resp),
+    };
+    if let Err(resp) = authorize_maintainer(&srv, &dataset, &manager).await {
+        return Ok(resp);
+    }
+
+    match dataset
+        .restore_partition(
+            &mut srv.db.with_actor(Actor::from(&manager)),
+            &params.partition_name,
+        )
+        .await
+    {
+        Ok(partition) => resp.json(partition).await,
+        Err(e) => {
+            log::error!(
+                "failed to restore partition '{}' for dataset '{}': {}",
+                params.partition_name,
+                params.dataset_name,
+                e
+            );
+
+            error_message(
+                resp,
+                &e,
+                format!(
+                    "failed to restore partition '{}' for dataset '{}'",
+                    params.partition_name, params.dataset_name
+                ),
+            )
+            .await
+        }
+    }
+}
+
+pub async fn list_maintainers(
+    srv: Data<Server>,
+    params: Path<FindDataset>,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    let dataset = match find_dataset_by_name(&srv, &params.dataset_name).await {
+        Ok(dataset) => dataset,
+        Err(resp) => return Ok(resp),
+    };
+
+    match dataset.maintainers(&mut srv.db.clone()).await {
+        Ok(maintainers) => resp.json(maintainers).await,
+        Err(e) => {
+            let msg = format!(
+                "failed to list maintainers for dataset '{}'",
+                params.dataset_name
+            );
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ManagerEmail {
+    email: String,
+}
+
+pub async fn add_maintainer(
+    srv: Data<Server>,
+    params: Path<FindDataset>,
+    maintainer: Json<ManagerEmail>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    let manager = match request_manager(&srv, &req).await {
+        Ok(manager) => manager,
+        Err(resp) => return Ok(resp),
+    };
+    let dataset = match find_dataset_by_name(&srv, &params.dataset_name).await {
+        Ok(dataset) => dataset,
+        Err(resp) => return Ok(resp),
+    };
+    if let Err(resp) = authorize_owner(&dataset, &manager) {
+        return Ok(resp);
+    }
+    let maintainer = match find_manager_by_email(&srv, &maintainer.email).await {
+        Ok(maintainer) => maintainer,
+        Err(resp) => return Ok(resp),
+    };
+
+    match dataset
+        .add_maintainer(&mut srv.db.with_actor(Actor::from(&manager)), &maintainer)
+        .await
+    {
+        Ok(maintainer) => resp.json(maintainer).await,
+        Err(e) => {
+            let msg = format!(
+                "failed to add maintainer to dataset '{}'",
+                params.dataset_name
+            );
+            log::error!("{}: {}", msg, e);
+
+            match e {
+                DDError::InputValidation(msg) => {
+                    json_message(resp, StatusCode::BAD_REQUEST, msg).await
+                }
+                _ => error_message(resp, &e, msg).await,
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RemoveMaintainer {
+    dataset_name: String,
+    manager_id: i32,
+}
+
+pub async fn remove_maintainer(
+    srv: Data<Server>,
+    params: Path<RemoveMaintainer>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let resp = HttpResponse::build(StatusCode::OK);
+    let manager = match request_manager(&srv, &req).await {
+        Ok(manager) => manager,
+        Err(resp) => return Ok(resp),
+    };
+    let dataset = match find_dataset_by_name(&srv, &params.dataset_name).await {
+        Ok(dataset) => dataset,
+        Err(resp) => return Ok(resp),
+    };
+
+    // maintainers may remove themselves, otherwise only the owner (or an admin) may remove them
+    if manager.id != params.manager_id {
+        if let Err(resp) = authorize_owner(&dataset, &manager) {
+            return Ok(resp);
+        }
+    }
+
+    match dataset
+        .remove_maintainer(
+            &mut srv.db.with_actor(Actor::from(&manager)),
+            params.manager_id,
+        )
+        .await
+    {
+        Ok(_) => {
+            json_message(
+                resp,
+                StatusCode::OK,
+                format!(
+                    "removed maintainer '{}' from dataset '{}'",
+                    params.manager_id, params.dataset_name
+                ),
+            )
+            .await
+        }
+        Err(e) => {
+            let msg = format!(
+                "failed to remove maintainer from dataset '{}'",
+                params.dataset_name
+            );
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+pub async fn list_read_grants(
+    srv: Data<Server>,
+    params: Path<FindDataset>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    let manager = match request_manager(&srv, &req).await {
+        Ok(manager) => manager,
+        Err(resp) => return Ok(resp),
+    };
+    let dataset = match find_dataset_by_name(&srv, &params.dataset_name).await {
+        Ok(dataset) => dataset,
+        Err(resp) => return Ok(resp),
+    };
+    if let Err(resp) = authorize_maintainer(&srv, &dataset, &manager).await {
+        return Ok(resp);
+    }
+
+    match dataset.read_grants(&mut srv.db.clone()).await {
+        Ok(grants) => resp.json(grants).await,
+        Err(e) => {
+            let msg = format!(
+                "failed to list read grants for dataset '{}'",
+                params.dataset_name
+            );
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+pub async fn add_read_grant(
+    srv: Data<Server>,
+    params: Path<FindDataset>,
+    grantee: Json<ManagerEmail>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    let manager = match request_manager(&srv, &req).await {
+        Ok(manager) => manager,
+        Err(resp) => return Ok(resp),
+    };
+    let dataset = match find_dataset_by_name(&srv, &params.dataset_name).await {
+        Ok(dataset) => dataset,
+        Err(resp) => return Ok(resp),
+    };
+    if let Err(resp) = authorize_owner(&dataset, &manager) {
+        return Ok(resp);
+    }
+    let grantee = match find_manager_by_email(&srv, &grantee.email).await {
+        Ok(grantee) => grantee,
+        Err(resp) => return Ok(resp),
+    };
+
+    match dataset
+        .grant_read(&mut srv.db.with_actor(Actor::from(&manager)), &grantee)
+        .await
+    {
+        Ok(grant) => resp.json(grant).await,
+        Err(e) => {
+            let msg = format!(
+                "failed to grant read access to dataset '{}'",
+                params.dataset_name
+            );
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+pub async fn remove_read_grant(
+    srv: Data<Server>,
+    params: Path<RemoveMaintainer>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let resp = HttpResponse::build(StatusCode::OK);
+    let manager = match request_manager(&srv, &req).await {
+        Ok(manager) => manager,
+        Err(resp) => return Ok(resp),
+    };
+    let dataset = match find_dataset_by_name(&srv, &params.dataset_name).await {
+        Ok(dataset) => dataset,
+        Err(resp) => return Ok(resp),
+    };
+
+    // grantees may give up their own access, otherwise only the owner (or an admin) may revoke it
+    if manager.id != params.manager_id {
+        if let Err(resp) = authorize_owner(&dataset, &manager) {
+            return Ok(resp);
+        }
+    }
+
+    match dataset
+        .revoke_read(
+            &mut srv.db.with_actor(Actor::from(&manager)),
+            params.manager_id,
+        )
+        .await
+    {
+        Ok(_) => {
+            json_message(
+                resp,
+                StatusCode::OK,
+                format!(
+                    "revoked read access of manager '{}' to dataset '{}'",
+                    params.manager_id, params.dataset_name
+                ),
+            )
+            .await
+        }
+        Err(e) => {
+            let msg = format!(
+                "failed to revoke read access to dataset '{}'",
+                params.dataset_name
+            );
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+pub async fn request_transfer(
+    srv: Data<Server>,
+    params: Path<FindDataset>,
+    recipient: Json<ManagerEmail>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    let manager = match request_manager(&srv, &req).await {
+        Ok(manager) => manager,
+        Err(resp) => return Ok(resp),
+    };
+    let dataset = match find_dataset_by_name(&srv, &params.dataset_name).await {
+        Ok(dataset) => dataset,
+        Err(resp) => return Ok(resp),
+    };
+    if let Err(resp) = authorize_owner(&dataset, &manager) {
+        return Ok(resp);
+    }
+    let recipient = match find_manager_by_email(&srv, &recipient.email).await {
+        Ok(recipient) => recipient,
+        Err(resp) => return Ok(resp),
+    };
+    if recipient.disabled {
+        return json_message(
+            resp,
+            StatusCode::BAD_REQUEST,
+            format!(
+                "cannot transfer dataset to disabled manager '{}'",
+                recipient.email
+            ),
+        )
+        .await;
+    }
+
+    match dataset
+        .request_transfer(&mut srv.db.with_actor(Actor::from(&manager)), &recipient)
+        .await
+    {
+        Ok(transfer) => resp.json(transfer).await,
+        Err(e) => {
+            let msg = format!(
+                "failed to request transfer of dataset '{}'",
+                params.dataset_name
+            );
+            log::error!("{}: {}", msg, e);
+
+            match e {
+                DDError::InputValidation(msg) => {
+                    json_message(resp, StatusCode::BAD_REQUEST, msg).await
+                }
+                _ => error_message(resp, &e, msg).await,
+            }
+        }
+    }
+}
+
+pub async fn find_dataset_transfer(
+    srv: Data<Server>,
+    params: Path<FindDataset>,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    let dataset = match find_dataset_by_name(&srv, &params.dataset_name).await {
+        Ok(dataset) => dataset,
+        Err(resp) => return Ok(resp),
+    };
+
+    match dataset.pending_transfer(&mut srv.db.clone()).await {
+        Ok(Some(transfer)) => resp.json(transfer).await,
+        Ok(None) => {
+            json_message(
+                resp,
+                StatusCode::NOT_FOUND,
+                format!(
+                    "no pending transfer found for dataset '{}'",
+                    params.dataset_name
+                ),
+            )
+            .await
+        }
+        Err(e) => {
+            let msg = format!(
+                "failed to find pending transfer for dataset '{}'",
+                params.dataset_name
+            );
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+pub async fn list_transfers(srv: Data<Server>, req: HttpRequest) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    let manager = match request_manager(&srv, &req).await {
+        Ok(manager) => manager,
+        Err(resp) => return Ok(resp),
+    };
+
+    match manager.transfers(&mut srv.db.clone()).await {
+        Ok(transfers) => resp.json(transfers).await,
+        Err(e) => {
+            let msg = "failed to list dataset transfers";
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TransferId {
+    transfer_id: i32,
+}
+
+pub async fn accept_transfer(
+    srv: Data<Server>,
+    params: Path<TransferId>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    let manager = match request_manager(&srv, &req).await {
+        Ok(manager) => manager,
+        Err(resp) => return Ok(resp),
+    };
+    let transfer = match find_transfer(&srv, params.transfer_id).await {
+        Ok(transfer) => transfer,
+        Err(resp) => return Ok(resp),
+    };
+    if transfer.to_manager_id != manager.id {
+        return json_message(
+            resp,
+            StatusCode::FORBIDDEN,
+            "only the recipient may accept a dataset transfer",
+        )
+        .await;
+    }
+
+    match transfer
+        .accept(&mut srv.db.with_actor(Actor::from(&manager)))
+        .await
+    {
+        Ok(dataset) => resp.json(dataset).await,
+        Err(e) => {
+            let msg = format!("failed to accept transfer '{}'", params.transfer_id);
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+pub async fn cancel_transfer(
+    srv: Data<Server>,
+    params: Path<TransferId>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let resp = HttpResponse::build(StatusCode::OK);
+    let manager = match request_manager(&srv, &req).await {
+        Ok(manager) => manager,
+        Err(resp) => return Ok(resp),
+    };
+    let transfer = match find_transfer(&srv, params.transfer_id).await {
+        Ok(transfer) => transfer,
+        Err(resp) => return Ok(resp),
+    };
+
+    // the sender may cancel the transfer, and the recipient may decline it
+    if !manager.admin
+        && transfer.from_manager_id != manager.id
+        && transfer.to_manager_id != manager.id
+    {
+        return json_message(
+            resp,
+            StatusCode::FORBIDDEN,
+            "only the sender or recipient may cancel a dataset transfer",
+        )
+        .await;
+    }
+
+    match transfer
+        .cancel(&mut srv.db.with_actor(Actor::from(&manager)))
+        .await
+    {
+        Ok(_) => {
+            json_message(
+                resp,
+                StatusCode::OK,
+                format!("cancelled transfer '{}'", params.transfer_id),
+            )
+            .await
+        }
+        Err(e) => {
+            let msg = format!("failed to cancel transfer '{}'", params.transfer_id);
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+/// AdminManager is the view of a manager returned to admins, which omits the manager's API key.
+#[derive(Serialize)]
+pub struct AdminManager {
+    id: i32,
+    email: String,
+    admin: bool,
+    disabled: bool,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<Manager> for AdminManager {
+    fn from(m: Manager) -> Self {
+        Self {
+            id: m.id,
+            email: m.email,
+            admin: m.admin,
+            disabled: m.disabled,
+            created_at: m.created_at,
+            updated_at: m.updated_at,
+        }
+    }
+}
+
+pub async fn list_managers(srv: Data<Server>, req: HttpRequest) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    if let Err(resp) = request_admin(&srv, &req).await {
+        return Ok(resp);
+    }
+
+    match Manager::list(&mut srv.db.clone()).await {
+        Ok(managers) => {
+            resp.json(
+                managers
+                    .into_iter()
+                    .map(AdminManager::from)
+                    .collect::<Vec<AdminManager>>(),
+            )
+            .await
+        }
+        Err(e) => {
+            let msg = "failed to list managers";
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ManagerId {
+    manager_id: i32,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateManager {
+    admin: Option<bool>,
+    disabled: Option<bool>,
+}
+
+pub async fn update_manager(
+    srv: Data<Server>,
+    params: Path<ManagerId>,
+    update: Json<UpdateManager>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    let admin = match request_admin(&srv, &req).await {
+        Ok(admin) => admin,
+        Err(resp) => return Ok(resp),
+    };
+
+    // prevent an admin from locking themselves out of the admin API
+    if admin.id == params.manager_id
+        && (update.admin == Some(false) || update.disabled == Some(true))
+    {
+        return json_message(
+            resp,
+            StatusCode::BAD_REQUEST,
+            "admins cannot demote or disable themselves",
+        )
+        .await;
+    }
+
+    let mut manager = match find_manager_by_id(&srv, params.manager_id).await {
+        Ok(manager) => manager,
+        Err(resp) => return Ok(resp),
+    };
+
+    let mut db = srv.db.with_actor(Actor::from(&admin));
+    if let Some(is_admin) = update.admin {
+        manager = match manager.set_admin(&mut db, is_admin).await {
+            Ok(manager) => manager,
+            Err(e) => {
+                let msg = format!("failed to update admin for manager '{}'", params.manager_id);
+                log::error!("{}: {}", msg, e);
+                return error_message(resp, &e, msg).await;
+            }
+        };
+    }
+
+    if let Some(disabled) = update.disabled {
+        manager = match manager.set_disabled(&mut db, disabled).await {
+            Ok(manager) => manager,
+            Err(e) => {
+                let msg = format!(
+                    "failed to update disabled for manager '{}'",
+                    params.manager_id
+                );
+                log::error!("{}: {}", msg, e);
+                return error_message(resp, &e, msg).await;
+            }
+        };
+    }
+
+    resp.json(AdminManager::from(manager)).await
+}
+
+#[derive(Deserialize)]
+pub struct TransferDatasets {
+    to_manager_id: i32,
+}
+
+pub async fn transfer_manager_datasets(
+    srv: Data<Server>,
+    params: Path<ManagerId>,
+    transfer: Json<TransferDatasets>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    let admin = match request_admin(&srv, &req).await {
+        Ok(admin) => admin,
+        Err(resp) => return Ok(resp),
+    };
+
+    let (from, to) =
+        match find_transfer_managers(&srv, params.manager_id, transfer.to_manager_id).await {
+            Ok(managers) => managers,
+            Err(resp) => return Ok(resp),
+        };
+
+    match from
+        .transfer_datasets(&mut srv.db.with_actor(Actor::from(&admin)), &to)
+        .await
+    {
+        Ok(transferred) => {
+            resp.json(serde_json::json!({ "transferred": transferred }))
+                .await
+        }
+        Err(e) => {
+            let msg = format!(
+                "failed to transfer datasets from manager '{}' to manager '{}'",
+                from.id, to.id
+            );
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DeleteManager {
+    successor_id: i32,
+}
+
+pub async fn delete_manager(
+    srv: Data<Server>,
+    params: Path<ManagerId>,
+    query: Query<DeleteManager>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let resp = HttpResponse::build(StatusCode::OK);
+    let admin = match request_admin(&srv, &req).await {
+        Ok(admin) => admin,
+        Err(resp) => return Ok(resp),
+    };
+
+    if admin.id == params.manager_id {
+        return json_message(
+            resp,
+            StatusCode::BAD_REQUEST,
+            "admins cannot delete themselves",
+        )
+        .await;
+    }
+
+    let (manager, successor) =
+        match find_transfer_managers(&srv, params.manager_id, query.successor_id).await {
+            Ok(managers) => managers,
+            Err(resp) => return Ok(resp),
+        };
+
+    let manager_id = manager.id;
+    match manager
+        .delete(&mut srv.db.with_actor(Actor::from(&admin)), &successor)
+        .await
+    {
+        Ok(_) => {
+            json_message(
+                resp,
+                StatusCode::OK,
+                format!("deleted manager '{}'", manager_id),
+            )
+            .await
+        }
+        Err(e) => {
+            let msg = format!("failed to delete manager '{}'", manager_id);
+            log::error!("{}: {}", msg, e);
+
+            match e {
+                DDError::InputValidation(msg) => {
+                    json_message(resp, StatusCode::BAD_REQUEST, msg).await
+                }
+                _ => error_message(resp, &e, msg).await,
+            }
+        }
+    }
+}
+
+pub async fn list_audit_events(
+    srv: Data<Server>,
+    filter: Query<AuditFilter>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    if let Err(resp) = request_admin(&srv, &req).await {
+        return Ok(resp);
+    }
+
+    match AuditEvent::list(&mut srv.db.clone(), &filter).await {
+        Ok(events) => resp.json(events).await,
+        Err(e) => {
+            let msg = "failed to list audit events";
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+pub async fn list_access_denials(
+    srv: Data<Server>,
+    filter: Query<AccessFilter>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let mut resp = HttpResponse::build(StatusCode::OK);
+    if let Err(resp) = request_admin(&srv, &req).await {
+        return Ok(resp);
+    }
+
+    match AccessDenial::list(&mut srv.db.clone(), &filter).await {
+        Ok(denials) => resp.json(denials).await,
+        Err(e) => {
+            let msg = "failed to list access denials";
+            log::error!("{}: {}", msg, e);
+            error_message(resp, &e, msg).await
+        }
+    }
+}
+
+async fn find_dataset_by_name(srv: &Data<Server>, name: &str) -> Result<Dataset, Response> {
+    let resp = HttpResponse::build(StatusCode::OK);
+    Dataset::find(&mut srv.db.clone(), name).await.map_err(|e| {
+        log::error!("failed to find dataset with name '{}': {}", name, e);
+        match e {
+            DDError::NotFound(_) => {
+                error_message(resp, &e, format!("no dataset found with name '{}'", name))
+            }
+            _ => error_message(resp, &e, format!("failed to find dataset '{}'", name)),
+        }
+    })
+}
+
+async fn find_transfer(srv: &Data<Server>, transfer_id: i32) -> Result<DatasetTransfer, Response> {
+    let resp = HttpResponse::build(StatusCode::OK);
+    DatasetTransfer::find(&mut srv.db.clone(), transfer_id)
+        .await
+        .map_err(|e| {
+            log::error!("failed to find transfer with id '{}': {}", transfer_id, e);
+            match e {
+                DDError::NotFound(_) => error_message(
+                    resp,
+                    &e,
+                    format!("no transfer found with id '{}'", transfer_id),
+                ),
+                _ => error_message(
+                    resp,
+                    &e,
+                    format!("failed to find transfer with id '{}'", transfer_id),
+                ),
+            }
+        })
+}
+
+async fn find_manager_by_email(srv: &Data<Server>, email: &str) -> Result<Manager, Response> {
+    let resp = HttpResponse::build(StatusCode::OK);
+    Manager::find_by_email(&mut srv.db.clone(), email)
+        .await
+        .map_err(|e| {
+            log::error!("failed to find manager with email '{}': {}", email, e);
+            match e {
+                DDError::NotFound(_) => {
+                    error_message(resp, &e, format!("no manager found with email '{}'", email))
+                }
+                _ => error_message(
+                    resp,
+                    &e,
+                    format!("failed to find manager with email '{}'", email),
+                ),
+            }
+        })
+}
+
+// Only the owner of a dataset (or an admin) may manage its maintainers and transfer ownership.
+fn authorize_owner(dataset: &Dataset, manager: &Manager) -> Result<(), Response> {
+    if manager.admin || manager.id == dataset.manager_id {
+        return Ok(());
+    }
+
+    log::error!(
+        "manager '{}' is not the owner of dataset '{}'",
+        manager.email,
+        dataset.name
+    );
+    Err(json_message(
+        HttpResponse::build(StatusCode::OK),
+        StatusCode::FORBIDDEN,
+        format!("only the owner may manage dataset '{}'", dataset.name),
+    ))
+}
+
+// The owner of a dataset, its co-maintainers, and admins may update the dataset's metadata.
+async fn authorize_maintainer(
+    srv: &Data<Server>,
+    dataset: &Dataset,
+    manager: &Manager,
+) -> Result<(), Response> {
+    let resp = HttpResponse::build(StatusCode::OK);
+    match dataset.is_maintained_by(&mut srv.db.clone(), manager).await {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            log::error!(
+                "manager '{}' is not a maintainer of dataset '{}'",
+                manager.email,
+                dataset.name
+            );
+            Err(json_message(
+                resp,
+                StatusCode::FORBIDDEN,
+                format!("only maintainers may update dataset '{}'", dataset.name),
+            ))
+        }
+        Err(e) => {
+            let msg = format!("failed to list maintainers for dataset '{}'", dataset.name);
+            log::error!("{}: {}", msg, e);
+            Err(error_message(resp, &e, msg))
+        }
+    }
+}
+
+// Finds both managers involved in a dataset ownership transfer, validating that the recipient is
+// a different, enabled manager.
+async fn find_transfer_managers(
+    srv: &Data<Server>,
+    from_id: i32,
+    to_id: i32,
+) -> Result<(Manager, Manager), Response> {
+    let resp = HttpResponse::build(StatusCode::OK);
+    if from_id == to_id {
+        return Err(json_message(
+            resp,
+            StatusCode::BAD_REQUEST,
+            "datasets must be transferred to a different manager",
+        ));
+    }
+
+    let from = find_manager_by_id(srv, from_id).await?;
+    let to = find_manager_by_id(srv, to_id).await?;
+    if to.disabled {
+        return Err(json_message(
+            resp,
+            StatusCode::BAD_REQUEST,
+            format!("cannot transfer datasets to disabled manager '{}'", to.id),
+        ));
+    }
+
+    Ok((from, to))
+}
+
+async fn find_manager_by_id(srv: &Data<Server>, manager_id: i32) -> Result<Manager, Response> {
+    let resp = HttpResponse::build(StatusCode::OK);
+    Manager::find_by_id(&mut srv.db.clone(), manager_id)
+        .await
+        .map_err(|e| {
+            log::error!("failed to find manager with id '{}': {}", manager_id, e);
+            match e {
+                DDError::NotFound(_) => error_message(
+                    resp,
+                    &e,
+                    format!("no manager found with id '{}'", manager_id),
+                ),
+                _ => error_message(
+                    resp,
+                    &e,
+                    format!("failed to find manager with id '{}'", manager_id),
+                ),
+            }
+        })
+}
+
+// Checks that the requester may read the partitions of a dataset, based on its classification (see
+// `Dataset::is_readable_by`). Denied reads are recorded in the access log, and the error contains
+// the response to be returned to the client.
+async fn authorize_read(
+    srv: &Data<Server>,
+    req: &HttpRequest,
+    dataset: &Dataset,
+    action: &str,
+    partition_name: Option<&str>,
+) -> Result<(), Response> {
+    let resp = HttpResponse::build(StatusCode::OK);
+    if dataset.classification == Classification::Public {
+        return Ok(());
+    }
+
+    // an invalid or unknown API key is treated the same as a missing one, but a failure to look
+    // it up is not
+    let manager = match api_key(req) {
+        Some(api_key) => match Manager::find(&mut srv.db.clone(), api_key).await {
+            Ok(manager) => Some(manager),
+            Err(DDError::NotFound(_)) | Err(DDError::Auth(_)) => None,
+            Err(e) => {
+                log::error!("failed to find manager for API key: {}", e);
+                return Err(error_message(resp, &e, "failed to authorize request"));
+            }
+        },
+        None => None,
+    };
+    let (status, reason) = match dataset
+        .is_readable_by(&mut srv.db.clone(), manager.as_ref())
+        .await
+    {
+        Ok(true) => return Ok(()),
+        Ok(false) if manager.is_none() => (
+            StatusCode::UNAUTHORIZED,
+            format!(
+                "a valid API key is required to read {} datasets",
+                dataset.classification
+            ),
+        ),
+        Ok(false) => (
+            StatusCode::FORBIDDEN,
+            format!(
+                "read access must be granted to read {} datasets",
+                dataset.classification
+            ),
+        ),
+        Err(e) => {
+            log::error!(
+                "failed to authorize read of dataset '{}': {}",
+                dataset.name,
+                e
+            );
+            return Err(error_message(
+                resp,
+                &e,
+                format!("failed to authorize read of dataset '{}'", dataset.name),
+            ));
+        }
+    };
+
+    log::warn!(
+        "denied '{}' of dataset '{}': {}",
+        action,
+        dataset.name,
+        reason
+    );
+    let denial = AccessDenial {
+        id: 0,
+        manager_id: manager.as_ref().map(|m| m.id),
+        remote_addr: req.connection_info().remote().map(String::from),
+        dataset_name: dataset.name.clone(),
+        partition_name: partition_name.map(String::from),
+        action: action.into(),
+        reason: reason.clone(),
+        created_at: Utc::now(),
+    };
+    if let Err(e) = denial.record(&mut srv.db.clone()).await {
+        log::error!("failed to record access denial: {}", e);
+    }
+
+    Err(json_message(resp, status, reason))
+}
+
+// Finds the manager identified by the API key in the request's "Authorization" header. If the
+// manager can't be found, the error contains the response to be returned to the client.
+async fn request_manager(srv: &Data<Server>, req: &HttpRequest) -> Result<Manager, Response> {
+    let resp = HttpResponse::build(StatusCode::OK);
+    let api_key = match api_key(req) {
+        Some(api_key) => api_key,
+        None => {
+            // only the header names are logged, since the values may hold credentials
+            let names: Vec<&str> = req.headers().keys().map(|name| name.as_str()).collect();
+            log::error!("invalid or missing API key, header names = {:?}", names);
+            return Err(json_message(
+                resp,
+                StatusCode::UNAUTHORIZED,
+                "invalid or missing API key",
+            ));
+        }
+    };
+
+    Manager::find(&mut srv.db.clone(), api_key)
+        .await
+        .map_err(|e| {
+            log::error!(
+                "failed to find manager with API key '{}': {}",
+                mask(api_key),
+                e
+            );
+            match e {
+                DDError::NotFound(_) => error_message(
+                    resp,
+                    &e,
+                    format!("no manager found with API key '{}'", api_key),
+                ),
+                DDError::Auth(msg) => json_message(resp, StatusCode::UNAUTHORIZED, msg),
+                _ => error_message(
+                    resp,
+                    &e,
+                    format!("failed to find manager with API key '{}'", api_key),
+                ),
+            }
+        })
+}
+
+// Like `request_manager`, but additionally requires the manager to be an admin.
+async fn request_admin(srv: &Data<Server>, req: &HttpRequest) -> Result<Manager, Response> {
+    let manager = request_manager(srv, req).await?;
+    if !manager.admin {
+        log::error!("manager '{}' attempted an admin request", manager.email);
+        return Err(json_message(
+            HttpResponse::build(StatusCode::OK),
+            StatusCode::FORBIDDEN,
+            "admin privileges required",
+        ));
+    }
+
+    Ok(manager)
+}
+
+fn api_key(req: &HttpRequest) -> Option<Uuid> {
+    req.headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|bearer| Uuid::parse_str(&trim_api_key(bearer)).ok())
+}
+
+fn json_message(
+    mut builder: HttpResponseBuilder,
+    status: StatusCode,
+    message: impl AsRef<str>,
+) -> Response {
+    if status.is_client_error() || status.is_server_error() {
+        return ErrorBody::new(ErrorKind::from_status(status), message.as_ref()).respond(builder);
+    }
+
+    builder.status(status).json(serde_json::json!({
+        "code": status.as_u16(),
+        "status": status.canonical_reason(),
+        "message": message.as_ref()
+    }))
+}
+
+// responds to a failed operation with the status and error code of the kind of error it was, and
+// its own message if it has one which can be shown to clients
+fn error_message(
+    builder: HttpResponseBuilder,
+    err: &DDError,
+    message: impl AsRef<str>,
+) -> Response {
+    let message = err
+        .public_message()
+        .unwrap_or_else(|| message.as_ref().to_string());
+    ErrorBody::new(err.kind(), message)
+        .with_details(err.details())
+        .respond(builder)
+}
+
+// Clients of the unversioned API predate pagination, so they're sent the bare list of a page's
+// items, rather than the page with its total and cursor.
+fn unversioned(req: &HttpRequest) -> bool {
+    !req.path().starts_with(routes::API_V1)
+}
+
+fn trim_api_key(bearer: &str) -> String {
+    bearer.replace("Bearer ", "").trim().into()
+}
+
+#[test]
+fn test_unversioned() {
+    use actix_web::test::TestRequest;
+
+    assert!(unversioned(
+        &TestRequest::with_uri("/api/datasets").to_http_request()
+    ));
+    assert!(!unversioned(
+        &TestRequest::with_uri("/api/v1/datasets").to_http_request()
+    ));
+}
+
+#[test]
+fn test_pagination_count() {
+    let pagination = |count| Pagination {
+        start: None,
+        end: None,
+        count: Some(count),
+        offset: None,
+        cursor: None,
+        sort: None,
+        order: None,
+    };
+
+    assert!(RangeParams::try_from(&pagination(1)).is_ok());
+    assert!(RangeParams::try_from(&pagination(MAX_PAGE_SIZE)).is_ok());
+    assert!(RangeParams::try_from(&pagination(0)).is_err());
+    assert!(RangeParams::try_from(&pagination(MAX_PAGE_SIZE + 1)).is_err());
+    assert!(RangeParams::try_from(&pagination(i32::MAX)).is_err());
 }
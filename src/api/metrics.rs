@@ -0,0 +1,119 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use crate::api::routes;
+use crate::metrics;
+
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    Error,
+};
+
+/// RequestMetrics is middleware which records how long every request took to respond to, by its
+/// method, route and status.
+pub struct RequestMetrics;
+
+impl<S, B> Transform<S> for RequestMetrics
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestMetricsMiddleware<S>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Transform, Self::InitError>>>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Box::pin(async move { Ok(RequestMetricsMiddleware { service }) })
+    }
+}
+
+pub struct RequestMetricsMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service for RequestMetricsMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let start = Instant::now();
+        let method = req.method().as_str().to_string();
+        let route =
+            routes::route_pattern(&method, req.path()).unwrap_or_else(|| routes::UNMATCHED.into());
+
+        let fut = self.service.call(req);
+        Box::pin(async move {
+            let res = fut.await;
+            let status = match &res {
+                Ok(res) => res.status(),
+                Err(e) => e.as_response_error().status_code(),
+            };
+            metrics::observe_request(
+                &method,
+                &route,
+                status.as_u16(),
+                start.elapsed().as_secs_f64(),
+            );
+
+            res
+        })
+    }
+}
+
+#[actix_rt::test]
+async fn test_request_metrics() {
+    use actix_web::{test, web, App};
+
+    let mut app = test::init_service(App::new().wrap(RequestMetrics).route(
+        "/api/v1/dataset/{dataset_name}",
+        web::get().to(|| async { "ok" }),
+    ))
+    .await;
+
+    for uri in &[
+        "/api/v1/dataset/first",
+        "/api/v1/dataset/second",
+        "/missing",
+    ] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        test::call_service(&mut app, req).await;
+    }
+
+    let count = |route: &str, status: &str| {
+        prometheus::gather()
+            .iter()
+            .filter(|family| family.get_name() == "dd_http_request_duration_seconds")
+            .flat_map(|family| family.get_metric())
+            .filter(|metric| {
+                let labels = metric.get_label();
+                labels
+                    .iter()
+                    .any(|l| l.get_name() == "route" && l.get_value() == route)
+                    && labels
+                        .iter()
+                        .any(|l| l.get_name() == "status" && l.get_value() == status)
+            })
+            .map(|metric| metric.get_histogram().get_sample_count())
+            .sum::<u64>()
+    };
+    // requests are counted by their route's pattern, not their path
+    assert_eq!(count("/api/v1/dataset/{dataset_name}", "200"), 2);
+    assert_eq!(count(routes::UNMATCHED, "404"), 1);
+}
@@ -1,2 +1,6 @@
 pub mod http;
+pub mod metrics;
+pub mod openapi;
+pub mod request_id;
+pub mod routes;
 pub use http::*;
@@ -0,0 +1,1545 @@
+use crate::api::routes::{API_V1, ROUTES};
+
+use serde_json::{json, Map, Value};
+
+/// The security required by an operation, where the API key of a manager is sent as a bearer
+/// token. Partitions of public datasets can be read without one.
+#[derive(Clone, Copy)]
+enum Auth {
+    None,
+    Optional,
+    Manager,
+    Admin,
+}
+
+/// The body of a request or response, named by its schema in the spec's components.
+#[derive(Clone, Copy)]
+enum Body {
+    Schema(&'static str),
+    Array(&'static str),
+}
+
+/// A query parameter, whose kind is either a JSON type, a string format ("date", "date-time"), or
+/// the name of a schema in the spec's components.
+struct Param {
+    name: &'static str,
+    kind: &'static str,
+    required: bool,
+    description: &'static str,
+}
+
+const fn opt(name: &'static str, kind: &'static str, description: &'static str) -> Param {
+    Param {
+        name,
+        kind,
+        required: false,
+        description,
+    }
+}
+
+const fn req(name: &'static str, kind: &'static str, description: &'static str) -> Param {
+    Param {
+        name,
+        kind,
+        required: true,
+        description,
+    }
+}
+
+/// An Operation documents the route served by a handler, which is found by its name in `ROUTES`.
+struct Operation {
+    handler: &'static str,
+    summary: &'static str,
+    auth: Auth,
+    query: &'static [&'static [Param]],
+    body: Option<Body>,
+    response: Body,
+}
+
+const PAGINATION: &[Param] = &[
+    opt(
+        "start",
+        "date-time",
+        "only results created at or after this time",
+    ),
+    opt(
+        "end",
+        "date-time",
+        "only results created at or before this time",
+    ),
+    opt(
+        "count",
+        "integer",
+        "the most results to return, from 1 to 1000",
+    ),
+    opt(
+        "offset",
+        "integer",
+        "the number of results to skip, can't be used with a cursor",
+    ),
+    opt("cursor", "string", "the `next` token of the previous page"),
+    opt("sort", "SortKey", "the attribute to sort results by"),
+    opt("order", "SortOrder", "the direction to sort results in"),
+];
+
+const DATASET_FILTERS: &[Param] = &[
+    opt("classification", "Classification", ""),
+    opt("manager", "string", "the email of the dataset's manager"),
+    opt("prefix", "string", "the prefix of the dataset's name"),
+    opt(
+        "labels",
+        "string",
+        "a label selector, e.g. team:billing,tier:1",
+    ),
+];
+
+const PARTITION_KEY: &[Param] = &[
+    opt(
+        "date",
+        "date",
+        "the date parsed by the dataset's partition template",
+    ),
+    opt(
+        "hour",
+        "integer",
+        "the hour parsed by the dataset's partition template",
+    ),
+    opt(
+        "shard",
+        "integer",
+        "the shard parsed by the dataset's partition template",
+    ),
+];
+
+const PARTITION_FILTERS: &[Param] = &[
+    opt(
+        "data_start",
+        "date-time",
+        "only partitions with data after this time",
+    ),
+    opt(
+        "data_end",
+        "date-time",
+        "only partitions with data before this time",
+    ),
+    opt("prefix", "string", "the prefix of the partition's name"),
+    opt(
+        "min_size",
+        "integer",
+        "the smallest partition size, in bytes",
+    ),
+    opt(
+        "max_size",
+        "integer",
+        "the largest partition size, in bytes",
+    ),
+];
+
+const SIGN_URL: &[Param] = &[opt(
+    "ttl",
+    "integer",
+    "seconds until the URL expires, 900 by default",
+)];
+
+const SEARCH: &[Param] = &[
+    opt(
+        "term",
+        "string",
+        "words matched against dataset names, descriptions, and fields",
+    ),
+    opt("classification", "Classification", ""),
+    opt("format", "Format", ""),
+    opt("compression", "Compression", ""),
+    opt("manager", "string", "the email of the dataset's manager"),
+    opt("updated_after", "date-time", ""),
+    opt("updated_before", "date-time", ""),
+    opt(
+        "labels",
+        "string",
+        "a label selector, e.g. team:billing,tier:1",
+    ),
+    opt("count", "integer", ""),
+    opt("offset", "integer", ""),
+];
+
+const FIELDS: &[Param] = &[
+    opt(
+        "name",
+        "string",
+        "a field name pattern, where * and ? are wildcards",
+    ),
+    opt("type", "string", "the field's type"),
+    opt(
+        "versions",
+        "boolean",
+        "whether to search every version of each schema",
+    ),
+];
+
+const PII: &[Param] = &[opt("sensitivity", "Sensitivity", "")];
+
+const AUDIT: &[Param] = &[
+    opt("actor_type", "string", "system, manager, or pubsub"),
+    opt("actor_id", "string", ""),
+    opt("action", "string", ""),
+    opt("target_type", "string", ""),
+    opt("target", "string", ""),
+    opt("start", "date-time", ""),
+    opt("end", "date-time", ""),
+    opt("offset", "integer", ""),
+    opt("count", "integer", ""),
+];
+
+const ACCESS: &[Param] = &[
+    opt("manager_id", "integer", ""),
+    opt("dataset_name", "string", ""),
+    opt("start", "date-time", ""),
+    opt("end", "date-time", ""),
+    opt("offset", "integer", ""),
+    opt("count", "integer", ""),
+];
+
+const SUCCESSOR: &[Param] = &[req(
+    "successor_id",
+    "integer",
+    "the manager who receives the deleted manager's datasets",
+)];
+
+const OPERATIONS: &[Operation] = &[
+    Operation {
+        handler: "openapi_spec",
+        summary: "Returns this OpenAPI document",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        response: Body::Schema("OpenApi"),
+    },
+    Operation {
+        handler: "register_manager",
+        summary: "Registers a manager",
+        auth: Auth::None,
+        query: &[],
+        body: Some(Body::Schema("AuthManager")),
+        response: Body::Schema("RestrictedManager"),
+    },
+    Operation {
+        handler: "login_manager",
+        summary: "Returns the API key of a manager",
+        auth: Auth::None,
+        query: &[],
+        body: Some(Body::Schema("AuthManager")),
+        response: Body::Schema("RestrictedManager"),
+    },
+    Operation {
+        handler: "list_managers",
+        summary: "Lists every manager",
+        auth: Auth::Admin,
+        query: &[],
+        body: None,
+        response: Body::Array("AdminManager"),
+    },
+    Operation {
+        handler: "list_audit_events",
+        summary: "Lists audit events",
+        auth: Auth::Admin,
+        query: &[AUDIT],
+        body: None,
+        response: Body::Array("AuditEvent"),
+    },
+    Operation {
+        handler: "list_access_denials",
+        summary: "Lists partition reads which were denied",
+        auth: Auth::Admin,
+        query: &[ACCESS],
+        body: None,
+        response: Body::Array("AccessDenial"),
+    },
+    Operation {
+        handler: "update_manager",
+        summary: "Promotes, demotes, disables, or enables a manager",
+        auth: Auth::Admin,
+        query: &[],
+        body: Some(Body::Schema("UpdateManager")),
+        response: Body::Schema("AdminManager"),
+    },
+    Operation {
+        handler: "delete_manager",
+        summary: "Deletes a manager, transferring their datasets to a successor",
+        auth: Auth::Admin,
+        query: &[SUCCESSOR],
+        body: None,
+        response: Body::Schema("Message"),
+    },
+    Operation {
+        handler: "transfer_manager_datasets",
+        summary: "Transfers every dataset of a manager to another",
+        auth: Auth::Admin,
+        query: &[],
+        body: Some(Body::Schema("TransferDatasets")),
+        response: Body::Schema("Transferred"),
+    },
+    Operation {
+        handler: "list_meta",
+        summary: "Lists dataset attributes and the bucket of each classification",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        response: Body::Schema("Meta"),
+    },
+    Operation {
+        handler: "search_datasets",
+        summary: "Searches datasets, with facet counts",
+        auth: Auth::None,
+        query: &[SEARCH],
+        body: None,
+        response: Body::Schema("SearchResults"),
+    },
+    Operation {
+        handler: "find_fields",
+        summary: "Finds schema fields across datasets",
+        auth: Auth::None,
+        query: &[FIELDS],
+        body: None,
+        response: Body::Array("SchemaField"),
+    },
+    Operation {
+        handler: "list_pii_fields",
+        summary: "Lists fields annotated as PII",
+        auth: Auth::None,
+        query: &[PII],
+        body: None,
+        response: Body::Array("PiiField"),
+    },
+    Operation {
+        handler: "list_datasets",
+        summary: "Lists a page of datasets",
+        auth: Auth::None,
+        query: &[DATASET_FILTERS, PAGINATION],
+        body: None,
+        response: Body::Schema("DatasetPage"),
+    },
+    Operation {
+        handler: "latest_partition",
+        summary: "Finds the most recently registered partition of a dataset",
+        auth: Auth::Optional,
+        query: &[],
+        body: None,
+        response: Body::Schema("Partition"),
+    },
+    Operation {
+        handler: "download_partition",
+        summary: "Signs a URL to download a partition",
+        auth: Auth::Optional,
+        query: &[SIGN_URL],
+        body: None,
+        response: Body::Schema("SignedUrl"),
+    },
+    Operation {
+        handler: "find_partition",
+        summary: "Finds a partition by name",
+        auth: Auth::Optional,
+        query: &[],
+        body: None,
+        response: Body::Schema("Partition"),
+    },
+    Operation {
+        handler: "find_dataset",
+        summary: "Finds a dataset by name",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        response: Body::Schema("Dataset"),
+    },
+    Operation {
+        handler: "update_dataset",
+        summary: "Updates the description, schema, or partition template of a dataset",
+        auth: Auth::Manager,
+        query: &[],
+        body: Some(Body::Schema("UpdateDataset")),
+        response: Body::Schema("Dataset"),
+    },
+    Operation {
+        handler: "register_dataset",
+        summary: "Registers a dataset from its dd.json config",
+        auth: Auth::Manager,
+        query: &[],
+        body: Some(Body::Schema("DatasetConfig")),
+        response: Body::Schema("Dataset"),
+    },
+    Operation {
+        handler: "register_partitions",
+        summary: "Registers or updates many partitions of a dataset at once",
+        auth: Auth::Manager,
+        query: &[],
+        body: Some(Body::Schema("RegisterPartitions")),
+        response: Body::Array("Partition"),
+    },
+    Operation {
+        handler: "list_partitions",
+        summary: "Lists a page of a dataset's partitions",
+        auth: Auth::Optional,
+        query: &[PARTITION_KEY, PARTITION_FILTERS, PAGINATION],
+        body: None,
+        response: Body::Schema("PartitionPage"),
+    },
+    Operation {
+        handler: "update_sensitivity",
+        summary: "Annotates the PII held by a dataset's fields",
+        auth: Auth::Manager,
+        query: &[],
+        body: Some(Body::Schema("DatasetSensitivity")),
+        response: Body::Schema("Dataset"),
+    },
+    Operation {
+        handler: "update_labels",
+        summary: "Replaces the labels of a dataset",
+        auth: Auth::Manager,
+        query: &[],
+        body: Some(Body::Schema("DatasetLabels")),
+        response: Body::Schema("Dataset"),
+    },
+    Operation {
+        handler: "upload_partition",
+        summary: "Signs a URL to start a resumable upload of a partition",
+        auth: Auth::Manager,
+        query: &[SIGN_URL],
+        body: None,
+        response: Body::Schema("SignedUrl"),
+    },
+    Operation {
+        handler: "restore_dataset",
+        summary: "Restores a deleted dataset",
+        auth: Auth::Manager,
+        query: &[],
+        body: None,
+        response: Body::Schema("Dataset"),
+    },
+    Operation {
+        handler: "restore_partition",
+        summary: "Restores a deleted partition",
+        auth: Auth::Manager,
+        query: &[],
+        body: None,
+        response: Body::Schema("Partition"),
+    },
+    Operation {
+        handler: "list_read_grants",
+        summary: "Lists the managers allowed to read a dataset's partitions",
+        auth: Auth::Manager,
+        query: &[],
+        body: None,
+        response: Body::Array("ReadGrant"),
+    },
+    Operation {
+        handler: "add_read_grant",
+        summary: "Allows a manager to read a dataset's partitions",
+        auth: Auth::Manager,
+        query: &[],
+        body: Some(Body::Schema("ManagerEmail")),
+        response: Body::Schema("ReadGrant"),
+    },
+    Operation {
+        handler: "remove_read_grant",
+        summary: "Removes a manager's grant to read a dataset's partitions",
+        auth: Auth::Manager,
+        query: &[],
+        body: None,
+        response: Body::Schema("Message"),
+    },
+    Operation {
+        handler: "list_maintainers",
+        summary: "Lists the maintainers of a dataset",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        response: Body::Array("Maintainer"),
+    },
+    Operation {
+        handler: "add_maintainer",
+        summary: "Adds a maintainer to a dataset",
+        auth: Auth::Manager,
+        query: &[],
+        body: Some(Body::Schema("ManagerEmail")),
+        response: Body::Schema("Maintainer"),
+    },
+    Operation {
+        handler: "remove_maintainer",
+        summary: "Removes a maintainer from a dataset",
+        auth: Auth::Manager,
+        query: &[],
+        body: None,
+        response: Body::Schema("Message"),
+    },
+    Operation {
+        handler: "list_transfers",
+        summary: "Lists the pending transfers to or from the requesting manager",
+        auth: Auth::Manager,
+        query: &[],
+        body: None,
+        response: Body::Array("DatasetTransfer"),
+    },
+    Operation {
+        handler: "find_dataset_transfer",
+        summary: "Finds the pending transfer of a dataset",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        response: Body::Schema("DatasetTransfer"),
+    },
+    Operation {
+        handler: "request_transfer",
+        summary: "Requests to transfer a dataset to another manager",
+        auth: Auth::Manager,
+        query: &[],
+        body: Some(Body::Schema("ManagerEmail")),
+        response: Body::Schema("DatasetTransfer"),
+    },
+    Operation {
+        handler: "accept_transfer",
+        summary: "Accepts a transfer, making the recipient the dataset's manager",
+        auth: Auth::Manager,
+        query: &[],
+        body: None,
+        response: Body::Schema("Dataset"),
+    },
+    Operation {
+        handler: "cancel_transfer",
+        summary: "Cancels or declines a transfer",
+        auth: Auth::Manager,
+        query: &[],
+        body: None,
+        response: Body::Schema("Message"),
+    },
+];
+
+/// Builds the OpenAPI 3 document describing every route in `ROUTES`.
+pub fn spec() -> Value {
+    let mut paths = Map::new();
+    for (method, path, handler) in ROUTES {
+        if let Some(op) = OPERATIONS.iter().find(|op| op.handler == *handler) {
+            let item = paths.entry(spec_path(path)).or_insert_with(|| json!({}));
+            item[*method] = operation(op, path);
+        }
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Data Dictionary",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "servers": [{ "url": API_V1 }],
+        "paths": paths,
+        "components": {
+            "schemas": schemas(),
+            "securitySchemes": {
+                "apiKey": {
+                    "type": "http",
+                    "scheme": "bearer",
+                    "description": "the API key of a manager",
+                },
+            },
+        },
+    })
+}
+
+// Converts an actix path into an OpenAPI path, removing the pattern of any segment which has one,
+// e.g. "{partition_name:.*}" becomes "{partition_name}".
+fn spec_path(path: &str) -> String {
+    path.split('/')
+        .map(
+            |segment| match (segment.starts_with('{'), segment.find(':')) {
+                (true, Some(i)) => format!("{}}}", &segment[..i]),
+                _ => segment.to_string(),
+            },
+        )
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn path_params(path: &str) -> Vec<Value> {
+    path.split('/')
+        .filter(|segment| segment.starts_with('{'))
+        .map(|segment| {
+            let name = segment
+                .trim_matches(|c| c == '{' || c == '}')
+                .split(':')
+                .next()
+                .unwrap_or_default();
+            let kind = if name.ends_with("_id") {
+                "integer"
+            } else {
+                "string"
+            };
+            json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": param_schema(kind),
+            })
+        })
+        .collect()
+}
+
+fn param_schema(kind: &str) -> Value {
+    match kind {
+        "string" | "integer" | "boolean" => json!({ "type": kind }),
+        "date" | "date-time" => json!({ "type": "string", "format": kind }),
+        schema => schema_ref(schema),
+    }
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn body_schema(body: Body) -> Value {
+    match body {
+        Body::Schema(name) => schema_ref(name),
+        Body::Array(name) => json!({ "type": "array", "items": schema_ref(name) }),
+    }
+}
+
+fn json_content(body: Body) -> Value {
+    json!({ "application/json": { "schema": body_schema(body) } })
+}
+
+fn operation(op: &Operation, path: &str) -> Value {
+    let mut params = path_params(path);
+    for param in op.query.iter().flat_map(|params| params.iter()) {
+        let mut value = json!({
+            "name": param.name,
+            "in": "query",
+            "required": param.required,
+            "schema": param_schema(param.kind),
+        });
+        if !param.description.is_empty() {
+            value["description"] = param.description.into();
+        }
+        params.push(value);
+    }
+
+    let mut value = json!({
+        "operationId": op.handler,
+        "summary": op.summary,
+        "parameters": params,
+        "responses": {
+            "200": { "description": "OK", "content": json_content(op.response) },
+            "default": { "description": "an error", "content": json_content(Body::Schema("Error")) },
+        },
+    });
+    if let Some(body) = op.body {
+        value["requestBody"] = json!({ "required": true, "content": json_content(body) });
+    }
+    let security = match op.auth {
+        Auth::None => json!([]),
+        Auth::Optional => json!([{}, { "apiKey": [] }]),
+        Auth::Manager | Auth::Admin => json!([{ "apiKey": [] }]),
+    };
+    value["security"] = security;
+    if let Auth::Admin = op.auth {
+        value["description"] = "Requires the API key of an admin manager.".into();
+    }
+    value
+}
+
+fn schemas() -> Value {
+    let timestamp = json!({ "type": "string", "format": "date-time" });
+    let nullable_timestamp = json!({ "type": "string", "format": "date-time", "nullable": true });
+    let string_map = json!({ "type": "object", "additionalProperties": { "type": "string" } });
+
+    let mut schemas = Map::new();
+    schemas.insert("OpenApi".into(), json!({ "type": "object" }));
+    schemas.insert(
+        "Message".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "code": { "type": "integer" },
+                "status": { "type": "string" },
+                "message": { "type": "string" },
+            },
+        }),
+    );
+    schemas.insert(
+        "Error".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "code": { "type": "integer" },
+                "status": { "type": "string" },
+                "error_code": {
+                    "type": "string",
+                    "enum": [
+                        "not_found",
+                        "conflict",
+                        "validation_failed",
+                        "unauthorized",
+                        "forbidden",
+                        "unavailable",
+                        "internal_error",
+                    ],
+                },
+                "message": { "type": "string" },
+                "details": { "type": "object", "nullable": true },
+                "request_id": { "type": "string", "nullable": true },
+            },
+        }),
+    );
+    schemas.insert(
+        "Classification".into(),
+        json!({
+            "type": "string",
+            "enum": ["confidential", "internal", "public", "restricted"],
+        }),
+    );
+    schemas.insert(
+        "Compression".into(),
+        json!({ "type": "string", "enum": ["uncompressed", "zip", "gzip"] }),
+    );
+    schemas.insert(
+        "Format".into(),
+        json!({
+            "type": "string",
+            "enum": ["plaintext", "json", "ndjson", "csv", "tsv", "protobuf"],
+        }),
+    );
+    schemas.insert(
+        "Sensitivity".into(),
+        json!({
+            "type": "string",
+            "enum": [
+                "email", "phone", "card", "name", "address", "government_id", "ip_address",
+                "date_of_birth", "other",
+            ],
+        }),
+    );
+    schemas.insert(
+        "SortKey".into(),
+        json!({ "type": "string", "enum": ["name", "created_at", "updated_at", "size"] }),
+    );
+    schemas.insert(
+        "SortOrder".into(),
+        json!({ "type": "string", "enum": ["asc", "desc"] }),
+    );
+    schemas.insert(
+        "DatasetSchema".into(),
+        json!({
+            "type": "object",
+            "description": "the type of each field, by name",
+            "additionalProperties": { "type": "string", "nullable": true },
+        }),
+    );
+    schemas.insert(
+        "DatasetSensitivity".into(),
+        json!({
+            "type": "object",
+            "description": "the PII held by each field, by name",
+            "additionalProperties": schema_ref("Sensitivity"),
+        }),
+    );
+    schemas.insert("DatasetLabels".into(), string_map.clone());
+    schemas.insert(
+        "AuthManager".into(),
+        json!({
+            "type": "object",
+            "required": ["email", "password"],
+            "properties": {
+                "email": { "type": "string" },
+                "password": { "type": "string", "format": "password" },
+            },
+        }),
+    );
+    schemas.insert(
+        "RestrictedManager".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "integer" },
+                "email": { "type": "string" },
+                "api_key": { "type": "string", "format": "uuid" },
+            },
+        }),
+    );
+    schemas.insert(
+        "AdminManager".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "integer" },
+                "email": { "type": "string" },
+                "admin": { "type": "boolean" },
+                "disabled": { "type": "boolean" },
+                "created_at": timestamp,
+                "updated_at": timestamp,
+            },
+        }),
+    );
+    schemas.insert(
+        "UpdateManager".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "admin": { "type": "boolean" },
+                "disabled": { "type": "boolean" },
+            },
+        }),
+    );
+    schemas.insert(
+        "TransferDatasets".into(),
+        json!({
+            "type": "object",
+            "required": ["to_manager_id"],
+            "properties": { "to_manager_id": { "type": "integer" } },
+        }),
+    );
+    schemas.insert(
+        "Transferred".into(),
+        json!({
+            "type": "object",
+            "properties": { "transferred": { "type": "integer" } },
+        }),
+    );
+    schemas.insert(
+        "ManagerEmail".into(),
+        json!({
+            "type": "object",
+            "required": ["email"],
+            "properties": { "email": { "type": "string" } },
+        }),
+    );
+    schemas.insert(
+        "Dataset".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "integer" },
+                "manager_id": { "type": "integer" },
+                "manager_email": { "type": "string" },
+                "name": { "type": "string" },
+                "classification": schema_ref("Classification"),
+                "compression": schema_ref("Compression"),
+                "format": schema_ref("Format"),
+                "description": { "type": "string" },
+                "schema": schema_ref("DatasetSchema"),
+                "sensitivity": schema_ref("DatasetSensitivity"),
+                "labels": schema_ref("DatasetLabels"),
+                "partition_template": { "type": "string", "nullable": true },
+                "created_at": timestamp,
+                "updated_at": timestamp,
+            },
+        }),
+    );
+    schemas.insert("DatasetConfig".into(), json!({
+        "type": "object",
+        "description": "the contents of a dd.json file",
+        "required": ["name", "classification", "compression", "format", "description", "schema"],
+        "properties": {
+            "name": { "type": "string" },
+            "classification": schema_ref("Classification"),
+            "compression": schema_ref("Compression"),
+            "format": schema_ref("Format"),
+            "description": { "type": "string" },
+            "schema": schema_ref("DatasetSchema"),
+            "sensitivity": schema_ref("DatasetSensitivity"),
+            "labels": schema_ref("DatasetLabels"),
+            "partition_template": { "type": "string" },
+        },
+    }));
+    schemas.insert(
+        "UpdateDataset".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "description": { "type": "string" },
+                "schema": schema_ref("DatasetSchema"),
+                "partition_template": {
+                    "type": "string",
+                    "description": "an empty template removes the dataset's partition template",
+                },
+            },
+        }),
+    );
+    schemas.insert(
+        "DatasetPage".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "total": { "type": "integer" },
+                "datasets": { "type": "array", "items": schema_ref("Dataset") },
+                "next": { "type": "string", "nullable": true },
+            },
+        }),
+    );
+    schemas.insert(
+        "PartitionKey".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "date": { "type": "string", "format": "date", "nullable": true },
+                "hour": { "type": "integer", "nullable": true },
+                "shard": { "type": "integer", "nullable": true },
+            },
+        }),
+    );
+    schemas.insert(
+        "Partition".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "partition_id": { "type": "integer" },
+                "partition_name": { "type": "string" },
+                "partition_url": { "type": "string" },
+                "partition_size": { "type": "integer" },
+                "partition_key": schema_ref("PartitionKey"),
+                "data_start": nullable_timestamp,
+                "data_end": nullable_timestamp,
+                "dataset_id": { "type": "integer" },
+                "created_at": timestamp,
+                "updated_at": timestamp,
+            },
+        }),
+    );
+    schemas.insert(
+        "RegisterPartitions".into(),
+        json!({
+            "type": "object",
+            "required": ["partitions"],
+            "properties": {
+                "partitions": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["name", "url", "size"],
+                        "properties": {
+                            "name": { "type": "string" },
+                            "url": { "type": "string" },
+                            "size": { "type": "integer" },
+                            "data_start": nullable_timestamp,
+                            "data_end": nullable_timestamp,
+                        },
+                    },
+                },
+            },
+        }),
+    );
+    schemas.insert(
+        "PartitionPage".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "total": { "type": "integer" },
+                "partitions": { "type": "array", "items": schema_ref("Partition") },
+                "next": { "type": "string", "nullable": true },
+            },
+        }),
+    );
+    schemas.insert(
+        "SignedUrl".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "object": { "type": "string" },
+                "method": { "type": "string" },
+                "url": { "type": "string" },
+                "headers": string_map,
+                "expires_at": timestamp,
+            },
+        }),
+    );
+    schemas.insert("Meta".into(), json!({
+        "type": "object",
+        "properties": {
+            "attrs": {
+                "type": "object",
+                "properties": {
+                    "format": { "type": "array", "items": schema_ref("Format") },
+                    "compression": { "type": "array", "items": schema_ref("Compression") },
+                    "classification": { "type": "array", "items": schema_ref("Classification") },
+                },
+            },
+            "buckets": string_map,
+        },
+    }));
+    schemas.insert(
+        "SearchResults".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "total": { "type": "integer" },
+                "datasets": { "type": "array", "items": schema_ref("Dataset") },
+                "facets": {
+                    "type": "object",
+                    "description": "the number of matches with each value of an attribute",
+                    "additionalProperties": {
+                        "type": "object",
+                        "additionalProperties": { "type": "integer" },
+                    },
+                },
+            },
+        }),
+    );
+    schemas.insert(
+        "SchemaField".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "dataset_id": { "type": "integer" },
+                "dataset_name": { "type": "string" },
+                "schema_version": { "type": "integer" },
+                "latest": { "type": "boolean" },
+                "field_name": { "type": "string" },
+                "field_type": { "type": "string", "nullable": true },
+                "created_at": timestamp,
+            },
+        }),
+    );
+    schemas.insert(
+        "PiiField".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "dataset_id": { "type": "integer" },
+                "dataset_name": { "type": "string" },
+                "classification": schema_ref("Classification"),
+                "field_name": { "type": "string" },
+                "field_type": { "type": "string", "nullable": true },
+                "sensitivity": schema_ref("Sensitivity"),
+            },
+        }),
+    );
+    schemas.insert(
+        "Maintainer".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "dataset_id": { "type": "integer" },
+                "manager_id": { "type": "integer" },
+                "manager_email": { "type": "string" },
+                "created_at": timestamp,
+            },
+        }),
+    );
+    schemas.insert(
+        "ReadGrant".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "dataset_id": { "type": "integer" },
+                "manager_id": { "type": "integer" },
+                "manager_email": { "type": "string" },
+                "created_at": timestamp,
+            },
+        }),
+    );
+    schemas.insert(
+        "DatasetTransfer".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "transfer_id": { "type": "integer" },
+                "dataset_id": { "type": "integer" },
+                "dataset_name": { "type": "string" },
+                "from_manager_id": { "type": "integer" },
+                "from_manager_email": { "type": "string" },
+                "to_manager_id": { "type": "integer" },
+                "to_manager_email": { "type": "string" },
+                "created_at": timestamp,
+            },
+        }),
+    );
+    schemas.insert(
+        "AuditEvent".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "event_id": { "type": "integer" },
+                "actor": {
+                    "type": "object",
+                    "description": "who made the change, tagged by its actor_type",
+                    "properties": {
+                        "actor_type": { "type": "string", "enum": ["system", "manager", "pubsub"] },
+                        "manager_id": { "type": "integer" },
+                        "message_id": { "type": "string" },
+                    },
+                },
+                "action": { "type": "string" },
+                "target_type": { "type": "string" },
+                "target": { "type": "string" },
+                "before": { "type": "object", "nullable": true },
+                "after": { "type": "object", "nullable": true },
+                "created_at": timestamp,
+            },
+        }),
+    );
+    schemas.insert(
+        "AccessDenial".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "access_id": { "type": "integer" },
+                "manager_id": { "type": "integer", "nullable": true },
+                "remote_addr": { "type": "string", "nullable": true },
+                "dataset_name": { "type": "string" },
+                "partition_name": { "type": "string", "nullable": true },
+                "action": { "type": "string" },
+                "reason": { "type": "string" },
+                "created_at": timestamp,
+            },
+        }),
+    );
+    Value::Object(schemas)
+}
+
+// Collects every schema reference made within a value.
+#[cfg(test)]
+fn refs<'a>(value: &'a Value, found: &mut Vec<&'a str>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                match (key.as_str(), value) {
+                    ("$ref", Value::String(r)) => found.push(r),
+                    _ => refs(value, found),
+                }
+            }
+        }
+        Value::Array(values) => values.iter().for_each(|value| refs(value, found)),
+        _ => {}
+    }
+}
+
+// Checks that a value is described by a schema, resolving references within the spec's schemas.
+// An object may only have the properties described by its schema, unless it's free-form.
+#[cfg(test)]
+fn check(value: &Value, schema: &Value, schemas: &Value, at: &str) -> Result<(), String> {
+    if let Some(Value::String(r)) = schema.get("$ref") {
+        let name = r.trim_start_matches("#/components/schemas/");
+        return check(value, &schemas[name], schemas, at);
+    }
+    if value.is_null() {
+        return match schema["nullable"].as_bool() {
+            Some(true) => Ok(()),
+            _ => Err(format!("{} is null", at)),
+        };
+    }
+    if let Some(values) = schema["enum"].as_array() {
+        if !values.contains(value) {
+            return Err(format!("{} is not one of {:?}", at, values));
+        }
+    }
+
+    let valid = match (schema["type"].as_str(), schema["format"].as_str()) {
+        (Some("object"), _) => value.is_object(),
+        (Some("array"), _) => value.is_array(),
+        (Some("integer"), _) => value.is_i64() || value.is_u64(),
+        (Some("boolean"), _) => value.is_boolean(),
+        (Some("string"), Some("date-time")) => {
+            matches!(value.as_str(), Some(s) if chrono::DateTime::parse_from_rfc3339(s).is_ok())
+        }
+        (Some("string"), Some("date")) => {
+            matches!(value.as_str(), Some(s) if s.parse::<chrono::NaiveDate>().is_ok())
+        }
+        (Some("string"), _) => value.is_string(),
+        _ => true,
+    };
+    if !valid {
+        return Err(format!("{} is not a valid {}: {}", at, schema, value));
+    }
+
+    match value {
+        Value::Object(fields) => {
+            for required in schema["required"].as_array().into_iter().flatten() {
+                if !fields.contains_key(required.as_str().unwrap_or_default()) {
+                    return Err(format!("{} is missing {}", at, required));
+                }
+            }
+            for (name, field) in fields {
+                let at = format!("{}.{}", at, name);
+                match (
+                    schema["properties"].get(name),
+                    schema.get("additionalProperties"),
+                ) {
+                    (Some(property), _) | (None, Some(property)) => {
+                        check(field, property, schemas, &at)?
+                    }
+                    (None, None) if schema.get("properties").is_none() => {}
+                    (None, None) => return Err(format!("{} is not described", at)),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                check(item, &schema["items"], schemas, &format!("{}[{}]", at, i))?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+// Builds an example of a schema, which has every property the schema describes.
+#[cfg(test)]
+fn example(schema: &Value, schemas: &Value) -> Value {
+    if let Some(Value::String(r)) = schema.get("$ref") {
+        let name = r.trim_start_matches("#/components/schemas/");
+        return example(&schemas[name], schemas);
+    }
+    if let Some(value) = schema["enum"].get(0) {
+        return value.clone();
+    }
+    match (schema["type"].as_str(), schema["format"].as_str()) {
+        (Some("object"), _) => Value::Object(
+            schema["properties"]
+                .as_object()
+                .map(|properties| {
+                    properties
+                        .iter()
+                        .map(|(name, property)| (name.clone(), example(property, schemas)))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        ),
+        (Some("array"), _) => json!([example(&schema["items"], schemas)]),
+        (Some("integer"), _) => json!(1),
+        (Some("boolean"), _) => json!(true),
+        (Some("string"), Some("date-time")) => json!("2020-01-01T00:00:00Z"),
+        (Some("string"), Some("date")) => json!("2020-01-01"),
+        (Some("string"), Some("uuid")) => json!(uuid::Uuid::nil()),
+        (Some("string"), _) => json!("example"),
+        _ => Value::Null,
+    }
+}
+
+#[test]
+fn test_spec_covers_routes() {
+    let spec = spec();
+    for (method, path, handler) in ROUTES {
+        assert!(
+            spec["paths"][spec_path(path)][*method].is_object(),
+            "route {} {} ({}) is missing from the OpenAPI spec",
+            method.to_uppercase(),
+            path,
+            handler
+        );
+    }
+
+    // every documented operation must be served
+    for op in OPERATIONS {
+        assert!(
+            ROUTES.iter().any(|(_, _, handler)| *handler == op.handler),
+            "operation {} is not routed",
+            op.handler
+        );
+    }
+}
+
+#[test]
+fn test_spec_refs() {
+    let spec = spec();
+    let mut found = vec![];
+    refs(&spec, &mut found);
+    assert!(!found.is_empty());
+    for r in found {
+        let name = r.trim_start_matches("#/components/schemas/");
+        assert!(
+            spec["components"]["schemas"][name].is_object(),
+            "schema {} is not defined",
+            r
+        );
+    }
+}
+
+#[test]
+fn test_spec_path() {
+    assert_eq!(
+        spec_path("/dataset/{dataset_name}/{partition_name:.*}/download"),
+        "/dataset/{dataset_name}/{partition_name}/download"
+    );
+    assert_eq!(spec_path("/datasets"), "/datasets");
+
+    let params = path_params("/grants/{dataset_name}/{manager_id}");
+    assert_eq!(params[0]["name"], "dataset_name");
+    assert_eq!(params[0]["schema"]["type"], "string");
+    assert_eq!(params[1]["name"], "manager_id");
+    assert_eq!(params[1]["schema"]["type"], "integer");
+}
+
+#[test]
+fn test_schemas_describe_responses() {
+    use crate::api::http::{AdminManager, RestrictedManager};
+    use crate::dict::{
+        AccessDenial, Actor, Attributes, AuditEvent, Classification, Compression, Dataset,
+        DatasetConfig, DatasetPage, DatasetTransfer, Format, Maintainer, Manager, Partition,
+        PartitionKey, PartitionPage, PiiField, ReadGrant, SchemaField, SearchFacets, SearchResults,
+        Sensitivity,
+    };
+    use crate::error::{ErrorBody, ErrorKind};
+    use crate::signed_url::SignedUrl;
+    use chrono::{NaiveDate, Utc};
+
+    let schemas = schemas();
+    let now = Utc::now();
+    let manager = || Manager {
+        id: 1,
+        email: "manager@example.com".into(),
+        api_key: uuid::Uuid::new_v4(),
+        admin: true,
+        disabled: false,
+        created_at: now,
+        updated_at: now,
+        salt: "salt".into(),
+        hash: vec![],
+    };
+    let dataset = || Dataset {
+        id: 1,
+        manager_id: 1,
+        manager_email: "manager@example.com".into(),
+        name: "dataset".into(),
+        classification: Classification::Confidential,
+        compression: Compression::Gzip,
+        format: Format::NdJson,
+        description: "a dataset".into(),
+        schema: vec![("email".to_string(), Some("string".to_string()))]
+            .into_iter()
+            .collect(),
+        sensitivity: vec![("email".to_string(), Sensitivity::Email)]
+            .into_iter()
+            .collect(),
+        labels: vec![("team".to_string(), "data".to_string())]
+            .into_iter()
+            .collect(),
+        partition_template: Some("{date}/{shard}".into()),
+        created_at: now,
+        updated_at: now,
+    };
+    let partition = || Partition {
+        id: 1,
+        name: "2020-01-01/1".into(),
+        url: "gs://bucket/dataset/2020-01-01/1".into(),
+        size: 10,
+        key: PartitionKey {
+            date: Some(NaiveDate::from_ymd(2020, 1, 1)),
+            hour: Some(0),
+            shard: Some(1),
+        },
+        data_start: Some(now),
+        data_end: None,
+        dataset_id: 1,
+        created_at: now,
+        updated_at: now,
+    };
+    let mut facets = SearchFacets::default();
+    facets.format.insert("ndjson".into(), 1);
+
+    let responses = vec![
+        (
+            "Error",
+            json!(ErrorBody {
+                request_id: Some("request".into()),
+                ..ErrorBody::new(ErrorKind::NotFound, "not found")
+                    .with_details(Some(json!({ "sql_state": "23505" })))
+            }),
+        ),
+        (
+            "RestrictedManager",
+            json!(RestrictedManager::from(manager())),
+        ),
+        ("AdminManager", json!(AdminManager::from(manager()))),
+        ("Dataset", json!(dataset())),
+        ("DatasetConfig", json!(DatasetConfig::from(&dataset()))),
+        (
+            "DatasetPage",
+            json!(DatasetPage {
+                total: 1,
+                datasets: vec![dataset()],
+                next: Some("next".into()),
+            }),
+        ),
+        ("Partition", json!(partition())),
+        (
+            "PartitionPage",
+            json!(PartitionPage {
+                total: 1,
+                partitions: vec![partition()],
+                next: None,
+            }),
+        ),
+        (
+            "SignedUrl",
+            json!(SignedUrl {
+                object: "dataset/2020-01-01/1".into(),
+                method: "GET".into(),
+                url: "https://storage.googleapis.com/bucket/dataset/2020-01-01/1".into(),
+                headers: vec![("host".to_string(), "storage.googleapis.com".to_string())]
+                    .into_iter()
+                    .collect(),
+                expires_at: now,
+            }),
+        ),
+        (
+            "Meta",
+            json!({
+                "attrs": Attributes {
+                    format: vec![Format::Csv],
+                    compression: vec![Compression::Zip],
+                    classification: vec![Classification::Public],
+                },
+                "buckets": { "public": "dd-public" },
+            }),
+        ),
+        (
+            "SearchResults",
+            json!(SearchResults {
+                total: 1,
+                datasets: vec![dataset()],
+                facets,
+            }),
+        ),
+        (
+            "SchemaField",
+            json!(SchemaField {
+                dataset_id: 1,
+                dataset_name: "dataset".into(),
+                schema_version: 1,
+                latest: true,
+                name: "email".into(),
+                field_type: None,
+                created_at: now,
+            }),
+        ),
+        (
+            "PiiField",
+            json!(PiiField {
+                dataset_id: 1,
+                dataset_name: "dataset".into(),
+                classification: Classification::Restricted,
+                field_name: "email".into(),
+                field_type: Some("string".into()),
+                sensitivity: Sensitivity::Email,
+            }),
+        ),
+        (
+            "Maintainer",
+            json!(Maintainer {
+                dataset_id: 1,
+                manager_id: 2,
+                manager_email: "maintainer@example.com".into(),
+                created_at: now,
+            }),
+        ),
+        (
+            "ReadGrant",
+            json!(ReadGrant {
+                dataset_id: 1,
+                manager_id: 2,
+                manager_email: "reader@example.com".into(),
+                created_at: now,
+            }),
+        ),
+        (
+            "DatasetTransfer",
+            json!(DatasetTransfer {
+                id: 1,
+                dataset_id: 1,
+                dataset_name: "dataset".into(),
+                from_manager_id: 1,
+                from_manager_email: "manager@example.com".into(),
+                to_manager_id: 2,
+                to_manager_email: "recipient@example.com".into(),
+                created_at: now,
+            }),
+        ),
+        (
+            "AccessDenial",
+            json!(AccessDenial {
+                id: 1,
+                manager_id: None,
+                remote_addr: Some("127.0.0.1".into()),
+                dataset_name: "dataset".into(),
+                partition_name: Some("2020-01-01/1".into()),
+                action: "download".into(),
+                reason: "unauthenticated".into(),
+                created_at: now,
+            }),
+        ),
+    ];
+    let actors = vec![
+        Actor::System,
+        Actor::Manager { manager_id: 1 },
+        Actor::Pubsub {
+            message_id: "message".into(),
+        },
+    ];
+    let events = actors.into_iter().map(|actor| {
+        (
+            "AuditEvent",
+            json!(AuditEvent {
+                id: 1,
+                actor,
+                action: "update_dataset".into(),
+                target_type: "dataset".into(),
+                target: "dataset".into(),
+                before: Some(json!({ "description": "before" })),
+                after: None,
+                created_at: now,
+            }),
+        )
+    });
+
+    for (name, value) in responses.into_iter().chain(events) {
+        if let Err(e) = check(&value, &schema_ref(name), &schemas, name) {
+            panic!("{} doesn't match its schema: {}", name, e);
+        }
+        // every property which is described is also sent
+        for property in schemas[name]["properties"].as_object().unwrap().keys() {
+            assert!(
+                value.get(property).is_some(),
+                "{}.{} is described but never sent",
+                name,
+                property
+            );
+        }
+    }
+}
+
+#[test]
+fn test_schemas_describe_requests() {
+    use crate::api::http::{
+        AuthManager, ManagerEmail, RegisterPartitions, TransferDatasets, UpdateDataset,
+        UpdateManager,
+    };
+    use crate::dict::{Classification, Compression, DatasetConfig, Format, Sensitivity};
+    use crate::dict::{SortKey, SortOrder};
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    fn accepts<T: DeserializeOwned>(schemas: &Value, name: &str) {
+        let value = example(&schemas[name], schemas);
+        if let Err(e) = serde_json::from_value::<T>(value) {
+            panic!("an example of {} isn't accepted: {}", name, e);
+        }
+    }
+    // every value of an enum is accepted, and sent back unchanged
+    fn round_trips<T: DeserializeOwned + Serialize>(schemas: &Value, name: &str) {
+        for value in schemas[name]["enum"].as_array().unwrap() {
+            let parsed: T = serde_json::from_value(value.clone()).unwrap();
+            assert_eq!(&json!(parsed), value, "{} doesn't round trip", name);
+        }
+    }
+
+    let schemas = schemas();
+    accepts::<AuthManager>(&schemas, "AuthManager");
+    accepts::<UpdateManager>(&schemas, "UpdateManager");
+    accepts::<TransferDatasets>(&schemas, "TransferDatasets");
+    accepts::<ManagerEmail>(&schemas, "ManagerEmail");
+    accepts::<DatasetConfig>(&schemas, "DatasetConfig");
+    accepts::<UpdateDataset>(&schemas, "UpdateDataset");
+    accepts::<RegisterPartitions>(&schemas, "RegisterPartitions");
+
+    round_trips::<Classification>(&schemas, "Classification");
+    round_trips::<Compression>(&schemas, "Compression");
+    round_trips::<Format>(&schemas, "Format");
+    round_trips::<Sensitivity>(&schemas, "Sensitivity");
+    round_trips::<SortKey>(&schemas, "SortKey");
+    round_trips::<SortOrder>(&schemas, "SortOrder");
+}
@@ -0,0 +1,273 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::api::routes;
+use crate::error::{ErrorBody, ErrorKind};
+
+use actix_http::body::{Body, MessageBody, ResponseBody};
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{HeaderMap, HeaderName, HeaderValue},
+    Error, HttpMessage,
+};
+use opentelemetry::{global, propagation::Extractor};
+use tracing_futures::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Correlates a request with the work of other services it's part of. Requests sent without one
+/// are correlated by their request ID.
+pub const CORRELATION_ID_HEADER: &str = "x-correlation-id";
+
+// request IDs provided by clients are only kept if they are reasonably short and printable
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// The RequestId of the request being handled, available from its extensions.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// RequestIds is middleware which identifies every request, either by the `X-Request-Id` header
+/// it was sent with or by a new UUID. The ID is returned in the same header of the response, and
+/// in the `request_id` of every error response.
+///
+/// Each request is handled within an `http_request` span, which records its request and
+/// correlation IDs, method, route and status. The span continues the trace of a W3C `traceparent`
+/// header the request was sent with, if any.
+pub struct RequestIds;
+
+impl<S, B> Transform<S> for RequestIds
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestIdMiddleware<S>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Transform, Self::InitError>>>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Box::pin(async move { Ok(RequestIdMiddleware { service }) })
+    }
+}
+
+pub struct RequestIdMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service for RequestIdMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|id| valid_request_id(id))
+            .map(String::from)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        let correlation_id = req
+            .headers()
+            .get(CORRELATION_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|id| valid_request_id(id))
+            .map(String::from)
+            .unwrap_or_else(|| id.clone());
+        req.extensions_mut().insert(RequestId(id.clone()));
+
+        let span = tracing::info_span!(
+            "http_request",
+            request_id = %id,
+            correlation_id = %correlation_id,
+            method = %req.method(),
+            route = %routes::route_pattern(req.method().as_str(), req.path())
+                .unwrap_or_else(|| routes::UNMATCHED.into()),
+            status = tracing::field::Empty,
+        );
+        span.set_parent(global::get_text_map_propagator(|propagator| {
+            propagator.extract(&HeaderExtractor(req.headers()))
+        }));
+
+        let fut = span.in_scope(|| self.service.call(req));
+        let request_span = span.clone();
+        Box::pin(
+            async move {
+                let res = fut.await;
+                let status = match &res {
+                    Ok(res) => res.status(),
+                    Err(e) => e.as_response_error().status_code(),
+                };
+                request_span.record("status", status.as_u16());
+                tracing::info!(status = status.as_u16(), "request handled");
+
+                let mut res = res?;
+                for (header, value) in &[
+                    (REQUEST_ID_HEADER, &id),
+                    (CORRELATION_ID_HEADER, &correlation_id),
+                ] {
+                    if let Ok(value) = HeaderValue::from_str(value) {
+                        res.headers_mut()
+                            .insert(HeaderName::from_static(header), value);
+                    }
+                }
+
+                // errors which weren't responded to with an ErrorBody (e.g. those of extractors) are
+                // given one, so that every failure has the same shape
+                let body = res
+                    .response()
+                    .extensions()
+                    .get::<ErrorBody>()
+                    .cloned()
+                    .or_else(|| {
+                        res.response().error().map(|e| {
+                            let status = res.status();
+                            let mut body =
+                                ErrorBody::new(ErrorKind::from_status(status), e.to_string());
+                            body.code = status.as_u16();
+                            body.status = status.canonical_reason().unwrap_or_default();
+                            body
+                        })
+                    });
+
+                Ok(match body {
+                    Some(mut body) => {
+                        body.request_id = Some(id);
+                        let json = serde_json::to_vec(&body).unwrap_or_default();
+                        res.map_body(|head, _| {
+                            head.headers.insert(
+                                actix_web::http::header::CONTENT_TYPE,
+                                HeaderValue::from_static("application/json"),
+                            );
+                            ResponseBody::Other(Body::from(json))
+                        })
+                    }
+                    None => res.map_body(|_, body| ResponseBody::Other(Body::from_message(body))),
+                })
+            }
+            .instrument(span),
+        )
+    }
+}
+
+// reads the `traceparent` of a request, so that its span continues the caller's trace
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl<'a> Extractor for HeaderExtractor<'a> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+fn valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+#[test]
+fn test_valid_request_id() {
+    assert!(valid_request_id("4f1c6c1e-6a5e-4a43-9d43-0b4b3e1a2c9f"));
+    assert!(valid_request_id("lb.1234_abc"));
+    assert!(!valid_request_id(""));
+    assert!(!valid_request_id("has spaces"));
+    assert!(!valid_request_id("line\nbreak"));
+    assert!(!valid_request_id(&"a".repeat(MAX_REQUEST_ID_LEN + 1)));
+}
+
+#[actix_rt::test]
+async fn test_request_ids() {
+    use actix_web::{test, web, App};
+
+    async fn fails() -> Result<String, crate::error::Error> {
+        Err(crate::error::Error::InputValidation("bad input".into()))
+    }
+
+    let mut app = test::init_service(
+        App::new()
+            .wrap(RequestIds)
+            .route("/fails", web::get().to(fails))
+            .route("/ok", web::get().to(|| async { "ok" }))
+            .route(
+                "/json",
+                web::post()
+                    .to(|body: web::Json<serde_json::Value>| async move { body.to_string() }),
+            ),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/fails")
+        .header(REQUEST_ID_HEADER, "abc-123")
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert_eq!(resp.status(), 400);
+    assert_eq!(resp.headers().get(REQUEST_ID_HEADER).unwrap(), "abc-123");
+    let body: serde_json::Value = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+    assert_eq!(body["error_code"], "validation_failed");
+    assert_eq!(body["message"], "bad input");
+    assert_eq!(body["request_id"], "abc-123");
+
+    // invalid IDs are replaced, and successful responses are left alone
+    let req = test::TestRequest::get()
+        .uri("/ok")
+        .header(REQUEST_ID_HEADER, "not valid")
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert_eq!(resp.status(), 200);
+    let id = resp
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert!(Uuid::parse_str(id).is_ok());
+    // requests without a correlation ID are correlated by their request ID
+    assert_eq!(resp.headers().get(CORRELATION_ID_HEADER).unwrap(), id);
+    assert_eq!(test::read_body(resp).await, "ok");
+
+    let req = test::TestRequest::get()
+        .uri("/ok")
+        .header(CORRELATION_ID_HEADER, "upstream-456")
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert_eq!(
+        resp.headers().get(CORRELATION_ID_HEADER).unwrap(),
+        "upstream-456"
+    );
+
+    // extractor errors are given the same shape
+    let req = test::TestRequest::post()
+        .uri("/json")
+        .header("content-type", "application/json")
+        .set_payload("{")
+        .to_request();
+    let resp = test::call_service(&mut app, req).await;
+    assert_eq!(resp.status(), 400);
+    let id = resp.headers().get(REQUEST_ID_HEADER).unwrap().clone();
+    let body: serde_json::Value = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+    assert_eq!(body["error_code"], "validation_failed");
+    assert_eq!(body["request_id"], id.to_str().unwrap());
+}
@@ -0,0 +1,155 @@
+use crate::api::http;
+
+use actix_web::{dev::ResourceDef, web};
+use lazy_static::lazy_static;
+
+/// The prefix of every route in the current version of the API.
+pub const API_V1: &str = "/api/v1";
+
+/// The prefix routes were served under before the API was versioned, which is kept so that
+/// existing clients continue to work. Its lists respond with the bare array of a page's items,
+/// rather than the page. New clients should use `API_V1`.
+pub const API_UNVERSIONED: &str = "/api";
+
+/// The path Prometheus metrics are served at, which is outside of the API.
+pub const METRICS_PATH: &str = "/metrics";
+
+/// The paths of the liveness and readiness checks, which are outside of the API.
+pub const HEALTH_PATH: &str = "/healthz";
+pub const READY_PATH: &str = "/readyz";
+
+/// Stands in for the pattern of requests which no route serves, so that they're recorded together
+/// rather than by their path.
+pub const UNMATCHED: &str = "unmatched";
+
+// routes which are served outside of the API's prefixes
+const ROOT_PATHS: &[&str] = &[METRICS_PATH, HEALTH_PATH, READY_PATH];
+
+// JSON bodies are limited to 4MiB, which fits the largest bulk registration of partitions
+const MAX_JSON_BODY_BYTES: usize = 4 * 1024 * 1024;
+
+/// Registers every route with the app, under both the versioned and unversioned prefixes.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    let scope = |path| {
+        web::scope(path)
+            .app_data(web::JsonConfig::default().limit(MAX_JSON_BODY_BYTES))
+            .configure(routes)
+    };
+    // the versioned scope must be registered first, since the unversioned prefix also matches it
+    cfg.route(METRICS_PATH, web::get().to(http::metrics))
+        .route(HEALTH_PATH, web::get().to(http::healthz))
+        .route(READY_PATH, web::get().to(http::readyz))
+        .service(scope(API_V1))
+        .service(scope(API_UNVERSIONED));
+}
+
+lazy_static! {
+    // every route's pattern, in the order the routes are registered
+    static ref PATTERNS: Vec<(&'static str, &'static str, ResourceDef)> = ROUTES
+        .iter()
+        .map(|(method, path, _)| (*method, *path, ResourceDef::new(*path)))
+        .collect();
+}
+
+/// Finds the pattern of the route which serves a request, including the prefix it was requested
+/// with, e.g. "/api/v1/dataset/{dataset_name}". Requests which no route serves have no pattern.
+pub fn route_pattern(method: &str, path: &str) -> Option<String> {
+    if let Some(path) = ROOT_PATHS.iter().find(|root| **root == path) {
+        return Some((*path).into());
+    }
+
+    // the versioned prefix is tried first, for the same reason it's registered first
+    [API_V1, API_UNVERSIONED].iter().find_map(|prefix| {
+        if !path.starts_with(prefix) {
+            return None;
+        }
+        let relative = &path[prefix.len()..];
+        PATTERNS
+            .iter()
+            .find(|(route_method, _, def)| {
+                route_method.eq_ignore_ascii_case(method) && def.is_match(relative)
+            })
+            .map(|(_, pattern, _)| format!("{}{}", prefix, pattern))
+    })
+}
+
+// Routes are declared once, so that the server and the OpenAPI spec can't disagree about them:
+// each becomes an entry in `ROUTES`, which the spec is built from, and is registered by `configure`.
+macro_rules! routes {
+    ($($method:ident $path:literal => $handler:ident),* $(,)?) => {
+        /// Every route served by the API, as its lowercase method, path relative to the API's
+        /// prefix, and handler name.
+        pub const ROUTES: &[(&str, &str, &str)] = &[
+            $((stringify!($method), $path, stringify!($handler))),*
+        ];
+
+        fn routes(cfg: &mut web::ServiceConfig) {
+            $(cfg.route($path, web::$method().to(http::$handler));)*
+        }
+    };
+}
+
+routes! {
+    get "/openapi.json" => openapi_spec,
+    post "/manager/register" => register_manager,
+    post "/manager/login" => login_manager,
+    get "/admin/managers" => list_managers,
+    get "/admin/audit" => list_audit_events,
+    get "/admin/access" => list_access_denials,
+    put "/admin/manager/{manager_id}" => update_manager,
+    delete "/admin/manager/{manager_id}" => delete_manager,
+    post "/admin/manager/{manager_id}/transfer" => transfer_manager_datasets,
+    get "/datasets/meta" => list_meta,
+    get "/datasets/search" => search_datasets,
+    get "/datasets/fields" => find_fields,
+    get "/datasets/pii" => list_pii_fields,
+    get "/datasets" => list_datasets,
+    get "/dataset/{dataset_name}/latest" => latest_partition,
+    get "/dataset/{dataset_name}/{partition_name:.*}/download" => download_partition,
+    get "/dataset/{dataset_name}/{partition_name:.*}" => find_partition,
+    get "/dataset/{dataset_name}" => find_dataset,
+    put "/dataset/{dataset_name}" => update_dataset,
+    post "/dataset/register" => register_dataset,
+    post "/dataset/{dataset_name}/partitions" => register_partitions,
+    get "/partitions/{dataset_name}" => list_partitions,
+    put "/sensitivity/{dataset_name}" => update_sensitivity,
+    put "/labels/{dataset_name}" => update_labels,
+    post "/upload/{dataset_name}/{partition_name:.*}" => upload_partition,
+    post "/restore/{dataset_name}" => restore_dataset,
+    post "/restore/{dataset_name}/{partition_name:.*}" => restore_partition,
+    get "/grants/{dataset_name}" => list_read_grants,
+    post "/grants/{dataset_name}" => add_read_grant,
+    delete "/grants/{dataset_name}/{manager_id}" => remove_read_grant,
+    get "/maintainers/{dataset_name}" => list_maintainers,
+    post "/maintainers/{dataset_name}" => add_maintainer,
+    delete "/maintainers/{dataset_name}/{manager_id}" => remove_maintainer,
+    get "/transfers" => list_transfers,
+    get "/transfers/{dataset_name}" => find_dataset_transfer,
+    post "/transfers/{dataset_name}" => request_transfer,
+    post "/transfer/{transfer_id}/accept" => accept_transfer,
+    delete "/transfer/{transfer_id}" => cancel_transfer,
+}
+
+#[test]
+fn test_route_pattern() {
+    assert_eq!(
+        route_pattern("GET", "/api/v1/dataset/example"),
+        Some("/api/v1/dataset/{dataset_name}".into())
+    );
+    assert_eq!(
+        route_pattern("GET", "/api/dataset/example/2020/06/01.csv"),
+        Some("/api/dataset/{dataset_name}/{partition_name:.*}".into())
+    );
+    assert_eq!(
+        route_pattern("POST", "/api/v1/dataset/register"),
+        Some("/api/v1/dataset/register".into())
+    );
+    assert_eq!(
+        route_pattern("DELETE", "/api/v1/transfer/1"),
+        Some("/api/v1/transfer/{transfer_id}".into())
+    );
+    assert_eq!(route_pattern("GET", "/metrics"), Some("/metrics".into()));
+    assert_eq!(route_pattern("GET", "/readyz"), Some("/readyz".into()));
+    assert_eq!(route_pattern("PATCH", "/api/v1/dataset/example"), None);
+    assert_eq!(route_pattern("GET", "/missing"), None);
+}
@@ -1,80 +1,136 @@
 use std::process;
 use std::sync::Arc;
 use std::thread;
+use std::time;
 
 use data_dictionary::api;
 use data_dictionary::bucket::BucketManager;
-use data_dictionary::db::{Db, PoolConfig};
+use data_dictionary::config::Config;
+use data_dictionary::db::Db;
+use data_dictionary::dict::Manager;
 use data_dictionary::error::Error;
-use data_dictionary::pubsub_rt;
+use data_dictionary::gcp_client::GcpClient;
+use data_dictionary::health::Heartbeat;
+use data_dictionary::{pubsub_rt, purge_rt, telemetry};
 
 use actix_cors::Cors;
-use actix_web::{web, App, HttpServer};
+use actix_web::{middleware::Condition, App, HttpServer};
+use chrono::Duration;
 use tokio::runtime::Runtime;
 
 #[actix_rt::main]
 async fn main() -> Result<(), Error> {
-    env_logger::init();
-
-    let cfg = PoolConfig {
-        min_idle: 5,
-        max_size: 30,
+    // every problem with the configuration is reported before anything is started
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
     };
-    let mut db = Db::connect(None, Some(cfg)).await?;
+
+    // spans are flushed to the collector once the telemetry is dropped, as the server exits
+    let _telemetry = telemetry::init(&config.telemetry)?;
+
+    let mut db = Db::connect(&config.database)
+        .await?
+        .with_email_domain(config.managers.email_domain.clone());
     db.migrate().await?;
 
-    let pubsubdb = db.clone();
-    thread::spawn(|| {
-        let runtime = Runtime::new();
-        if let Ok(rt) = runtime {
-            pubsub_rt::start(rt, pubsubdb, 1000)
-        } else {
-            log::error!(
-                "failed to create pubsub runtime, messages will be re-queued: {}",
-                runtime.err().expect("no runtime fail error specified")
-            );
-            process::exit(1);
+    // create the first admin manager, if one does not already exist
+    if let (Some(email), Some(password)) = (
+        &config.managers.admin_email,
+        &config.managers.admin_password,
+    ) {
+        if let Some(admin) = Manager::bootstrap_admin(&mut db, email, password).await? {
+            log::info!("bootstrapped admin manager: {}", admin.email);
         }
-    });
+    }
 
-    let app = HttpServer::new(move || {
-        let bucket_manager = BucketManager::from_env(Default::default());
-        let apidb = db.clone();
+    // the server is only ready while the pubsub runtime is alive and pulling messages
+    let pubsub = if config.features.pubsub {
+        let heartbeat = Heartbeat::new();
+        let pubsubdb = db.clone();
+        let pubsub_heartbeat = heartbeat.clone();
+        let client = GcpClient::new(config.gcp.credentials.as_deref())?;
+        let pubsub_config = config.clone();
+        thread::spawn(|| {
+            let runtime = Runtime::new();
+            if let Ok(rt) = runtime {
+                pubsub_rt::start(rt, pubsubdb, pubsub_heartbeat, client, pubsub_config)
+            } else {
+                log::error!(
+                    "failed to create pubsub runtime, messages will be re-queued: {}",
+                    runtime.err().expect("no runtime fail error specified")
+                );
+                process::exit(1);
+            }
+        });
+        Some(heartbeat)
+    } else {
+        log::warn!("pubsub is disabled, bucket notifications will not be handled");
+        None
+    };
+
+    // deleted datasets and partitions can be restored until their retention period has passed
+    if config.features.purge {
+        let purgedb = db.clone();
+        let retention = Duration::days(config.purge.retention_days);
+        let interval = time::Duration::from_secs(config.purge.interval_secs);
+        thread::spawn(move || match Runtime::new() {
+            Ok(rt) => purge_rt::start(rt, purgedb, retention, interval),
+            Err(e) => {
+                log::error!(
+                    "failed to create purge runtime, deleted rows will not be purged: {}",
+                    e
+                );
+                process::exit(1);
+            }
+        });
+    } else {
+        log::warn!("purge is disabled, deleted rows will not be purged");
+    }
+
+    // the bucket manager is shared by every worker, so that it's only created once
+    let bucket_manager = Arc::new(BucketManager::new(
+        &config,
+        GcpClient::new(config.gcp.credentials.as_deref())?,
+    ));
+    let cors = config.features.cors;
+    let mut app = HttpServer::new(move || {
         App::new()
-            .wrap(Cors::new().send_wildcard().finish())
+            .wrap(api::request_id::RequestIds)
+            .wrap(api::metrics::RequestMetrics)
+            .wrap(Condition::new(cors, Cors::new().send_wildcard().finish()))
             .data(api::Server {
-                db: apidb,
-                bucket_manager: Arc::new(bucket_manager),
+                db: db.clone(),
+                bucket_manager: bucket_manager.clone(),
+                pubsub: pubsub.clone(),
             })
-            .route(
-                "/api/manager/register",
-                web::post().to(api::register_manager),
-            )
-            .route("/api/manager/login", web::post().to(api::login_manager))
-            .route("/api/datasets/meta", web::get().to(api::list_meta))
-            .route("/api/datasets/search", web::get().to(api::search_datasets))
-            .route("/api/datasets", web::get().to(api::list_datasets))
-            .route(
-                "/api/dataset/{dataset_name}/latest",
-                web::get().to(api::latest_partition),
-            )
-            .route(
-                "/api/dataset/{dataset_name}/{partition_name:.*}",
-                web::get().to(api::find_partition),
-            )
-            .route(
-                "/api/dataset/{dataset_name}",
-                web::get().to(api::find_dataset),
-            )
-            .route(
-                "/api/dataset/register",
-                web::post().to(api::register_dataset),
-            )
-            .route(
-                "/api/partitions/{dataset_name}",
-                web::get().to(api::list_partitions),
-            )
+            .configure(api::routes::configure)
     });
-    app.bind("127.0.0.1:8080")?.run().await?;
+    if let Some(workers) = config.server.workers {
+        app = app.workers(workers);
+    }
+
+    match &config.server.tls {
+        #[cfg(feature = "tls")]
+        Some(tls) => {
+            use openssl::ssl::{SslAcceptor, SslFiletype, SslMethod};
+
+            let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())
+                .map_err(|e| Error::Generic(Box::new(e)))?;
+            builder
+                .set_private_key_file(&tls.key, SslFiletype::PEM)
+                .map_err(|e| Error::Generic(Box::new(e)))?;
+            builder
+                .set_certificate_chain_file(&tls.cert)
+                .map_err(|e| Error::Generic(Box::new(e)))?;
+            app.bind_openssl(&config.server.bind, builder)?
+                .run()
+                .await?;
+        }
+        _ => app.bind(&config.server.bind)?.run().await?,
+    }
     Ok(())
 }
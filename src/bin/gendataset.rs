@@ -1,4 +1,4 @@
-use data_dictionary::{db::Db, dict::DatasetConfig, dict::Manager, error::Error};
+use data_dictionary::{config::Config, db::Db, dict::DatasetConfig, dict::Manager, error::Error};
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
@@ -9,7 +9,7 @@ async fn main() -> Result<(), Error> {
         .parse()
         .unwrap();
 
-    let mut db = Db::connect(None, None).await?;
+    let mut db = Db::connect(&Config::from_env()?.database).await?;
     db.migrate().await?;
 
     if let Ok(manager) = Manager::authenticate(&mut db, email, password).await {
@@ -1,11 +1,15 @@
 use std::collections::HashMap;
-use std::env;
 
-use crate::dict::{Classification, DatasetConfig};
+use crate::config::Config;
+use crate::dict::{partition_ext, Classification, Compression, Dataset, DatasetConfig, Format};
 use crate::error::Error;
 use crate::gcp_client::GcpClient;
+use crate::signed_url::{SignedUrl, UrlSigner};
 use crate::util;
 
+use chrono::{Duration, Utc};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+
 use reqwest::{
     header::{HeaderMap, CONTENT_TYPE},
     Method, StatusCode,
@@ -18,33 +22,133 @@ pub struct BucketManager {
     bucket_name_restricted: String,
     bucket_name_confidential: String,
     client: GcpClient,
+    signer: Option<UrlSigner>,
 }
 
 impl BucketManager {
-    pub fn from_env(client: GcpClient) -> Self {
-        // TODO: clean this up, it has become disgusting...
+    /// Creates a manager of the buckets in the storage configuration provided, which signs URLs
+    /// with the service account key of the GCP configuration, if it has one.
+    pub fn new(config: &Config, client: GcpClient) -> Self {
+        let storage = &config.storage;
         Self {
-            service_endpoint: env::var("DD_STORAGE_SERVICE")
-                .expect("DD_STORAGE_SERVICE environment variable not set"),
-            bucket_name_internal: env::var("DD_BUCKET_NAME_PRIVATE")
-                .expect("DD_BUCKET_NAME_PRIVATE environment variable not set"),
-            bucket_name_public: env::var("DD_BUCKET_NAME_PUBLIC")
-                .expect("DD_BUCKET_NAME_PUBLIC environment variable not set"),
-            bucket_name_restricted: env::var("DD_BUCKET_NAME_RESTRICTED")
-                .expect("DD_BUCKET_NAME_RESTRICTED environment variable not set"),
-            bucket_name_confidential: env::var("DD_BUCKET_NAME_CONFIDENTIAL")
-                .expect("DD_BUCKET_NAME_CONFIDENTIAL environment variable not set"),
+            service_endpoint: storage.service.clone(),
+            bucket_name_internal: storage.buckets.private.clone(),
+            bucket_name_public: storage.buckets.public.clone(),
+            bucket_name_restricted: storage.buckets.restricted.clone(),
+            bucket_name_confidential: storage.buckets.confidential.clone(),
             client,
+            // signing requires a service account key, which isn't available to every environment
+            signer: match &config.gcp.credentials {
+                Some(path) => match UrlSigner::from_file(path) {
+                    Ok(signer) => Some(signer),
+                    Err(e) => {
+                        log::warn!("signed URLs are disabled, invalid service account key: {}", e);
+                        None
+                    }
+                },
+                None => {
+                    log::warn!("signed URLs are disabled, no service account key configured");
+                    None
+                }
+            },
         }
     }
 
-    pub async fn register_dataset(&self, config: &DatasetConfig) -> Result<(), Error> {
-        let bucket = match config.classification {
+    /// Returns the name of the bucket which stores datasets of the classification provided.
+    pub fn bucket_name(&self, classification: &Classification) -> &str {
+        match classification {
             Classification::Internal => &self.bucket_name_internal,
             Classification::Public => &self.bucket_name_public,
             Classification::Restricted => &self.bucket_name_restricted,
             Classification::Confidential => &self.bucket_name_confidential,
-        };
+        }
+    }
+
+    /// Creates a signed URL to download a partition of the dataset, which expires after `ttl`. The
+    /// `ttl` may not exceed the limit of the dataset's classification.
+    pub fn signed_download_url(
+        &self,
+        dataset: &Dataset,
+        partition_name: &str,
+        ttl: Duration,
+    ) -> Result<SignedUrl, Error> {
+        check_signed_url_ttl(dataset, ttl)?;
+        self.signer()?.sign(
+            "GET",
+            self.bucket_name(&dataset.classification),
+            &format!("{}/{}", dataset.name, partition_name),
+            &[],
+            ttl,
+            Utc::now(),
+        )
+    }
+
+    /// Creates a signed URL to start a resumable upload of a partition of the dataset, which
+    /// expires after `ttl`. The `ttl` may not exceed the limit of the dataset's classification.
+    pub fn signed_upload_url(
+        &self,
+        dataset: &Dataset,
+        partition_name: &str,
+        ttl: Duration,
+    ) -> Result<SignedUrl, Error> {
+        check_signed_url_ttl(dataset, ttl)?;
+        let object = partition_object_name(
+            &dataset.name,
+            &dataset.format,
+            &dataset.compression,
+            partition_name,
+        )?;
+        // reject names which the partition couldn't be registered with once its upload completes
+        dataset.partition_key(&object[dataset.name.len() + 1..])?;
+        self.signer()?.sign(
+            "POST",
+            self.bucket_name(&dataset.classification),
+            &object,
+            &[("x-goog-resumable", "start")],
+            ttl,
+            Utc::now(),
+        )
+    }
+
+    /// Checks that every bucket can be reached with the manager's credentials, by listing at most
+    /// one of its objects.
+    pub async fn check_buckets(&self) -> Result<(), Error> {
+        for bucket in &[
+            &self.bucket_name_internal,
+            &self.bucket_name_public,
+            &self.bucket_name_restricted,
+            &self.bucket_name_confidential,
+        ] {
+            let url = format!(
+                "{}/storage/v1/b/{}/o?maxResults=1&fields=kind",
+                self.service_endpoint, bucket
+            );
+            let resp = self
+                .client
+                .request(Method::GET, &url)?
+                .send()
+                .await
+                .map_err(|e| Error::storage_source("failed to make storage request", e))?;
+            if !resp.status().is_success() {
+                return Err(Error::storage(format!(
+                    "failed to access GCP bucket '{}', status code: {}",
+                    bucket,
+                    resp.status()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn signer(&self) -> Result<&UrlSigner, Error> {
+        self.signer
+            .as_ref()
+            .ok_or_else(|| Error::Auth("a service account key is required to sign URLs".into()))
+    }
+
+    pub async fn register_dataset(&self, config: &DatasetConfig) -> Result<(), Error> {
+        let bucket = self.bucket_name(&config.classification);
         let url = format!(
             "{}/upload/storage/v1/b/{}/o?uploadType=media&name={}",
             self.service_endpoint,
@@ -58,14 +162,14 @@ impl BucketManager {
                 .parse()
                 .expect("failed to parse content-type header from sting"),
         );
-        let data = serde_json::to_vec(config).map_err(|e| Error::Generic(Box::new(e)))?;
+        let data = serde_json::to_vec(config)?;
         let resp = self
             .client
             .request(Method::POST, &url)?
             .body(data)
             .send()
             .await
-            .map_err(|e| Error::Generic(Box::new(e)))?;
+            .map_err(|e| Error::storage_source("failed to make storage request", e))?;
 
         match resp.status() {
             StatusCode::OK => Ok(()),
@@ -81,7 +185,7 @@ impl BucketManager {
                     resp.status()
                 );
                 log::error!("{}", &msg);
-                Err(Error::Http(msg))
+                Err(Error::storage(msg))
             }
             _ => {
                 let msg = format!(
@@ -89,12 +193,135 @@ impl BucketManager {
                     resp.status()
                 );
                 log::error!("{}", &msg);
-                Err(Error::Http(msg))
+                Err(Error::storage(msg))
+            }
+        }
+    }
+
+    /// Deletes the configuration of a dataset from GCS, which undoes `register_dataset`. A
+    /// configuration which doesn't exist is already deleted.
+    pub async fn delete_dataset(&self, config: &DatasetConfig) -> Result<(), Error> {
+        let bucket = self.bucket_name(&config.classification);
+        let object = format!("{}/{}", &config.name, util::FILENAME_DD_JSON);
+        let url = format!(
+            "{}/storage/v1/b/{}/o/{}",
+            self.service_endpoint,
+            bucket,
+            utf8_percent_encode(&object, NON_ALPHANUMERIC),
+        );
+        let resp = self
+            .client
+            .request(Method::DELETE, &url)?
+            .send()
+            .await
+            .map_err(|e| Error::storage_source("failed to make storage request", e))?;
+
+        match resp.status() {
+            StatusCode::OK | StatusCode::NO_CONTENT | StatusCode::NOT_FOUND => Ok(()),
+            StatusCode::FORBIDDEN => {
+                let msg = "forbidden: invalid credentials for GCP bucket manager".into();
+                log::error!("{}", &msg);
+                Err(Error::Auth(msg))
+            }
+            _ => {
+                let msg = format!(
+                    "failed to delete '{}' from GCP bucket, status code: {}",
+                    object,
+                    resp.status()
+                );
+                log::error!("{}", &msg);
+                Err(Error::storage(msg))
             }
         }
     }
 }
 
+fn check_signed_url_ttl(dataset: &Dataset, ttl: Duration) -> Result<(), Error> {
+    let max_ttl = dataset.classification.max_signed_url_ttl();
+    if ttl > max_ttl {
+        return Err(Error::InputValidation(format!(
+            "signed URLs for {} datasets must expire within {} seconds",
+            dataset.classification,
+            max_ttl.num_seconds()
+        )));
+    }
+
+    Ok(())
+}
+
+// Names the object of a dataset's partition, which ends with the extension of the dataset's format
+// and compression, e.g. "example_dataset/2020/06/01.json.tar.gz". The extension is only added if the
+// partition name doesn't already end with it.
+fn partition_object_name(
+    dataset_name: &str,
+    format: &Format,
+    compression: &Compression,
+    partition_name: &str,
+) -> Result<String, Error> {
+    let partition_name = partition_name.trim_matches('/');
+    if partition_name == util::FILENAME_DD_JSON
+        || partition_name
+            .split('/')
+            .any(|part| part.is_empty() || part == "." || part == "..")
+    {
+        return Err(Error::InputValidation(format!(
+            "invalid partition name '{}'",
+            partition_name
+        )));
+    }
+
+    let ext = partition_ext(format, compression);
+    if partition_name.ends_with(&format!(".{}", ext)) {
+        Ok(format!("{}/{}", dataset_name, partition_name))
+    } else {
+        Ok(format!("{}/{}.{}", dataset_name, partition_name, ext))
+    }
+}
+
+#[test]
+fn test_partition_object_name() {
+    let tests = &[
+        (
+            "2020/06/01",
+            Format::Csv,
+            Compression::Gzip,
+            "ds/2020/06/01.csv.tar.gz",
+        ),
+        (
+            "/2020/06/01/",
+            Format::Json,
+            Compression::Uncompressed,
+            "ds/2020/06/01.json",
+        ),
+        (
+            "part.pb.zip",
+            Format::Protobuf,
+            Compression::Zip,
+            "ds/part.pb.zip",
+        ),
+        (
+            "part.json",
+            Format::Json,
+            Compression::Zip,
+            "ds/part.json.json.zip",
+        ),
+    ];
+    for (partition_name, format, compression, expected) in tests {
+        assert_eq!(
+            partition_object_name("ds", format, compression, partition_name).unwrap(),
+            *expected
+        );
+    }
+
+    for partition_name in &["", "/", "dd.json", "a//b", "../other_dataset/part", "a/./b"] {
+        assert!(
+            partition_object_name("ds", &Format::Csv, &Compression::Gzip, partition_name).is_err(),
+            "{}",
+            partition_name
+        );
+    }
+}
+
 impl From<&BucketManager> for HashMap<Classification, String> {
     fn from(bm: &BucketManager) -> Self {
         let mut buckets = HashMap::new();
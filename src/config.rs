@@ -0,0 +1,598 @@
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::error::{Context, Error};
+use crate::health::PUBSUB_STALE_AFTER_SECS;
+use crate::telemetry::Secret;
+
+use serde::Deserialize;
+use tracing_subscriber::EnvFilter;
+
+/// The environment variable holding the path of the TOML file which configuration is loaded from.
+pub const CONFIG_PATH_VAR: &str = "DD_CONFIG";
+
+/// Config is the configuration of every subsystem of the server. It's loaded from a TOML file (see
+/// `data-dictionary.example.toml`), whose values can each be overridden by an environment
+/// variable, and any value which is left out of both takes its default.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub gcp: GcpConfig,
+    pub storage: StorageConfig,
+    pub pubsub: PubsubConfig,
+    pub managers: ManagersConfig,
+    pub purge: PurgeConfig,
+    pub telemetry: TelemetryConfig,
+    pub features: Features,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// The address the API is served on, e.g. "127.0.0.1:8080".
+    pub bind: String,
+    /// The number of HTTP workers, which is the number of CPUs unless set.
+    pub workers: Option<usize>,
+    /// Serves the API over HTTPS when set, which requires the `tls` feature.
+    pub tls: Option<TlsConfig>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: "127.0.0.1:8080".into(),
+            workers: None,
+            tls: None,
+        }
+    }
+}
+
+/// The PEM encoded certificate chain and private key the API is served with over HTTPS.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// Connection parameters, e.g. "host=127.0.0.1 user=postgres port=5432", which are redacted
+    /// from logs since they may include a password.
+    pub params: Secret<String>,
+    pub min_idle: u32,
+    pub max_size: u32,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            params: Secret("host=127.0.0.1 user=postgres port=5432".into()),
+            min_idle: 5,
+            max_size: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GcpConfig {
+    pub project_id: String,
+    /// The path of a service account key, which authenticates requests to Google Cloud and signs
+    /// partition URLs. Requests are otherwise authenticated by the environment's default
+    /// credentials, and signed URLs are disabled.
+    pub credentials: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// The URL of the Cloud Storage service, e.g. "https://storage.googleapis.com".
+    pub service: String,
+    pub buckets: BucketsConfig,
+}
+
+/// The names of the buckets which store the datasets of each classification.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BucketsConfig {
+    pub private: String,
+    pub public: String,
+    pub restricted: String,
+    pub confidential: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PubsubConfig {
+    /// The URL of the global or region-specific Pub/Sub service, e.g.
+    /// "https://pubsub.googleapis.com".
+    pub service: String,
+    pub topic: String,
+    pub subscription: String,
+    /// The most messages pulled at once.
+    pub max_messages: usize,
+    /// How long to wait before each pull, in milliseconds, which must be less than a minute for
+    /// the runtime to be considered alive between pulls.
+    pub pull_delay_ms: u64,
+}
+
+impl Default for PubsubConfig {
+    fn default() -> Self {
+        Self {
+            service: String::new(),
+            topic: String::new(),
+            subscription: String::new(),
+            max_messages: 10,
+            pull_delay_ms: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ManagersConfig {
+    /// Managers may only register with an email address of this domain, e.g. "example.com", when
+    /// set.
+    pub email_domain: Option<String>,
+    /// The first admin manager is created (or promoted) at startup when no admin exists, if both
+    /// its email and password are set.
+    pub admin_email: Option<String>,
+    pub admin_password: Option<Secret<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PurgeConfig {
+    /// How many days a deleted dataset or partition can be restored for before it's purged.
+    pub retention_days: i64,
+    /// How often deleted rows past their retention are purged, in seconds.
+    pub interval_secs: u64,
+}
+
+impl Default for PurgeConfig {
+    fn default() -> Self {
+        Self {
+            retention_days: 30,
+            interval_secs: 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetryConfig {
+    /// The filter of the logs written, e.g. "info,data_dictionary=debug".
+    pub log_filter: String,
+    /// The OTLP endpoint spans are exported to, e.g. "http://localhost:4317". Spans aren't
+    /// exported unless it's set.
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            log_filter: "info".into(),
+            otlp_endpoint: None,
+            service_name: "data-dictionary".into(),
+        }
+    }
+}
+
+/// Features are the subsystems which can be turned off, e.g. to run the API without the background
+/// runtimes in development.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Features {
+    /// Keeps the catalog in sync with the buckets by handling their notifications from Pub/Sub.
+    pub pubsub: bool,
+    /// Purges deleted datasets and partitions once their retention has passed.
+    pub purge: bool,
+    /// Allows cross-origin requests from any origin.
+    pub cors: bool,
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Self {
+            pubsub: true,
+            purge: true,
+            cors: true,
+        }
+    }
+}
+
+impl Config {
+    /// Loads the configuration from the TOML file at the path set in `DD_CONFIG`, if any, applies
+    /// the overrides of the environment, and validates the result.
+    pub fn load() -> Result<Self, Error> {
+        let mut config = match env::var(CONFIG_PATH_VAR) {
+            Ok(path) if !path.is_empty() => Self::from_file(path)?,
+            _ => Self::default(),
+        };
+        config.apply_env(|name| env::var(name).ok())?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Creates the default configuration with the overrides of the environment applied, without
+    /// validating it, e.g. for tests which only use some of the subsystems.
+    pub fn from_env() -> Result<Self, Error> {
+        let mut config = Self::default();
+        config.apply_env(|name| env::var(name).ok())?;
+
+        Ok(config)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let toml = fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file '{}'", path.display()))?;
+        Self::from_toml(&toml)
+            .with_context(|| format!("failed to load config file '{}'", path.display()))
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Self, Error> {
+        toml::from_str(toml).map_err(|e| Error::Config(e.to_string()))
+    }
+
+    /// Overrides the values of the configuration with the environment variables which are set,
+    /// where `var` looks up a variable. Variables which are empty are considered unset.
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), Error> {
+        let mut env = Env {
+            var,
+            problems: vec![],
+        };
+
+        env.parse("DD_BIND_ADDRESS", &mut self.server.bind);
+        env.optional("DD_SERVER_WORKERS", &mut self.server.workers);
+        let (cert, key) = (env.get("DD_TLS_CERT"), env.get("DD_TLS_KEY"));
+        if cert.is_some() || key.is_some() {
+            let tls = self.server.tls.get_or_insert_with(TlsConfig::default);
+            if let Some(cert) = cert {
+                tls.cert = cert.into();
+            }
+            if let Some(key) = key {
+                tls.key = key.into();
+            }
+        }
+
+        env.parse("DD_DATABASE_PARAMS", &mut self.database.params.0);
+        env.parse("DD_DATABASE_MIN_IDLE", &mut self.database.min_idle);
+        env.parse("DD_DATABASE_MAX_SIZE", &mut self.database.max_size);
+
+        env.parse("DD_GCP_PROJECT_ID", &mut self.gcp.project_id);
+        env.optional("GOOGLE_APPLICATION_CREDENTIALS", &mut self.gcp.credentials);
+
+        env.parse("DD_STORAGE_SERVICE", &mut self.storage.service);
+        env.parse("DD_BUCKET_NAME_PRIVATE", &mut self.storage.buckets.private);
+        env.parse("DD_BUCKET_NAME_PUBLIC", &mut self.storage.buckets.public);
+        env.parse(
+            "DD_BUCKET_NAME_RESTRICTED",
+            &mut self.storage.buckets.restricted,
+        );
+        env.parse(
+            "DD_BUCKET_NAME_CONFIDENTIAL",
+            &mut self.storage.buckets.confidential,
+        );
+
+        env.parse("DD_PUBSUB_SERVICE", &mut self.pubsub.service);
+        env.parse("DD_TOPIC_NAME", &mut self.pubsub.topic);
+        env.parse("DD_SUBSCRIPTION_NAME", &mut self.pubsub.subscription);
+        env.parse("DD_TOPIC_MAX_MESSAGES", &mut self.pubsub.max_messages);
+        env.parse("DD_PULL_DELAY_MS", &mut self.pubsub.pull_delay_ms);
+
+        env.optional("DD_MANAGER_EMAIL_DOMAIN", &mut self.managers.email_domain);
+        env.optional("DD_ADMIN_EMAIL", &mut self.managers.admin_email);
+        if let Some(password) = env.get("DD_ADMIN_PASSWORD") {
+            self.managers.admin_password = Some(Secret(password));
+        }
+
+        env.parse("DD_DELETED_RETENTION_DAYS", &mut self.purge.retention_days);
+        env.parse("DD_PURGE_INTERVAL_SECS", &mut self.purge.interval_secs);
+
+        env.parse("RUST_LOG", &mut self.telemetry.log_filter);
+        env.optional(
+            "OTEL_EXPORTER_OTLP_ENDPOINT",
+            &mut self.telemetry.otlp_endpoint,
+        );
+        env.optional(
+            "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+            &mut self.telemetry.otlp_endpoint,
+        );
+        env.parse("OTEL_SERVICE_NAME", &mut self.telemetry.service_name);
+
+        env.parse("DD_FEATURE_PUBSUB", &mut self.features.pubsub);
+        env.parse("DD_FEATURE_PURGE", &mut self.features.purge);
+        env.parse("DD_FEATURE_CORS", &mut self.features.cors);
+
+        report(env.problems)
+    }
+
+    /// Checks that every value required by the enabled subsystems is set and valid, describing
+    /// each problem found in the error returned.
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut problems = vec![];
+        let mut require = |key: &str, var: &str, value: &str| {
+            if value.trim().is_empty() {
+                problems.push(format!("{} (or {}) is required", key, var));
+            }
+        };
+
+        require(
+            "database.params",
+            "DD_DATABASE_PARAMS",
+            &self.database.params.0,
+        );
+        require(
+            "storage.service",
+            "DD_STORAGE_SERVICE",
+            &self.storage.service,
+        );
+        let buckets = &self.storage.buckets;
+        require(
+            "storage.buckets.private",
+            "DD_BUCKET_NAME_PRIVATE",
+            &buckets.private,
+        );
+        require(
+            "storage.buckets.public",
+            "DD_BUCKET_NAME_PUBLIC",
+            &buckets.public,
+        );
+        require(
+            "storage.buckets.restricted",
+            "DD_BUCKET_NAME_RESTRICTED",
+            &buckets.restricted,
+        );
+        require(
+            "storage.buckets.confidential",
+            "DD_BUCKET_NAME_CONFIDENTIAL",
+            &buckets.confidential,
+        );
+        if self.features.pubsub {
+            require("gcp.project_id", "DD_GCP_PROJECT_ID", &self.gcp.project_id);
+            require("pubsub.service", "DD_PUBSUB_SERVICE", &self.pubsub.service);
+            require("pubsub.topic", "DD_TOPIC_NAME", &self.pubsub.topic);
+            require(
+                "pubsub.subscription",
+                "DD_SUBSCRIPTION_NAME",
+                &self.pubsub.subscription,
+            );
+        }
+
+        if self.server.bind.parse::<SocketAddr>().is_err() {
+            problems.push(format!(
+                "server.bind must be an address such as \"127.0.0.1:8080\", not \"{}\"",
+                self.server.bind
+            ));
+        }
+        if self.server.workers == Some(0) {
+            problems.push("server.workers must be at least 1".into());
+        }
+        if let Some(tls) = &self.server.tls {
+            if !cfg!(feature = "tls") {
+                problems.push("server.tls requires building with the \"tls\" feature".into());
+            }
+            for (key, path) in &[("server.tls.cert", &tls.cert), ("server.tls.key", &tls.key)] {
+                if !path.is_file() {
+                    problems.push(format!("{} '{}' is not a file", key, path.display()));
+                }
+            }
+        }
+
+        if self.database.max_size == 0 {
+            problems.push("database.max_size must be at least 1".into());
+        }
+        if self.database.min_idle > self.database.max_size {
+            problems.push("database.min_idle must not be greater than database.max_size".into());
+        }
+
+        if let Some(credentials) = &self.gcp.credentials {
+            if !credentials.is_file() {
+                problems.push(format!(
+                    "gcp.credentials '{}' is not a file",
+                    credentials.display()
+                ));
+            }
+        }
+
+        if self.features.pubsub && self.pubsub.max_messages == 0 {
+            problems.push("pubsub.max_messages must be at least 1".into());
+        }
+        // readiness fails when the runtime waits longer than this between pulls
+        let stale_after_ms = PUBSUB_STALE_AFTER_SECS as u64 * 1000;
+        if self.features.pubsub && self.pubsub.pull_delay_ms >= stale_after_ms {
+            problems.push(format!(
+                "pubsub.pull_delay_ms must be less than {}",
+                stale_after_ms
+            ));
+        }
+
+        match (&self.managers.admin_email, &self.managers.admin_password) {
+            (Some(_), None) => {
+                problems.push("managers.admin_password is required with an admin_email".into())
+            }
+            (None, Some(_)) => {
+                problems.push("managers.admin_email is required with an admin_password".into())
+            }
+            _ => {}
+        }
+
+        if self.features.purge {
+            if self.purge.retention_days < 1 {
+                problems.push("purge.retention_days must be at least 1".into());
+            }
+            if self.purge.interval_secs == 0 {
+                problems.push("purge.interval_secs must be at least 1".into());
+            }
+        }
+
+        if let Err(e) = EnvFilter::try_new(&self.telemetry.log_filter) {
+            problems.push(format!("telemetry.log_filter is invalid: {}", e));
+        }
+
+        report(problems)
+    }
+}
+
+// collects the problems of the environment's overrides, so that all of them are reported at once
+struct Env<F> {
+    var: F,
+    problems: Vec<String>,
+}
+
+impl<F: Fn(&str) -> Option<String>> Env<F> {
+    fn get(&self, name: &str) -> Option<String> {
+        (self.var)(name).filter(|value| !value.is_empty())
+    }
+
+    // parses the variable if it's set, recording a problem if it's invalid
+    fn value<T>(&mut self, name: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = self.get(name)?;
+        match value.parse() {
+            Ok(parsed) => Some(parsed),
+            Err(e) => {
+                self.problems
+                    .push(format!("{} is invalid ('{}'): {}", name, value, e));
+                None
+            }
+        }
+    }
+
+    fn parse<T>(&mut self, name: &str, field: &mut T)
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        if let Some(value) = self.value(name) {
+            *field = value;
+        }
+    }
+
+    fn optional<T>(&mut self, name: &str, field: &mut Option<T>)
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        if let Some(value) = self.value(name) {
+            *field = Some(value);
+        }
+    }
+}
+
+// reports every problem found in a single error
+fn report(problems: Vec<String>) -> Result<(), Error> {
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Config(problems.join("; ")))
+    }
+}
+
+#[test]
+fn test_config_from_toml() {
+    let config = Config::from_toml(include_str!("../data-dictionary.example.toml")).unwrap();
+    assert_eq!(config.server.bind, "127.0.0.1:8080");
+    assert!(config.server.tls.is_none());
+    assert_eq!(config.storage.buckets.restricted, "dd-restricted");
+    assert_eq!(config.pubsub.max_messages, 10);
+    assert!(config.features.pubsub);
+    assert!(config.validate().is_ok());
+
+    // sections and values which are left out take their defaults
+    let config = Config::from_toml("[database]\nmax_size = 10").unwrap();
+    assert_eq!(config.database.max_size, 10);
+    assert_eq!(config.database.min_idle, 5);
+    assert_eq!(config.purge.retention_days, 30);
+
+    // keys which aren't known are rejected, rather than silently ignored
+    let err = Config::from_toml("[database]\nmax_sise = 10").unwrap_err();
+    assert!(err.to_string().contains("max_sise"));
+}
+
+#[test]
+fn test_config_env_overrides() {
+    let mut config = Config::from_toml(include_str!("../data-dictionary.example.toml")).unwrap();
+    let vars = [
+        ("DD_BIND_ADDRESS", "0.0.0.0:9000"),
+        ("DD_DATABASE_MAX_SIZE", "50"),
+        ("DD_ADMIN_PASSWORD", "hunter2"),
+        ("DD_FEATURE_PUBSUB", "false"),
+        ("DD_STORAGE_SERVICE", ""),
+    ];
+    let env = |name: &str| {
+        vars.iter()
+            .find(|(var, _)| *var == name)
+            .map(|(_, value)| value.to_string())
+    };
+    config.apply_env(env).unwrap();
+    assert_eq!(config.server.bind, "0.0.0.0:9000");
+    assert_eq!(config.database.max_size, 50);
+    assert_eq!(
+        config.managers.admin_password.as_ref().unwrap().as_ref(),
+        "hunter2"
+    );
+    assert!(!config.features.pubsub);
+    // empty variables don't override the file
+    assert_eq!(config.storage.service, "https://storage.googleapis.com");
+
+    // every invalid variable is reported
+    let vars = [
+        ("DD_DATABASE_MAX_SIZE", "lots"),
+        ("DD_FEATURE_PURGE", "yes"),
+    ];
+    let err = config
+        .apply_env(|name: &str| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        })
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("DD_DATABASE_MAX_SIZE"), "{}", err);
+    assert!(err.contains("DD_FEATURE_PURGE"), "{}", err);
+}
+
+#[test]
+fn test_config_validate() {
+    let err = Config::default().validate().unwrap_err().to_string();
+    assert!(err.contains("storage.service (or DD_STORAGE_SERVICE) is required"));
+    assert!(err.contains("storage.buckets.confidential"));
+    assert!(err.contains("pubsub.topic"));
+
+    let mut config = Config::from_toml(include_str!("../data-dictionary.example.toml")).unwrap();
+    config.server.bind = "localhost".into();
+    config.database.min_idle = 40;
+    config.managers.admin_email = Some("admin@example.com".into());
+    config.pubsub.pull_delay_ms = 60_000;
+    let err = config.validate().unwrap_err().to_string();
+    assert!(err.contains("server.bind"), "{}", err);
+    assert!(err.contains("database.min_idle"), "{}", err);
+    assert!(err.contains("managers.admin_password"), "{}", err);
+    assert!(err.contains("pubsub.pull_delay_ms"), "{}", err);
+
+    // values only required by a disabled feature aren't checked
+    let mut config = Config::from_toml(include_str!("../data-dictionary.example.toml")).unwrap();
+    config.pubsub = PubsubConfig::default();
+    assert!(config.validate().is_err());
+    config.features.pubsub = false;
+    assert!(config.validate().is_ok());
+
+    // secrets are redacted when the configuration is logged
+    config.database.params = Secret("host=db password=hunter2".into());
+    assert!(!format!("{:?}", config).contains("hunter2"));
+}
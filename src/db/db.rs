@@ -1,21 +1,37 @@
-use std::env;
+use std::future::Future;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::sync::Mutex;
 
-use crate::db::range_query::{self, Target};
+use crate::config::DatabaseConfig;
+use crate::db::query::{self, Sorted, Target};
 use crate::db::sql;
+use crate::dict::{validate_labels, validate_sensitivity};
 use crate::dict::{
-    Attributes, Classification, Compression, Dataset, DatasetSchema, Format, Manager, Partition,
-    RangeParams, PARTITION_LATEST,
+    AccessDenial, AccessFilter, Actor, Attributes, AuditEvent, AuditFilter, CatalogStats,
+    Classification, Compression, DataRange, Dataset, DatasetFilter, DatasetLabels, DatasetPage,
+    DatasetSchema, DatasetSensitivity, DatasetTransfer, FieldSearch, Format, Maintainer, Manager,
+    Partition, PartitionFilter, PartitionKey, PartitionPage, PartitionRegistration, PiiField,
+    Purged, RangeParams, ReadGrant, SchemaField, SearchFacets, SearchParams, SearchResults,
+    Sensitivity, PARTITION_LATEST,
 };
-use crate::error::Error;
+use crate::error::{Context, Error};
+use crate::partition_template::PartitionTemplate;
 use crate::service::DataService;
 
 use argon2rs;
 use async_trait::async_trait;
-use bb8_postgres::{bb8::Pool, PostgresConnectionManager};
+use bb8_postgres::{
+    bb8::{Pool, PooledConnection},
+    PostgresConnectionManager,
+};
+use chrono::{DateTime, Utc};
 use log;
-use postgres_types::ToSql;
+use postgres_types::{Json, ToSql};
 use rand::Rng;
-use tokio_postgres::{row::Row, NoTls};
+use serde::Serialize;
+use serde_json::Value;
+use tokio_postgres::{row::Row, NoTls, Transaction};
 use uuid::Uuid;
 
 pub mod migrate {
@@ -23,6 +39,24 @@ pub mod migrate {
     embed!("migrations");
 }
 
+/// The version of the latest migration, which must be applied before the database is used.
+pub const LATEST_MIGRATION: u32 = 12;
+
+#[test]
+fn test_latest_migration() {
+    let latest = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/migrations"))
+        .unwrap()
+        .filter_map(|entry| {
+            let name = entry.unwrap().file_name().into_string().unwrap();
+            name.trim_start_matches('V')
+                .split("__")
+                .next()
+                .and_then(|version| version.parse::<u32>().ok())
+        })
+        .max();
+    assert_eq!(latest, Some(LATEST_MIGRATION));
+}
+
 pub const CHARACTER_SET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
 
 pub fn rand(length: usize, using: String) -> String {
@@ -52,54 +86,311 @@ type DbPool = Pool<PostgresConnectionManager<NoTls>>;
 #[derive(Clone)]
 pub struct Db {
     pub client: DbPool,
+    pub actor: Actor,
+    pub email_domain: Option<String>,
+}
+
+type Compensation = Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
+
+/// The future returned by the function which `Db::transaction` runs.
+pub type TxFuture<'t, T> = Pin<Box<dyn Future<Output = Result<T, Error>> + Send + 't>>;
+
+/// A database transaction, which implements `DataService` so that several of its operations can be
+/// committed or rolled back together. Transactions are run with `Db::transaction`.
+pub struct DbTx<'a> {
+    tx: Transaction<'a>,
+    actor: Actor,
+    email_domain: Option<String>,
+    compensations: Mutex<Vec<Compensation>>,
+}
+
+impl<'a> DbTx<'a> {
+    /// Registers a compensation, which undoes a change made outside of the database if the
+    /// transaction is rolled back. Compensations run in the reverse order they were registered.
+    pub fn on_rollback<F>(&self, compensation: F)
+    where
+        F: Future<Output = Result<(), Error>> + Send + 'static,
+    {
+        self.compensations
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(Box::pin(compensation));
+    }
+}
+
+/// Connect provides the connection, and the actor, which the operations of a `DataService` are
+/// run with.
+#[async_trait]
+pub trait Connect: Send + Sync {
+    fn actor(&self) -> &Actor;
+
+    /// The domain which managers must register with an email address of, if any.
+    fn email_domain(&self) -> Option<&str>;
+
+    async fn conn<'s>(&'s self) -> Result<Conn<'s>, Error>;
+}
+
+#[async_trait]
+impl Connect for Db {
+    fn actor(&self) -> &Actor {
+        &self.actor
+    }
+
+    fn email_domain(&self) -> Option<&str> {
+        self.email_domain.as_deref()
+    }
+
+    async fn conn<'s>(&'s self) -> Result<Conn<'s>, Error> {
+        Ok(Conn::Pooled(self.client.get().await?))
+    }
+}
+
+#[async_trait]
+impl<'a> Connect for DbTx<'a> {
+    fn actor(&self) -> &Actor {
+        &self.actor
+    }
+
+    fn email_domain(&self) -> Option<&str> {
+        self.email_domain.as_deref()
+    }
+
+    async fn conn<'s>(&'s self) -> Result<Conn<'s>, Error> {
+        Ok(Conn::Tx(&self.tx))
+    }
+}
+
+/// A connection taken from the pool, or the open transaction of a `DbTx`.
+pub enum Conn<'a> {
+    Pooled(PooledConnection<'a, PostgresConnectionManager<NoTls>>),
+    Tx(&'a Transaction<'a>),
+}
+
+impl<'a> Conn<'a> {
+    pub async fn query(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, Error> {
+        Ok(match self {
+            Conn::Pooled(conn) => conn.query(statement, params).await?,
+            Conn::Tx(tx) => tx.query(statement, params).await?,
+        })
+    }
+
+    pub async fn query_one(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Row, Error> {
+        Ok(match self {
+            Conn::Pooled(conn) => conn.query_one(statement, params).await?,
+            Conn::Tx(tx) => tx.query_one(statement, params).await?,
+        })
+    }
+
+    pub async fn query_opt(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Option<Row>, Error> {
+        Ok(match self {
+            Conn::Pooled(conn) => conn.query_opt(statement, params).await?,
+            Conn::Tx(tx) => tx.query_opt(statement, params).await?,
+        })
+    }
+
+    pub async fn execute(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, Error> {
+        Ok(match self {
+            Conn::Pooled(conn) => conn.execute(statement, params).await?,
+            Conn::Tx(tx) => tx.execute(statement, params).await?,
+        })
+    }
+
+    /// Begins a transaction on a pooled connection. Within a `DbTx`, its transaction is joined
+    /// instead, and is only committed once every operation of the `DbTx` has succeeded.
+    pub async fn transaction(&mut self) -> Result<Tx<'_>, Error> {
+        Ok(match self {
+            Conn::Pooled(conn) => Tx::Begun(conn.transaction().await?),
+            Conn::Tx(tx) => Tx::Joined(tx),
+        })
+    }
+}
+
+/// A transaction begun by a single operation, or the transaction of a `DbTx` which it joined.
+pub enum Tx<'a> {
+    Begun(Transaction<'a>),
+    Joined(&'a Transaction<'a>),
+}
+
+impl<'a> Tx<'a> {
+    /// Commits a transaction which was begun, while a joined transaction is left to be committed
+    /// by its `DbTx`.
+    pub async fn commit(self) -> Result<(), Error> {
+        match self {
+            Tx::Begun(tx) => Ok(tx.commit().await?),
+            Tx::Joined(_) => Ok(()),
+        }
+    }
+}
+
+impl<'a> Deref for Tx<'a> {
+    type Target = Transaction<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Tx::Begun(tx) => tx,
+            Tx::Joined(tx) => tx,
+        }
+    }
 }
 
-pub struct PoolConfig {
-    pub min_idle: u32,
-    pub max_size: u32,
+#[async_trait]
+trait Audit: Connect {
+    // Records an audit event within the same transaction as the mutation it describes, so that
+    // the event is stored if and only if the mutation is committed.
+    async fn audit(
+        &self,
+        tx: &Transaction<'_>,
+        action: &str,
+        target_type: &str,
+        target: &str,
+        before: Option<Value>,
+        after: Option<Value>,
+    ) -> Result<(), Error> {
+        // managers are recorded by their ID alone, never by their API key, which is a credential
+        let (actor_type, actor_id) = match self.actor() {
+            Actor::System => ("system", None),
+            Actor::Manager { manager_id } => ("manager", Some(manager_id.to_string())),
+            Actor::Pubsub { message_id } => ("pubsub", Some(message_id.clone())),
+        };
+
+        tx.execute(
+            sql::INSERT_AUDIT_EVENT,
+            &[
+                &actor_type,
+                &actor_id,
+                &action,
+                &target_type,
+                &target,
+                &before,
+                &after,
+            ],
+        )
+        .await?;
+
+        Ok(())
+    }
 }
 
+impl<C: Connect> Audit for C {}
+
 impl Db {
-    pub async fn connect(params: Option<String>, cfg: Option<PoolConfig>) -> Result<Self, Error> {
+    pub async fn connect(config: &DatabaseConfig) -> Result<Self, Error> {
         Ok(Db {
-            client: Db::create_pool(params, cfg).await?,
+            client: Db::create_pool(config).await?,
+            actor: Actor::System,
+            email_domain: None,
         })
     }
 
+    /// Restricts the managers registered through the database handle to email addresses of the
+    /// `domain` provided, if any.
+    pub fn with_email_domain(mut self, domain: Option<String>) -> Self {
+        self.email_domain = domain;
+        self
+    }
+
+    /// Creates a copy of the database handle, sharing its connection pool, which attributes every
+    /// mutation it makes to the `actor` provided.
+    pub fn with_actor(&self, actor: Actor) -> Self {
+        Db {
+            client: self.client.clone(),
+            actor,
+            email_domain: self.email_domain.clone(),
+        }
+    }
+
+    /// Runs `f` within a single database transaction, which every operation made through the
+    /// `DbTx` it's given joins. The transaction is committed if `f` succeeds, and is otherwise
+    /// rolled back, after which the compensations `f` registered are run to undo any changes it
+    /// made outside of the database, e.g. to storage.
+    pub async fn transaction<T, F>(&self, f: F) -> Result<T, Error>
+    where
+        T: Send,
+        F: for<'t, 'c> FnOnce(&'t mut DbTx<'c>) -> TxFuture<'t, T> + Send,
+    {
+        let mut conn = self.client.get().await?;
+        let mut tx = DbTx {
+            tx: conn.transaction().await?,
+            actor: self.actor.clone(),
+            email_domain: self.email_domain.clone(),
+            compensations: Mutex::new(vec![]),
+        };
+        let result = f(&mut tx).await;
+
+        let DbTx {
+            tx, compensations, ..
+        } = tx;
+        let result = match result {
+            Ok(value) => tx.commit().await.map(|_| value).map_err(Error::from),
+            Err(e) => {
+                if let Err(rollback_err) = tx.rollback().await {
+                    log::error!("failed to roll back transaction: {}", rollback_err);
+                }
+                Err(e)
+            }
+        };
+        if result.is_err() {
+            let compensations = compensations
+                .into_inner()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            for compensation in compensations.into_iter().rev() {
+                if let Err(e) = compensation.await {
+                    log::error!("failed to compensate for rolled back transaction: {}", e);
+                }
+            }
+        }
+
+        result
+    }
+
     pub async fn migrate(&mut self) -> Result<(), Error> {
         migrate::migrations::runner()
             .run_async(&mut *self.client.get().await?)
             .await
             .map(|_| ())
-            .map_err(|e| Error::Generic(Box::new(e)))
-    }
-
-    pub async fn create_pool(
-        params: Option<String>,
-        cfg: Option<PoolConfig>,
-    ) -> Result<DbPool, Error> {
-        let manager = PostgresConnectionManager::new_from_stringlike(
-            &params.unwrap_or_else(|| {
-                env::var("DD_DATABASE_PARAMS")
-                    .unwrap_or_else(|_| "host=127.0.0.1 user=postgres port=5432".into())
-            }),
-            NoTls,
-        )?;
+            .map_err(Error::from)
+    }
 
-        let mut min_idle: u32 = 1;
-        let mut max_size: u32 = 1;
+    /// Returns the version of the last migration applied to the database, if any have been.
+    pub async fn migration_version(&self) -> Result<Option<u32>, Error> {
+        Ok(migrate::migrations::runner()
+            .get_last_applied_migration_async(&mut *self.client.get().await?)
+            .await?
+            .map(|migration| migration.version()))
+    }
 
-        if let Some(pool_cfg) = cfg {
-            min_idle = pool_cfg.min_idle;
-            max_size = pool_cfg.max_size;
-        }
+    /// Checks that a connection can be taken from the pool and used.
+    pub async fn ping(&self) -> Result<(), Error> {
+        self.client.get().await?.simple_query("SELECT 1").await?;
+        Ok(())
+    }
+
+    pub async fn create_pool(config: &DatabaseConfig) -> Result<DbPool, Error> {
+        let manager =
+            PostgresConnectionManager::new_from_stringlike(config.params.as_ref(), NoTls)?;
 
         Pool::builder()
-            .min_idle(Some(min_idle))
-            .max_size(max_size)
+            .min_idle(Some(config.min_idle))
+            .max_size(config.max_size)
             .build(manager)
             .await
-            .map_err(|e| Error::Generic(Box::new(e)))
+            .context("failed to create database pool")
     }
 }
 
@@ -115,6 +406,15 @@ impl From<&Row> for Dataset {
             format: row.get("dataset_format"),
             description: row.get("dataset_desc"),
             schema: row.get("dataset_schema"),
+            sensitivity: row
+                .try_get::<_, Json<DatasetSensitivity>>("dataset_sensitivity")
+                .map(|sensitivity| sensitivity.0)
+                .unwrap_or_default(),
+            labels: row
+                .try_get::<_, Json<DatasetLabels>>("dataset_labels")
+                .map(|labels| labels.0)
+                .unwrap_or_default(),
+            partition_template: row.try_get("dataset_partition_template").unwrap_or(None),
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
         }
@@ -133,6 +433,15 @@ impl From<Row> for Dataset {
             format: row.get("dataset_format"),
             description: row.get("dataset_desc"),
             schema: row.get("dataset_schema"),
+            sensitivity: row
+                .try_get::<_, Json<DatasetSensitivity>>("dataset_sensitivity")
+                .map(|sensitivity| sensitivity.0)
+                .unwrap_or_default(),
+            labels: row
+                .try_get::<_, Json<DatasetLabels>>("dataset_labels")
+                .map(|labels| labels.0)
+                .unwrap_or_default(),
+            partition_template: row.try_get("dataset_partition_template").unwrap_or(None),
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
         }
@@ -146,6 +455,13 @@ impl From<&Row> for Partition {
             name: row.get("partition_name"),
             url: row.get("partition_url"),
             size: row.get("partition_size"),
+            key: PartitionKey {
+                date: row.get("partition_date"),
+                hour: row.get("partition_hour"),
+                shard: row.get("partition_shard"),
+            },
+            data_start: row.get("partition_data_start"),
+            data_end: row.get("partition_data_end"),
             dataset_id: row.get("dataset_id"),
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
@@ -160,6 +476,13 @@ impl From<Row> for Partition {
             name: row.get("partition_name"),
             url: row.get("partition_url"),
             size: row.get("partition_size"),
+            key: PartitionKey {
+                date: row.get("partition_date"),
+                hour: row.get("partition_hour"),
+                shard: row.get("partition_shard"),
+            },
+            data_start: row.get("partition_data_start"),
+            data_end: row.get("partition_data_end"),
             dataset_id: row.get("dataset_id"),
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
@@ -174,6 +497,7 @@ impl From<Row> for Manager {
             email: row.get("manager_email"),
             api_key: row.get("api_key"),
             admin: row.get("is_admin"),
+            disabled: row.get("is_disabled"),
             salt: row.get("manager_salt"),
             hash: row.get("manager_hash"),
             created_at: row.get("created_at"),
@@ -189,6 +513,7 @@ impl From<&Row> for Manager {
             email: row.get("manager_email"),
             api_key: row.get("api_key"),
             admin: row.get("is_admin"),
+            disabled: row.get("is_disabled"),
             salt: row.get("manager_salt"),
             hash: row.get("manager_hash"),
             created_at: row.get("created_at"),
@@ -225,18 +550,298 @@ impl From<Row> for Attributes {
     }
 }
 
+impl From<&Row> for Maintainer {
+    fn from(row: &Row) -> Self {
+        Self {
+            dataset_id: row.get("dataset_id"),
+            manager_id: row.get("manager_id"),
+            manager_email: row.try_get("manager_email").unwrap_or("".into()),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+impl From<Row> for Maintainer {
+    fn from(row: Row) -> Self {
+        Self::from(&row)
+    }
+}
+
+impl From<&Row> for DatasetTransfer {
+    fn from(row: &Row) -> Self {
+        Self {
+            id: row.get("transfer_id"),
+            dataset_id: row.get("dataset_id"),
+            dataset_name: row.get("dataset_name"),
+            from_manager_id: row.get("from_manager_id"),
+            from_manager_email: row.get("from_manager_email"),
+            to_manager_id: row.get("to_manager_id"),
+            to_manager_email: row.get("to_manager_email"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+impl From<Row> for DatasetTransfer {
+    fn from(row: Row) -> Self {
+        Self::from(&row)
+    }
+}
+
+impl From<&Row> for SchemaField {
+    fn from(row: &Row) -> Self {
+        Self {
+            dataset_id: row.get("dataset_id"),
+            dataset_name: row.get("dataset_name"),
+            schema_version: row.get("schema_version"),
+            latest: row.get("is_latest"),
+            name: row.get("field_name"),
+            field_type: row.get("field_type"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+impl From<Row> for SchemaField {
+    fn from(row: Row) -> Self {
+        Self::from(&row)
+    }
+}
+
+impl From<&Row> for ReadGrant {
+    fn from(row: &Row) -> Self {
+        Self {
+            dataset_id: row.get("dataset_id"),
+            manager_id: row.get("manager_id"),
+            manager_email: row.try_get("manager_email").unwrap_or("".into()),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+impl From<Row> for ReadGrant {
+    fn from(row: Row) -> Self {
+        Self::from(&row)
+    }
+}
+
+impl From<&Row> for AccessDenial {
+    fn from(row: &Row) -> Self {
+        Self {
+            id: row.get("access_id"),
+            manager_id: row.get("manager_id"),
+            remote_addr: row.get("remote_addr"),
+            dataset_name: row.get("dataset_name"),
+            partition_name: row.get("partition_name"),
+            action: row.get("access_action"),
+            reason: row.get("denial_reason"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+impl From<Row> for AccessDenial {
+    fn from(row: Row) -> Self {
+        Self::from(&row)
+    }
+}
+
+impl From<&Row> for PiiField {
+    fn from(row: &Row) -> Self {
+        Self {
+            dataset_id: row.get("dataset_id"),
+            dataset_name: row.get("dataset_name"),
+            classification: row.get("dataset_classification"),
+            field_name: row.get("field_name"),
+            field_type: row.get("field_type"),
+            sensitivity: row.get::<_, Json<Sensitivity>>("sensitivity").0,
+        }
+    }
+}
+
+impl From<Row> for PiiField {
+    fn from(row: Row) -> Self {
+        Self::from(&row)
+    }
+}
+
+impl From<&Row> for AuditEvent {
+    fn from(row: &Row) -> Self {
+        let actor_id: Option<String> = row.get("actor_id");
+        let actor = match row.get("actor_type") {
+            "manager" => Actor::Manager {
+                manager_id: actor_id.and_then(|id| id.parse().ok()).unwrap_or_default(),
+            },
+            "pubsub" => Actor::Pubsub {
+                message_id: actor_id.unwrap_or_default(),
+            },
+            _ => Actor::System,
+        };
+
+        Self {
+            id: row.get("event_id"),
+            actor,
+            action: row.get("event_action"),
+            target_type: row.get("target_type"),
+            target: row.get("target_name"),
+            before: row.get("snapshot_before"),
+            after: row.get("snapshot_after"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+impl From<Row> for AuditEvent {
+    fn from(row: Row) -> Self {
+        Self::from(&row)
+    }
+}
+
+// Audit snapshots are stored as JSON, using the same representation returned by the HTTP API.
+fn snapshot(v: &impl Serialize) -> Option<Value> {
+    serde_json::to_value(v).ok()
+}
+
+// Managers are serialized with their API key, which must never be stored in an audit snapshot.
+fn manager_snapshot(m: &Manager) -> Option<Value> {
+    Some(serde_json::json!({
+        "id": m.id,
+        "email": m.email,
+        "admin": m.admin,
+        "disabled": m.disabled,
+    }))
+}
+
+// Converts a glob-style pattern, where `*` matches any number of characters and `?` matches a single
+// character, into a lowercase LIKE pattern. Characters with a special meaning to LIKE are escaped.
+fn like_pattern(glob: &str) -> String {
+    let mut pattern = String::with_capacity(glob.len());
+    for c in glob.to_lowercase().chars() {
+        match c {
+            '*' => pattern.push('%'),
+            '?' => pattern.push('_'),
+            '%' | '_' | '\\' => {
+                pattern.push('\\');
+                pattern.push(c);
+            }
+            _ => pattern.push(c),
+        }
+    }
+    pattern
+}
+
+#[test]
+fn test_like_pattern() {
+    let cases = &[
+        ("merchant_id", "merchant\\_id"),
+        ("*Email*", "%email%"),
+        ("user_?d", "user\\__d"),
+        ("100%", "100\\%"),
+        ("a\\b", "a\\\\b"),
+    ];
+
+    for case in cases {
+        assert_eq!(like_pattern(case.0), case.1);
+    }
+}
+
+// Converts a search term into a LIKE pattern matching any text which contains it. Characters with a
+// special meaning to LIKE are escaped, so that they only match themselves.
+fn contains_pattern(term: &str) -> String {
+    let mut pattern = String::with_capacity(term.len() + 2);
+    pattern.push('%');
+    for c in term.chars() {
+        if let '%' | '_' | '\\' = c {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+#[test]
+fn test_contains_pattern() {
+    assert_eq!(contains_pattern("orders"), "%orders%");
+    assert_eq!(contains_pattern("user_id"), "%user\\_id%");
+    assert_eq!(contains_pattern("100%"), "%100\\%%");
+    assert_eq!(contains_pattern("a\\b"), "%a\\\\b%");
+}
+
+// Converts a search term into a full-text query, matching documents which contain every word in the
+// term, or a word that it is a prefix of. Any characters other than letters and digits are used to
+// separate words, which also keeps the query free of tsquery operators.
+fn prefix_ts_query(term: &str) -> Option<String> {
+    let words = term
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("{}:*", word.to_lowercase()))
+        .collect::<Vec<String>>();
+
+    if words.is_empty() {
+        None
+    } else {
+        Some(words.join(" & "))
+    }
+}
+
+#[test]
+fn test_prefix_ts_query() {
+    let cases = &[
+        ("team", Some("team:*")),
+        ("teamA", Some("teama:*")),
+        ("merchant churn", Some("merchant:* & churn:*")),
+        ("no_results", Some("no:* & results:*")),
+        ("a|b & !c:*", Some("a:* & b:* & c:*")),
+        ("'(&|!)'", None),
+    ];
+
+    for case in cases {
+        assert_eq!(prefix_ts_query(case.0).as_deref(), case.1);
+    }
+}
+
+fn partition_target(dataset: &Dataset, partition_name: &str) -> String {
+    format!("{}/{}", dataset.name, partition_name)
+}
+
+// Pages are queried with one more result than their count, which is only found if there is a next
+// page. It is removed from the results, and the last result which remains is returned as the cursor
+// for the next page.
+fn next_page<T>(results: &mut Vec<T>, count: Option<i32>) -> Option<&T> {
+    match count {
+        Some(count) if results.len() > count.max(0) as usize => {
+            results.truncate(count.max(0) as usize);
+            results.last()
+        }
+        _ => None,
+    }
+}
+
+#[test]
+fn test_next_page() {
+    let mut results = vec![1, 2, 3];
+    assert_eq!(next_page(&mut results, Some(2)), Some(&2));
+    assert_eq!(results, [1, 2]);
+
+    let mut results = vec![1, 2];
+    assert_eq!(next_page(&mut results, Some(2)), None);
+    assert_eq!(results, [1, 2]);
+    assert_eq!(next_page(&mut results, None), None);
+    assert_eq!(results, [1, 2]);
+}
+
 #[derive(Debug)]
 enum PartitionQuery {
     Named,
     Latest,
 }
 
-fn un_send(s: &(dyn ToSql + Sync + Send)) -> Box<&(dyn ToSql + Sync)> {
-    Box::new(s)
-}
-
+// every call is made within a span named after its method. Arguments aren't recorded, since some
+// are secrets (e.g. passwords) and others, such as schemas, are too large to be useful in a span.
 #[async_trait]
-impl DataService for Db {
+impl<C: Connect> DataService for C {
+    #[tracing::instrument(skip_all)]
     async fn register_dataset(
         &mut self,
         manager: &Manager,
@@ -247,10 +852,9 @@ impl DataService for Db {
         schema: DatasetSchema,
         description: &str,
     ) -> Result<Dataset, Error> {
-        Ok(self
-            .client
-            .get()
-            .await?
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        let dataset: Dataset = tx
             .query_one(
                 sql::REGISTER_DATASET,
                 &[
@@ -264,78 +868,494 @@ impl DataService for Db {
                 ],
             )
             .await?
-            .into())
+            .into();
+
+        self.audit(
+            &tx,
+            "register_dataset",
+            "dataset",
+            &dataset.name,
+            None,
+            snapshot(&dataset),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(dataset)
     }
 
+    #[tracing::instrument(skip_all)]
     async fn find_dataset(&mut self, name: &str) -> Result<Dataset, Error> {
-        Ok(self
-            .client
-            .get()
+        self.conn()
             .await?
-            .query_one(sql::FIND_DATASET, &[&name])
+            .query_opt(sql::FIND_DATASET, &[&name])
             .await?
-            .into())
+            .map(Dataset::from)
+            .ok_or_else(|| Error::NotFound(format!("no dataset found with name '{}'", name)))
     }
 
-    async fn search_datasets(&mut self, term: &str) -> Result<Vec<Dataset>, Error> {
-        Ok(self
-            .client
-            .get()
-            .await?
-            .query(sql::SEARCH_DATASETS, &[&term])
+    #[tracing::instrument(skip_all)]
+    async fn search_datasets(&mut self, params: &SearchParams) -> Result<SearchResults, Error> {
+        let term = params
+            .term
+            .as_ref()
+            .map(|term| term.trim())
+            .filter(|term| !term.is_empty());
+        let ts_query = term.and_then(prefix_ts_query);
+        let pattern = term.map(contains_pattern);
+        let labels = params
+            .labels
+            .as_ref()
+            .filter(|labels| !labels.is_empty())
+            .map(Json);
+        let filters: [&(dyn ToSql + Sync); 9] = [
+            &pattern,
+            &ts_query,
+            &params.classification,
+            &params.format,
+            &params.compression,
+            &params.manager_email,
+            &params.updated_after,
+            &params.updated_before,
+            &labels,
+        ];
+
+        let conn = self.conn().await?;
+        let datasets = conn
+            .query(
+                sql::SEARCH_DATASETS,
+                &[&filters[..], &[&params.offset, &params.count]].concat(),
+            )
             .await?
             .iter()
             .map(Dataset::from)
-            .collect())
+            .collect();
+
+        let mut facets = SearchFacets::default();
+        for row in conn.query(sql::SEARCH_DATASET_FACETS, &filters).await? {
+            let counts = match row.get("facet") {
+                "classification" => &mut facets.classification,
+                "format" => &mut facets.format,
+                "compression" => &mut facets.compression,
+                _ => &mut facets.manager,
+            };
+            counts.insert(row.get("facet_value"), row.get("facet_count"));
+        }
+
+        Ok(SearchResults {
+            // every matching dataset has exactly one classification
+            total: facets.classification.values().sum(),
+            datasets,
+            facets,
+        })
     }
 
+    #[tracing::instrument(skip_all)]
     async fn list_datasets(&mut self, params: Option<RangeParams>) -> Result<Vec<Dataset>, Error> {
-        let (query, boxed_bindvars) = range_query::create(Target::Dataset, params);
-        let bindvars = boxed_bindvars
-            .iter()
-            .map(|v| v.as_ref())
-            .collect::<Vec<&(dyn ToSql + Sync + Send)>>();
-
-        let bindvars: Vec<&(dyn ToSql + Sync)> =
-            bindvars.iter().map(|v| *un_send(*v).as_ref()).collect();
+        let params = params.unwrap_or_default();
+        let mut query = query::datasets(&DatasetFilter::default(), &params);
+        query::paginate(&mut query, Target::Dataset, &params, params.count)?;
 
         Ok(self
-            .client
-            .get()
+            .conn()
             .await?
-            .query(&query as &str, &bindvars[..])
+            .query(query.sql().as_str(), &query.params())
             .await?
             .iter()
             .map(Dataset::from)
             .collect())
     }
 
-    async fn delete_dataset(&mut self, dataset: &Dataset) -> Result<(), Error> {
-        self.client
-            .get()
-            .await?
-            .execute(sql::DELETE_DATASET, &[&dataset.name])
-            .await
-            .map(|_| ())
-            .map_err(|e| Error::Generic(Box::new(e)))
-    }
-
-    async fn list_dataset_attributes(&mut self) -> Result<Attributes, Error> {
+    #[tracing::instrument(skip_all)]
+    async fn list_pii_fields(
+        &mut self,
+        sensitivity: Option<Sensitivity>,
+    ) -> Result<Vec<PiiField>, Error> {
         Ok(self
-            .client
-            .get()
+            .conn()
             .await?
-            .query_one(sql::DATASET_ATTRIBUTES, &[])
+            .query(sql::LIST_PII_FIELDS, &[&sensitivity.map(Json)])
             .await?
-            .into())
+            .iter()
+            .map(PiiField::from)
+            .collect())
     }
 
-    async fn register_partition(
+    #[tracing::instrument(skip_all)]
+    async fn page_datasets(
         &mut self,
-        dataset: &Dataset,
-        partition_name: &str,
-        partition_url: &str,
+        filter: &DatasetFilter,
+        params: &RangeParams,
+    ) -> Result<DatasetPage, Error> {
+        let mut query = query::datasets(filter, params);
+        // the total is counted before the cursor and pagination are bound
+        let count_query = query.count_sql();
+        let count_params = query.params().len();
+        // fetch one more dataset than requested to find whether there is a next page
+        let (sort, order) = query::paginate(
+            &mut query,
+            Target::Dataset,
+            params,
+            params.count.map(|count| count.saturating_add(1)),
+        )?;
+
+        let conn = self.conn().await?;
+        let mut datasets = conn
+            .query(query.sql().as_str(), &query.params())
+            .await?
+            .iter()
+            .map(Dataset::from)
+            .collect::<Vec<_>>();
+        let total = conn
+            .query_one(count_query.as_str(), &query.params()[..count_params])
+            .await?
+            .get("total");
+
+        let next =
+            next_page(&mut datasets, params.count).map(|last| last.cursor(sort, order).encode());
+        Ok(DatasetPage {
+            total,
+            datasets,
+            next,
+        })
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn list_labeled_datasets(
+        &mut self,
+        labels: &DatasetLabels,
+        params: Option<RangeParams>,
+    ) -> Result<Vec<Dataset>, Error> {
+        let params = params.unwrap_or_default();
+        let filter = DatasetFilter {
+            labels: Some(labels.clone()),
+            ..Default::default()
+        };
+        let mut query = query::datasets(&filter, &params);
+        query::paginate(&mut query, Target::Dataset, &params, params.count)?;
+
+        Ok(self
+            .conn()
+            .await?
+            .query(query.sql().as_str(), &query.params())
+            .await?
+            .iter()
+            .map(Dataset::from)
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn find_schema_fields(
+        &mut self,
+        search: &FieldSearch,
+    ) -> Result<Vec<SchemaField>, Error> {
+        let name = search.name.as_ref().map(|name| like_pattern(name));
+        Ok(self
+            .conn()
+            .await?
+            .query(
+                sql::FIND_SCHEMA_FIELDS,
+                &[&name, &search.field_type, &search.all_versions],
+            )
+            .await?
+            .iter()
+            .map(SchemaField::from)
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn update_dataset(
+        &mut self,
+        dataset: &Dataset,
+        description: &str,
+        schema: DatasetSchema,
+    ) -> Result<Dataset, Error> {
+        // a schema change must not remove a field which is annotated as sensitive
+        validate_sensitivity(&schema, &dataset.classification, &dataset.sensitivity)?;
+
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        let mut updated = tx
+            .query_opt(sql::UPDATE_DATASET, &[&dataset.id, &description, &schema])
+            .await?
+            .map(Dataset::from)
+            .ok_or_else(|| {
+                Error::NotFound(format!("no dataset found with name '{}'", dataset.name))
+            })?;
+        updated.manager_email = dataset.manager_email.clone();
+
+        self.audit(
+            &tx,
+            "update_dataset",
+            "dataset",
+            &dataset.name,
+            snapshot(dataset),
+            snapshot(&updated),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn update_dataset_sensitivity(
+        &mut self,
+        dataset: &Dataset,
+        sensitivity: &DatasetSensitivity,
+    ) -> Result<Dataset, Error> {
+        validate_sensitivity(&dataset.schema, &dataset.classification, sensitivity)?;
+
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        tx.execute(
+            sql::UPDATE_DATASET_SENSITIVITY,
+            &[&dataset.id, &Json(sensitivity)],
+        )
+        .await?;
+        let updated = tx
+            .query_opt(sql::FIND_DATASET, &[&dataset.name])
+            .await?
+            .map(Dataset::from)
+            .ok_or_else(|| {
+                Error::NotFound(format!("no dataset found with name '{}'", dataset.name))
+            })?;
+
+        self.audit(
+            &tx,
+            "update_dataset_sensitivity",
+            "dataset",
+            &dataset.name,
+            Some(serde_json::json!({ "sensitivity": dataset.sensitivity })),
+            Some(serde_json::json!({ "sensitivity": updated.sensitivity })),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn update_partition_template(
+        &mut self,
+        dataset: &Dataset,
+        template: Option<&str>,
+    ) -> Result<Dataset, Error> {
+        if let Some(template) = template {
+            PartitionTemplate::parse(template)?;
+        }
+
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        tx.execute(sql::UPDATE_PARTITION_TEMPLATE, &[&dataset.id, &template])
+            .await?;
+        let updated = tx
+            .query_opt(sql::FIND_DATASET, &[&dataset.name])
+            .await?
+            .map(Dataset::from)
+            .ok_or_else(|| {
+                Error::NotFound(format!("no dataset found with name '{}'", dataset.name))
+            })?;
+
+        self.audit(
+            &tx,
+            "update_partition_template",
+            "dataset",
+            &dataset.name,
+            Some(serde_json::json!({ "partition_template": dataset.partition_template })),
+            Some(serde_json::json!({ "partition_template": updated.partition_template })),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn update_dataset_labels(
+        &mut self,
+        dataset: &Dataset,
+        labels: &DatasetLabels,
+    ) -> Result<Dataset, Error> {
+        validate_labels(labels)?;
+
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        tx.execute(sql::DELETE_DATASET_LABELS, &[&dataset.id])
+            .await?;
+        tx.execute(sql::INSERT_DATASET_LABELS, &[&dataset.id, &Json(labels)])
+            .await?;
+        let updated = tx
+            .query_opt(sql::FIND_DATASET, &[&dataset.name])
+            .await?
+            .map(Dataset::from)
+            .ok_or_else(|| {
+                Error::NotFound(format!("no dataset found with name '{}'", dataset.name))
+            })?;
+
+        self.audit(
+            &tx,
+            "update_dataset_labels",
+            "dataset",
+            &dataset.name,
+            Some(serde_json::json!({ "labels": dataset.labels })),
+            Some(serde_json::json!({ "labels": updated.labels })),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn delete_dataset(&mut self, dataset: &Dataset) -> Result<(), Error> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+
+        // partitions are deleted along with the dataset, so keep track of how many are deleted
+        let partition_count: i64 = tx
+            .query_one(sql::DELETE_DATASET, &[&dataset.name])
+            .await?
+            .get("partition_count");
+
+        let mut before = snapshot(dataset);
+        if let Some(Value::Object(fields)) = before.as_mut() {
+            fields.insert("partition_count".into(), partition_count.into());
+        }
+        self.audit(
+            &tx,
+            "delete_dataset",
+            "dataset",
+            &dataset.name,
+            before,
+            None,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn find_deleted_dataset(&mut self, name: &str) -> Result<Dataset, Error> {
+        self.conn()
+            .await?
+            .query_opt(sql::FIND_DELETED_DATASET, &[&name])
+            .await?
+            .map(Dataset::from)
+            .ok_or_else(|| {
+                Error::NotFound(format!("no deleted dataset found with name '{}'", name))
+            })
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn restore_dataset(&mut self, dataset: &Dataset) -> Result<Dataset, Error> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        let mut restored = tx
+            .query_opt(sql::RESTORE_DATASET, &[&dataset.id])
+            .await?
+            .map(Dataset::from)
+            .ok_or_else(|| {
+                Error::NotFound(format!(
+                    "no deleted dataset found with name '{}'",
+                    dataset.name
+                ))
+            })?;
+        restored.manager_email = dataset.manager_email.clone();
+
+        self.audit(
+            &tx,
+            "restore_dataset",
+            "dataset",
+            &dataset.name,
+            None,
+            snapshot(&restored),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(restored)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn purge_deleted(&mut self, deleted_before: DateTime<Utc>) -> Result<Purged, Error> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+
+        // partitions are purged first, so that those removed along with a dataset are audited
+        let partitions = tx.query(sql::PURGE_PARTITIONS, &[&deleted_before]).await?;
+        for row in &partitions {
+            let dataset_name: String = row.get("dataset_name");
+            let partition = Partition::from(row);
+            self.audit(
+                &tx,
+                "purge_partition",
+                "partition",
+                &format!("{}/{}", dataset_name, partition.name),
+                snapshot(&partition),
+                None,
+            )
+            .await?;
+        }
+
+        let datasets = tx.query(sql::PURGE_DATASETS, &[&deleted_before]).await?;
+        for row in &datasets {
+            let dataset = Dataset::from(row);
+            self.audit(
+                &tx,
+                "purge_dataset",
+                "dataset",
+                &dataset.name,
+                snapshot(&dataset),
+                None,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(Purged {
+            datasets: datasets.len() as u64,
+            partitions: partitions.len() as u64,
+        })
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn list_dataset_attributes(&mut self) -> Result<Attributes, Error> {
+        Ok(self
+            .conn()
+            .await?
+            .query_one(sql::DATASET_ATTRIBUTES, &[])
+            .await?
+            .into())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn list_catalog_stats(&mut self) -> Result<Vec<CatalogStats>, Error> {
+        Ok(self
+            .conn()
+            .await?
+            .query(sql::CATALOG_STATS, &[])
+            .await?
+            .iter()
+            .map(|row| CatalogStats {
+                classification: row.get("dataset_classification"),
+                datasets: row.get("dataset_count"),
+                partitions: row.get("partition_count"),
+                bytes: row.get("partition_bytes"),
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn register_partition(
+        &mut self,
+        dataset: &Dataset,
+        partition_name: &str,
+        partition_url: &str,
         partition_size: i64,
+        partition_key: &PartitionKey,
+        data_range: Option<DataRange>,
     ) -> Result<Partition, Error> {
         if partition_name == PARTITION_LATEST {
             log::error!(
@@ -349,37 +1369,182 @@ impl DataService for Db {
             ));
         }
 
-        Ok(self
-            .client
-            .get()
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+
+        // registering an existing partition overwrites it, so keep its previous state
+        let before = tx
+            .query_opt(sql::FIND_PARTITION, &[&partition_name, &dataset.id])
             .await?
+            .map(Partition::from);
+        let partition: Partition = tx
             .query_one(
                 sql::REGISTER_PARTITION,
                 &[
                     &partition_name,
                     &partition_url,
                     &partition_size,
+                    &partition_key.date,
+                    &partition_key.hour,
+                    &partition_key.shard,
+                    &data_range.map(|range| range.start),
+                    &data_range.map(|range| range.end),
                     &dataset.id,
                 ],
             )
             .await?
-            .into())
+            .into();
+
+        self.audit(
+            &tx,
+            "register_partition",
+            "partition",
+            &partition_target(dataset, partition_name),
+            before.as_ref().and_then(snapshot),
+            snapshot(&partition),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(partition)
     }
 
+    #[tracing::instrument(skip_all)]
+    async fn register_partitions(
+        &mut self,
+        dataset: &Dataset,
+        partitions: &[PartitionRegistration],
+    ) -> Result<Vec<Partition>, Error> {
+        if partitions.iter().any(|p| p.name == PARTITION_LATEST) {
+            return Err(Error::InputValidation(
+                "cannot use reserved name 'latest' for partition".into(),
+            ));
+        }
+        if partitions.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let names = partitions
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect::<Vec<_>>();
+        let urls = partitions
+            .iter()
+            .map(|p| p.url.as_str())
+            .collect::<Vec<_>>();
+        let sizes = partitions.iter().map(|p| p.size).collect::<Vec<_>>();
+        let dates = partitions.iter().map(|p| p.key.date).collect::<Vec<_>>();
+        let hours = partitions.iter().map(|p| p.key.hour).collect::<Vec<_>>();
+        let shards = partitions.iter().map(|p| p.key.shard).collect::<Vec<_>>();
+        let starts = partitions
+            .iter()
+            .map(|p| p.data_range.map(|range| range.start))
+            .collect::<Vec<_>>();
+        let ends = partitions
+            .iter()
+            .map(|p| p.data_range.map(|range| range.end))
+            .collect::<Vec<_>>();
+
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        let registered = tx
+            .query(
+                sql::REGISTER_PARTITIONS,
+                &[
+                    &names,
+                    &urls,
+                    &sizes,
+                    &dates,
+                    &hours,
+                    &shards,
+                    &starts,
+                    &ends,
+                    &dataset.id,
+                ],
+            )
+            .await?
+            .into_iter()
+            .map(Partition::from)
+            .collect::<Vec<_>>();
+
+        // a single event is audited for the whole batch, rather than one per partition
+        self.audit(
+            &tx,
+            "register_partitions",
+            "dataset",
+            &dataset.name,
+            None,
+            Some(serde_json::json!({ "partitions": names })),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(registered)
+    }
+
+    #[tracing::instrument(skip_all)]
     async fn delete_partition(
         &mut self,
         dataset: &Dataset,
         partition_name: &str,
     ) -> Result<(), Error> {
-        self.client
-            .get()
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        let deleted = tx
+            .query_opt(sql::DELETE_PARTITION, &[&dataset.id, &partition_name])
             .await?
-            .execute(sql::DELETE_PARTITION, &[&dataset.id, &partition_name])
-            .await
-            .map(|_| ())
-            .map_err(|e| Error::Generic(Box::new(e)))
+            .map(Partition::from);
+
+        if let Some(partition) = deleted {
+            self.audit(
+                &tx,
+                "delete_partition",
+                "partition",
+                &partition_target(dataset, partition_name),
+                snapshot(&partition),
+                None,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn restore_partition(
+        &mut self,
+        dataset: &Dataset,
+        partition_name: &str,
+    ) -> Result<Partition, Error> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        let partition = tx
+            .query_opt(sql::RESTORE_PARTITION, &[&dataset.id, &partition_name])
+            .await?
+            .map(Partition::from)
+            .ok_or_else(|| {
+                Error::NotFound(format!(
+                    "no deleted partition '{}' found for dataset '{}'",
+                    partition_name, dataset.name
+                ))
+            })?;
+
+        self.audit(
+            &tx,
+            "restore_partition",
+            "partition",
+            &partition_target(dataset, partition_name),
+            None,
+            snapshot(&partition),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(partition)
     }
 
+    #[tracing::instrument(skip_all)]
     async fn find_partition(
         &mut self,
         dataset: &Dataset,
@@ -393,111 +1558,198 @@ impl DataService for Db {
             sql_querytype.1 = PartitionQuery::Latest;
         }
 
-        match sql_querytype.1 {
-            PartitionQuery::Named => Ok(self
-                .client
-                .get()
-                .await?
-                .query_one(sql_querytype.0, &[&partition_name, &dataset.id])
-                .await?
-                .into()),
-            PartitionQuery::Latest => Ok(self
-                .client
-                .get()
-                .await?
-                .query_one(sql_querytype.0, &[&dataset.id])
-                .await?
-                .into()),
-        }
+        let row = match sql_querytype.1 {
+            PartitionQuery::Named => {
+                self.conn()
+                    .await?
+                    .query_opt(sql_querytype.0, &[&partition_name, &dataset.id])
+                    .await?
+            }
+            PartitionQuery::Latest => {
+                self.conn()
+                    .await?
+                    .query_opt(sql_querytype.0, &[&dataset.id])
+                    .await?
+            }
+        };
+
+        row.map(Partition::from).ok_or_else(|| {
+            Error::NotFound(format!(
+                "no partition '{}' found for dataset '{}'",
+                partition_name, dataset.name
+            ))
+        })
     }
 
+    #[tracing::instrument(skip_all)]
     async fn list_partitions(
         &mut self,
         dataset: &Dataset,
         params: Option<RangeParams>,
     ) -> Result<Vec<Partition>, Error> {
-        let (query, boxed_bindvars) = range_query::create(Target::Partition, params);
-        let mut bindvars = boxed_bindvars
+        let params = params.unwrap_or_default();
+        let mut query = query::partitions(dataset.id, &PartitionFilter::default(), &params);
+        query::paginate(&mut query, Target::Partition, &params, params.count)?;
+
+        Ok(self
+            .conn()
+            .await?
+            .query(query.sql().as_str(), &query.params())
+            .await?
+            .iter()
+            .map(Partition::from)
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn page_partitions(
+        &mut self,
+        dataset: &Dataset,
+        filter: &PartitionFilter,
+        params: &RangeParams,
+    ) -> Result<PartitionPage, Error> {
+        let mut query = query::partitions(dataset.id, filter, params);
+        // the total is counted before the cursor and pagination are bound
+        let count_query = query.count_sql();
+        let count_params = query.params().len();
+        // fetch one more partition than requested to find whether there is a next page
+        let (sort, order) = query::paginate(
+            &mut query,
+            Target::Partition,
+            params,
+            params.count.map(|count| count.saturating_add(1)),
+        )?;
+
+        let conn = self.conn().await?;
+        let mut partitions = conn
+            .query(query.sql().as_str(), &query.params())
+            .await?
             .iter()
-            .map(|v| v.as_ref())
-            .collect::<Vec<&(dyn ToSql + Sync + Send)>>();
-        // prepend the dataset id to the bind vars, since it is used for all partition range queries
-        bindvars.insert(0, &dataset.id);
+            .map(Partition::from)
+            .collect::<Vec<_>>();
+        let total = conn
+            .query_one(count_query.as_str(), &query.params()[..count_params])
+            .await?
+            .get("total");
+
+        let next =
+            next_page(&mut partitions, params.count).map(|last| last.cursor(sort, order).encode());
+        Ok(PartitionPage {
+            total,
+            partitions,
+            next,
+        })
+    }
 
-        let bindvars: Vec<&(dyn ToSql + Sync)> =
-            bindvars.iter().map(|v| *un_send(*v).as_ref()).collect();
+    #[tracing::instrument(skip_all)]
+    async fn list_keyed_partitions(
+        &mut self,
+        dataset: &Dataset,
+        key: &PartitionKey,
+        params: Option<RangeParams>,
+    ) -> Result<Vec<Partition>, Error> {
+        let params = params.unwrap_or_default();
+        let filter = PartitionFilter {
+            key: *key,
+            ..Default::default()
+        };
+        let mut query = query::partitions(dataset.id, &filter, &params);
+        query::paginate(&mut query, Target::Partition, &params, params.count)?;
 
         Ok(self
-            .client
-            .get()
+            .conn()
             .await?
-            .query(&query as &str, &bindvars[..])
+            .query(query.sql().as_str(), &query.params())
             .await?
             .iter()
             .map(Partition::from)
             .collect())
     }
 
+    #[tracing::instrument(skip_all)]
     async fn register_manager(&mut self, email: &str, password: &str) -> Result<Manager, Error> {
-        match env::var("DD_MANAGER_EMAIL_DOMAIN") {
-            Ok(domain) => {
-                if !email.contains(&format!("@{}", domain)) {
-                    return Err(Error::InputValidation(format!(
-                        "invalid email pattern, must be <user>@{} address",
-                        domain
-                    )));
-                }
+        if let Some(domain) = self.email_domain() {
+            if !email.contains(&format!("@{}", domain)) {
+                return Err(Error::InputValidation(format!(
+                    "invalid email pattern, must be <user>@{} address",
+                    domain
+                )));
             }
-            Err(e) => log::error!(
-                "skipping manager email domain validation, 'DD_MANAGER_EMAIL_DOMAIN' {}",
-                e
-            ),
         }
 
         let salt = rand(32, CHARACTER_SET.into());
         let hash = argon2rs::argon2d_simple(&password, &salt).to_vec();
         let api_key = Uuid::new_v4();
-        Ok(self
-            .client
-            .get()
-            .await?
+
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        let manager: Manager = tx
             .query_one(sql::REGISTER_MANAGER, &[&email, &hash, &salt, &api_key])
             .await?
-            .into())
+            .into();
+
+        self.audit(
+            &tx,
+            "register_manager",
+            "manager",
+            &manager.email,
+            None,
+            manager_snapshot(&manager),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(manager)
     }
 
+    #[tracing::instrument(skip_all)]
     async fn find_manager(&mut self, api_key: &Uuid) -> Result<Manager, Error> {
-        Ok(self
-            .client
-            .get()
+        let manager: Manager = self
+            .conn()
             .await?
-            .query_one(sql::FIND_MANAGER, &[api_key])
+            .query_opt(sql::FIND_MANAGER, &[api_key])
             .await?
-            .into())
+            .map(Manager::from)
+            .ok_or_else(|| Error::NotFound("no manager found with api key".into()))?;
+
+        if manager.disabled {
+            Err(Error::Auth(format!(
+                "manager account '{}' is disabled",
+                manager.email
+            )))
+        } else {
+            Ok(manager)
+        }
     }
 
+    #[tracing::instrument(skip_all)]
     async fn auth_manager(&mut self, email: &str, password: &str) -> Result<Manager, Error> {
-        let manager: Manager = self
-            .client
-            .get()
+        let manager = self
+            .conn()
             .await?
-            .query_one(sql::AUTH_MANAGER, &[&email])
+            .query_opt(sql::AUTH_MANAGER, &[&email])
             .await?
-            .into();
+            .map(Manager::from)
+            .ok_or_else(|| Error::NotFound(format!("no manager found with email '{}'", email)))?;
 
         // validate that the password provided is the same as our stored value
         let hash = argon2rs::argon2d_simple(&password, &manager.salt);
         if hash != manager.hash.as_slice() {
             Err(Error::Auth(format!("invalid credentials for '{}'", email)))
+        } else if manager.disabled {
+            Err(Error::Auth(format!(
+                "manager account '{}' is disabled",
+                email
+            )))
         } else {
             Ok(manager)
         }
     }
 
+    #[tracing::instrument(skip_all)]
     async fn manager_datasets(&mut self, api_key: &Uuid) -> Result<Vec<Dataset>, Error> {
         Ok(self
-            .client
-            .get()
+            .conn()
             .await?
             .query(sql::MANAGED_DATASETS, &[api_key])
             .await?
@@ -505,4 +1757,509 @@ impl DataService for Db {
             .map(Dataset::from)
             .collect())
     }
+
+    #[tracing::instrument(skip_all)]
+    async fn find_manager_by_id(&mut self, manager_id: i32) -> Result<Manager, Error> {
+        self.conn()
+            .await?
+            .query_opt(sql::FIND_MANAGER_BY_ID, &[&manager_id])
+            .await?
+            .map(Manager::from)
+            .ok_or_else(|| Error::NotFound(format!("no manager found with id '{}'", manager_id)))
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn list_managers(&mut self) -> Result<Vec<Manager>, Error> {
+        Ok(self
+            .conn()
+            .await?
+            .query(sql::LIST_MANAGERS, &[])
+            .await?
+            .iter()
+            .map(Manager::from)
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn update_manager_admin(
+        &mut self,
+        manager_id: i32,
+        admin: bool,
+    ) -> Result<Manager, Error> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        let before = tx
+            .query_opt(sql::FIND_MANAGER_BY_ID, &[&manager_id])
+            .await?
+            .map(Manager::from)
+            .ok_or_else(|| Error::NotFound(format!("no manager found with id '{}'", manager_id)))?;
+        let manager = tx
+            .query_opt(sql::UPDATE_MANAGER_ADMIN, &[&manager_id, &admin])
+            .await?
+            .map(Manager::from)
+            .ok_or_else(|| Error::NotFound(format!("no manager found with id '{}'", manager_id)))?;
+
+        self.audit(
+            &tx,
+            "update_manager_admin",
+            "manager",
+            &manager.email,
+            manager_snapshot(&before),
+            manager_snapshot(&manager),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(manager)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn update_manager_disabled(
+        &mut self,
+        manager_id: i32,
+        disabled: bool,
+    ) -> Result<Manager, Error> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        let before = tx
+            .query_opt(sql::FIND_MANAGER_BY_ID, &[&manager_id])
+            .await?
+            .map(Manager::from)
+            .ok_or_else(|| Error::NotFound(format!("no manager found with id '{}'", manager_id)))?;
+        let manager = tx
+            .query_opt(sql::UPDATE_MANAGER_DISABLED, &[&manager_id, &disabled])
+            .await?
+            .map(Manager::from)
+            .ok_or_else(|| Error::NotFound(format!("no manager found with id '{}'", manager_id)))?;
+
+        self.audit(
+            &tx,
+            "update_manager_disabled",
+            "manager",
+            &manager.email,
+            manager_snapshot(&before),
+            manager_snapshot(&manager),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(manager)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn transfer_datasets(
+        &mut self,
+        from_manager_id: i32,
+        to_manager_id: i32,
+    ) -> Result<u64, Error> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        let transferred = tx
+            .execute(sql::TRANSFER_DATASETS, &[&from_manager_id, &to_manager_id])
+            .await?;
+
+        self.audit(
+            &tx,
+            "transfer_datasets",
+            "manager",
+            &from_manager_id.to_string(),
+            Some(serde_json::json!({ "manager_id": from_manager_id })),
+            Some(serde_json::json!({
+                "manager_id": to_manager_id,
+                "transferred": transferred
+            })),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(transferred)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn delete_manager(&mut self, manager_id: i32, successor_id: i32) -> Result<(), Error> {
+        if manager_id == successor_id {
+            return Err(Error::InputValidation(
+                "cannot transfer datasets of a deleted manager to itself".into(),
+            ));
+        }
+
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        let deleted = tx
+            .query_opt(sql::DELETE_MANAGER, &[&manager_id, &successor_id])
+            .await?
+            .map(Manager::from);
+
+        match deleted {
+            Some(manager) => {
+                self.audit(
+                    &tx,
+                    "delete_manager",
+                    "manager",
+                    &manager.email,
+                    manager_snapshot(&manager),
+                    Some(serde_json::json!({ "successor_id": successor_id })),
+                )
+                .await?;
+                tx.commit().await?;
+
+                Ok(())
+            }
+            None => Err(Error::NotFound(format!(
+                "no manager found with id '{}'",
+                manager_id
+            ))),
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn find_manager_by_email(&mut self, email: &str) -> Result<Manager, Error> {
+        self.conn()
+            .await?
+            .query_opt(sql::FIND_MANAGER_BY_EMAIL, &[&email])
+            .await?
+            .map(Manager::from)
+            .ok_or_else(|| Error::NotFound(format!("no manager found with email '{}'", email)))
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn list_maintainers(&mut self, dataset: &Dataset) -> Result<Vec<Maintainer>, Error> {
+        Ok(self
+            .conn()
+            .await?
+            .query(sql::LIST_MAINTAINERS, &[&dataset.id])
+            .await?
+            .iter()
+            .map(Maintainer::from)
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn add_maintainer(
+        &mut self,
+        dataset: &Dataset,
+        manager: &Manager,
+    ) -> Result<Maintainer, Error> {
+        if manager.id == dataset.manager_id {
+            return Err(Error::Conflict(format!(
+                "manager '{}' already owns dataset '{}'",
+                manager.email, dataset.name
+            )));
+        }
+
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        let mut maintainer: Maintainer = tx
+            .query_one(sql::ADD_MAINTAINER, &[&dataset.id, &manager.id])
+            .await?
+            .into();
+        maintainer.manager_email = manager.email.clone();
+
+        self.audit(
+            &tx,
+            "add_maintainer",
+            "dataset",
+            &dataset.name,
+            None,
+            snapshot(&maintainer),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(maintainer)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn remove_maintainer(&mut self, dataset: &Dataset, manager_id: i32) -> Result<(), Error> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        let removed = tx
+            .execute(sql::REMOVE_MAINTAINER, &[&dataset.id, &manager_id])
+            .await?;
+
+        if removed > 0 {
+            self.audit(
+                &tx,
+                "remove_maintainer",
+                "dataset",
+                &dataset.name,
+                Some(serde_json::json!({
+                    "dataset_id": dataset.id,
+                    "manager_id": manager_id
+                })),
+                None,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn list_read_grants(&mut self, dataset: &Dataset) -> Result<Vec<ReadGrant>, Error> {
+        Ok(self
+            .conn()
+            .await?
+            .query(sql::LIST_READ_GRANTS, &[&dataset.id])
+            .await?
+            .iter()
+            .map(ReadGrant::from)
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn add_read_grant(
+        &mut self,
+        dataset: &Dataset,
+        manager: &Manager,
+    ) -> Result<ReadGrant, Error> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        let mut grant: ReadGrant = tx
+            .query_one(sql::ADD_READ_GRANT, &[&dataset.id, &manager.id])
+            .await?
+            .into();
+        grant.manager_email = manager.email.clone();
+
+        self.audit(
+            &tx,
+            "add_read_grant",
+            "dataset",
+            &dataset.name,
+            None,
+            snapshot(&grant),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(grant)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn remove_read_grant(&mut self, dataset: &Dataset, manager_id: i32) -> Result<(), Error> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        let removed = tx
+            .execute(sql::REMOVE_READ_GRANT, &[&dataset.id, &manager_id])
+            .await?;
+
+        if removed > 0 {
+            self.audit(
+                &tx,
+                "remove_read_grant",
+                "dataset",
+                &dataset.name,
+                Some(serde_json::json!({
+                    "dataset_id": dataset.id,
+                    "manager_id": manager_id
+                })),
+                None,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn log_access_denial(&mut self, denial: &AccessDenial) -> Result<(), Error> {
+        self.conn()
+            .await?
+            .execute(
+                sql::INSERT_ACCESS_DENIAL,
+                &[
+                    &denial.manager_id,
+                    &denial.remote_addr,
+                    &denial.dataset_name,
+                    &denial.partition_name,
+                    &denial.action,
+                    &denial.reason,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn list_access_denials(
+        &mut self,
+        filter: &AccessFilter,
+    ) -> Result<Vec<AccessDenial>, Error> {
+        Ok(self
+            .conn()
+            .await?
+            .query(
+                sql::LIST_ACCESS_DENIALS,
+                &[
+                    &filter.manager_id,
+                    &filter.dataset_name,
+                    &filter.start,
+                    &filter.end,
+                    &filter.offset,
+                    &filter.count,
+                ],
+            )
+            .await?
+            .iter()
+            .map(AccessDenial::from)
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn request_transfer(
+        &mut self,
+        dataset: &Dataset,
+        to: &Manager,
+    ) -> Result<DatasetTransfer, Error> {
+        if to.id == dataset.manager_id {
+            return Err(Error::Conflict(format!(
+                "manager '{}' already owns dataset '{}'",
+                to.email, dataset.name
+            )));
+        }
+
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        let transfer_id: i32 = tx
+            .query_one(
+                sql::REQUEST_TRANSFER,
+                &[&dataset.id, &dataset.manager_id, &to.id],
+            )
+            .await?
+            .get("transfer_id");
+        let transfer: DatasetTransfer = tx
+            .query_one(sql::FIND_TRANSFER, &[&transfer_id])
+            .await?
+            .into();
+
+        self.audit(
+            &tx,
+            "request_transfer",
+            "dataset",
+            &dataset.name,
+            None,
+            snapshot(&transfer),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(transfer)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn find_transfer(&mut self, transfer_id: i32) -> Result<DatasetTransfer, Error> {
+        self.conn()
+            .await?
+            .query_opt(sql::FIND_TRANSFER, &[&transfer_id])
+            .await?
+            .map(DatasetTransfer::from)
+            .ok_or_else(|| Error::NotFound(format!("no transfer found with id '{}'", transfer_id)))
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn find_dataset_transfer(
+        &mut self,
+        dataset: &Dataset,
+    ) -> Result<Option<DatasetTransfer>, Error> {
+        Ok(self
+            .conn()
+            .await?
+            .query_opt(sql::FIND_DATASET_TRANSFER, &[&dataset.id])
+            .await?
+            .map(DatasetTransfer::from))
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn list_transfers(&mut self, manager: &Manager) -> Result<Vec<DatasetTransfer>, Error> {
+        Ok(self
+            .conn()
+            .await?
+            .query(sql::LIST_TRANSFERS, &[&manager.id])
+            .await?
+            .iter()
+            .map(DatasetTransfer::from)
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn accept_transfer(&mut self, transfer: &DatasetTransfer) -> Result<Dataset, Error> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        // a transfer is only accepted while its sender still owns the dataset, since it may have
+        // been reassigned (e.g. by an admin) after the transfer was offered
+        let mut dataset: Dataset = match tx.query_opt(sql::ACCEPT_TRANSFER, &[&transfer.id]).await?
+        {
+            Some(row) => row.into(),
+            None => {
+                return Err(Error::Conflict(format!(
+                    "dataset '{}' is no longer owned by the manager who offered the transfer",
+                    transfer.dataset_name
+                )))
+            }
+        };
+        dataset.manager_email = transfer.to_manager_email.clone();
+
+        self.audit(
+            &tx,
+            "accept_transfer",
+            "dataset",
+            &dataset.name,
+            snapshot(transfer),
+            snapshot(&dataset),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(dataset)
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn delete_transfer(&mut self, transfer: &DatasetTransfer) -> Result<(), Error> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction().await?;
+        let deleted = tx.execute(sql::DELETE_TRANSFER, &[&transfer.id]).await?;
+
+        if deleted > 0 {
+            self.audit(
+                &tx,
+                "delete_transfer",
+                "dataset",
+                &transfer.dataset_name,
+                snapshot(transfer),
+                None,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn list_audit_events(&mut self, filter: &AuditFilter) -> Result<Vec<AuditEvent>, Error> {
+        Ok(self
+            .conn()
+            .await?
+            .query(
+                sql::LIST_AUDIT_EVENTS,
+                &[
+                    &filter.actor_type,
+                    &filter.actor_id,
+                    &filter.action,
+                    &filter.target_type,
+                    &filter.target,
+                    &filter.start,
+                    &filter.end,
+                    &filter.offset,
+                    &filter.count,
+                ],
+            )
+            .await?
+            .iter()
+            .map(AuditEvent::from)
+            .collect())
+    }
 }
@@ -1,5 +1,5 @@
 pub mod db;
-mod range_query;
+mod query;
 mod sql;
 
 pub use db::*;
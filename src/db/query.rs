@@ -0,0 +1,455 @@
+use crate::db::sql;
+use crate::dict::{
+    Cursor, Dataset, DatasetFilter, Partition, PartitionFilter, RangeParams, SortKey, SortOrder,
+    SortValue,
+};
+use crate::error::Error;
+
+use postgres_types::{Json, ToSql};
+
+/// A QueryBuilder composes a list query from a base query, which must end with a `WHERE` clause,
+/// followed by any number of filters, then its sorts, offset, and limit. Every value is bound to
+/// the next numbered placeholder in the order it is added, so filters can be combined freely.
+pub struct QueryBuilder {
+    base: &'static str,
+    conditions: Vec<String>,
+    sorts: Vec<String>,
+    offset: Option<String>,
+    limit: Option<String>,
+    params: Vec<Box<dyn ToSql + Sync + Send>>,
+}
+
+impl QueryBuilder {
+    pub fn new(base: &'static str) -> Self {
+        Self {
+            base,
+            conditions: vec![],
+            sorts: vec![],
+            offset: None,
+            limit: None,
+            params: vec![],
+        }
+    }
+
+    /// Binds a value to the next placeholder, and returns the placeholder, e.g. "$3".
+    pub fn bind(&mut self, value: impl ToSql + Sync + Send + 'static) -> String {
+        self.params.push(Box::new(value));
+        format!("${}", self.params.len())
+    }
+
+    /// Adds a condition which every result must match, where each "{}" in the condition is
+    /// replaced by the placeholder of the value provided.
+    pub fn filter(
+        &mut self,
+        condition: &str,
+        value: impl ToSql + Sync + Send + 'static,
+    ) -> &mut Self {
+        let placeholder = self.bind(value);
+        self.conditions.push(condition.replace("{}", &placeholder));
+        self
+    }
+
+    /// Adds a condition as `filter` does, only if a value is provided.
+    pub fn filter_opt<T: ToSql + Sync + Send + 'static>(
+        &mut self,
+        condition: &str,
+        value: Option<T>,
+    ) -> &mut Self {
+        if let Some(value) = value {
+            self.filter(condition, value);
+        }
+        self
+    }
+
+    /// Adds a condition whose placeholders have already been bound.
+    pub fn condition(&mut self, condition: String) -> &mut Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Sorts the results by an expression, after any sorts which were added before it.
+    pub fn sort(&mut self, expr: &str, order: SortOrder) -> &mut Self {
+        self.sorts.push(format!("{} {}", expr, direction(order)));
+        self
+    }
+
+    pub fn offset(&mut self, offset: Option<i32>) -> &mut Self {
+        if let Some(offset) = offset {
+            self.offset = Some(format!("OFFSET {}::INTEGER", self.bind(offset)));
+        }
+        self
+    }
+
+    pub fn limit(&mut self, limit: Option<i32>) -> &mut Self {
+        if let Some(limit) = limit {
+            self.limit = Some(format!("LIMIT {}::INTEGER", self.bind(limit)));
+        }
+        self
+    }
+
+    fn filtered(&self) -> String {
+        let mut query = self.base.trim_end().to_string();
+        for condition in &self.conditions {
+            query.push_str("\n    AND ");
+            query.push_str(condition);
+        }
+        query
+    }
+
+    /// Returns the query, with its clauses in the order required by Postgres.
+    pub fn sql(&self) -> String {
+        let mut query = self.filtered();
+        if !self.sorts.is_empty() {
+            query.push_str("\n    ORDER BY ");
+            query.push_str(&self.sorts.join(", "));
+        }
+        for clause in self.offset.iter().chain(self.limit.iter()) {
+            query.push_str("\n    ");
+            query.push_str(clause);
+        }
+        query
+    }
+
+    /// Returns a query counting every result which matches the filters, ignoring the sorts and
+    /// pagination. Since it is bound to the same params as `sql`, it must be taken before any
+    /// cursor, offset, or limit is added.
+    pub fn count_sql(&self) -> String {
+        debug_assert!(self.offset.is_none() && self.limit.is_none());
+        format!(
+            "SELECT COUNT(*) AS total FROM ({}\n) AS matches",
+            self.filtered()
+        )
+    }
+
+    pub fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.params
+            .iter()
+            .map(|v| v.as_ref() as &(dyn ToSql + Sync))
+            .collect()
+    }
+}
+
+fn direction(order: SortOrder) -> &'static str {
+    match order {
+        SortOrder::Asc => "ASC",
+        SortOrder::Desc => "DESC",
+    }
+}
+
+// Converts a name prefix into a LIKE pattern, escaping any characters with a special meaning to
+// LIKE.
+fn prefix_pattern(prefix: &str) -> String {
+    let mut pattern = String::with_capacity(prefix.len() + 1);
+    for c in prefix.chars() {
+        if let '%' | '_' | '\\' = c {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// A Target is the kind of result a list query returns, which determines the columns that its
+/// results are sorted by.
+#[derive(Debug, Clone, Copy)]
+pub enum Target {
+    Dataset,
+    Partition,
+}
+
+impl Target {
+    // results are sorted by when they were created unless asked otherwise, which never changes, so
+    // that a cursor can't skip or repeat results that are updated between pages
+    fn default_sort(self) -> SortKey {
+        SortKey::CreatedAt
+    }
+
+    fn id_column(self) -> &'static str {
+        match self {
+            Target::Dataset => "dataset_id",
+            Target::Partition => "partition_id",
+        }
+    }
+
+    fn sort_column(self, key: SortKey) -> Result<&'static str, Error> {
+        match (self, key) {
+            (Target::Dataset, SortKey::Name) => Ok("dataset_name"),
+            (Target::Dataset, SortKey::CreatedAt) => Ok("datasets.created_at"),
+            (Target::Dataset, SortKey::UpdatedAt) => Ok("datasets.updated_at"),
+            (Target::Dataset, SortKey::Size) => Err(Error::InputValidation(
+                "datasets cannot be sorted by size".into(),
+            )),
+            (Target::Partition, SortKey::Name) => Ok("partition_name"),
+            (Target::Partition, SortKey::CreatedAt) => Ok("created_at"),
+            (Target::Partition, SortKey::UpdatedAt) => Ok("updated_at"),
+            (Target::Partition, SortKey::Size) => Ok("partition_size"),
+        }
+    }
+}
+
+/// Composes a query for the datasets which match every filter and the created_at range provided.
+pub fn datasets(filter: &DatasetFilter, params: &RangeParams) -> QueryBuilder {
+    let labels = filter.labels.clone().filter(|labels| !labels.is_empty());
+    let mut query = QueryBuilder::new(sql::LIST_DATASETS);
+    query
+        .filter_opt(
+            "dataset_classification = {}",
+            filter.classification.clone(),
+        )
+        .filter_opt("manager_email = {}", filter.manager_email.clone())
+        .filter_opt(
+            "dataset_name LIKE {}",
+            filter.name_prefix.as_deref().map(prefix_pattern),
+        )
+        .filter_opt(
+            "datasets.dataset_id IN (
+        SELECT dataset_id FROM dataset_labels
+        JOIN jsonb_each_text({}::JSONB) AS selector ON label_key = selector.key AND label_value = selector.value
+        GROUP BY dataset_id
+        HAVING COUNT(*) = (SELECT COUNT(*) FROM jsonb_object_keys({}::JSONB))
+    )",
+            labels.map(Json),
+        )
+        .filter_opt("datasets.created_at >= {}::TIMESTAMPTZ", params.start)
+        .filter_opt("datasets.created_at <= {}::TIMESTAMPTZ", params.end);
+    query
+}
+
+/// Composes a query for a dataset's partitions which match every filter, along with the created_at
+/// and data ranges provided.
+pub fn partitions(dataset_id: i32, filter: &PartitionFilter, params: &RangeParams) -> QueryBuilder {
+    let mut query = QueryBuilder::new(sql::LIST_PARTITIONS);
+    query
+        .filter("dataset_id = {}", dataset_id)
+        .filter_opt("partition_date = {}", filter.key.date)
+        .filter_opt("partition_hour = {}", filter.key.hour)
+        .filter_opt("partition_shard = {}", filter.key.shard)
+        .filter_opt(
+            "partition_name LIKE {}",
+            filter.name_prefix.as_deref().map(prefix_pattern),
+        )
+        .filter_opt("partition_size >= {}", filter.min_size)
+        .filter_opt("partition_size <= {}", filter.max_size)
+        .filter_opt("partition_data_end > {}::TIMESTAMPTZ", params.data_start)
+        .filter_opt("partition_data_start < {}::TIMESTAMPTZ", params.data_end)
+        .filter_opt("created_at >= {}::TIMESTAMPTZ", params.start)
+        .filter_opt("created_at <= {}::TIMESTAMPTZ", params.end);
+    query
+}
+
+/// Sorts a query by the sort key and order in the range parameters, then by id, and adds its
+/// cursor, offset, and the limit provided. A cursor must have been created by the same sort, which
+/// is used when the parameters don't specify one. Returns the sort key and order which were used.
+pub fn paginate(
+    query: &mut QueryBuilder,
+    target: Target,
+    params: &RangeParams,
+    limit: Option<i32>,
+) -> Result<(SortKey, SortOrder), Error> {
+    let cursor = params.cursor.as_ref();
+    let sort = params
+        .sort
+        .or_else(|| cursor.map(|c| c.sort))
+        .unwrap_or_else(|| target.default_sort());
+    let order = params
+        .order
+        .or_else(|| cursor.map(|c| c.order))
+        .unwrap_or_else(|| sort.default_order());
+    let column = target.sort_column(sort)?;
+    let id_column = target.id_column();
+
+    if let Some(cursor) = cursor {
+        if cursor.sort != sort || cursor.order != order {
+            return Err(Error::InputValidation(
+                "cursor does not match the sort requested".into(),
+            ));
+        }
+        let value = match (sort, &cursor.value) {
+            (SortKey::Name, SortValue::Text(name)) => query.bind(name.clone()),
+            (SortKey::CreatedAt, SortValue::Time(at))
+            | (SortKey::UpdatedAt, SortValue::Time(at)) => {
+                format!("{}::TIMESTAMPTZ", query.bind(*at))
+            }
+            (SortKey::Size, SortValue::Int(size)) => query.bind(*size),
+            _ => {
+                return Err(Error::InputValidation(
+                    "cursor value does not match its sort".into(),
+                ))
+            }
+        };
+        let id = query.bind(cursor.id);
+        // results after the cursor in a descending sort are less than it, and greater otherwise
+        let comparison = match order {
+            SortOrder::Asc => ">",
+            SortOrder::Desc => "<",
+        };
+        query.condition(format!(
+            "({}, {}) {} ({}, {}::INTEGER)",
+            column, id_column, comparison, value, id
+        ));
+    }
+
+    query
+        .sort(column, order)
+        .sort(id_column, order)
+        .offset(params.offset)
+        .limit(limit);
+    Ok((sort, order))
+}
+
+/// A Sorted result provides the value of each of its sort keys, so that a cursor can be created
+/// from it.
+pub trait Sorted {
+    fn sort_value(&self, key: SortKey) -> SortValue;
+    fn id(&self) -> i32;
+
+    fn cursor(&self, sort: SortKey, order: SortOrder) -> Cursor {
+        Cursor {
+            sort,
+            order,
+            value: self.sort_value(sort),
+            id: self.id(),
+        }
+    }
+}
+
+impl Sorted for Dataset {
+    fn sort_value(&self, key: SortKey) -> SortValue {
+        match key {
+            SortKey::Name => SortValue::Text(self.name.clone()),
+            SortKey::CreatedAt => SortValue::Time(self.created_at),
+            SortKey::UpdatedAt => SortValue::Time(self.updated_at),
+            SortKey::Size => unreachable!("datasets cannot be sorted by size"),
+        }
+    }
+
+    fn id(&self) -> i32 {
+        self.id
+    }
+}
+
+impl Sorted for Partition {
+    fn sort_value(&self, key: SortKey) -> SortValue {
+        match key {
+            SortKey::Name => SortValue::Text(self.name.clone()),
+            SortKey::CreatedAt => SortValue::Time(self.created_at),
+            SortKey::UpdatedAt => SortValue::Time(self.updated_at),
+            SortKey::Size => SortValue::Int(self.size),
+        }
+    }
+
+    fn id(&self) -> i32 {
+        self.id
+    }
+}
+
+#[test]
+fn test_query_builder() {
+    let mut query = QueryBuilder::new("SELECT * FROM t\n    WHERE deleted_at IS NULL\n");
+    query
+        .filter("a = {}", 1)
+        .filter_opt("b = {}", None::<i32>)
+        .filter_opt("c BETWEEN {} AND {}", Some("c".to_string()));
+    assert_eq!(
+        query.count_sql(),
+        "SELECT COUNT(*) AS total FROM (SELECT * FROM t\n    WHERE deleted_at IS NULL\n    AND a = $1\n    AND c BETWEEN $2 AND $2\n) AS matches"
+    );
+
+    query
+        .sort("created_at", SortOrder::Desc)
+        .sort("id", SortOrder::Asc)
+        .offset(Some(10))
+        .limit(Some(5));
+    assert_eq!(
+        query.sql(),
+        "SELECT * FROM t\n    WHERE deleted_at IS NULL\n    AND a = $1\n    AND c BETWEEN $2 AND $2\n    ORDER BY created_at DESC, id ASC\n    OFFSET $3::INTEGER\n    LIMIT $4::INTEGER"
+    );
+    assert_eq!(query.params().len(), 4);
+
+    // clauses which aren't set are left out, without skipping any placeholders
+    let mut query = QueryBuilder::new("SELECT * FROM t WHERE true");
+    query.limit(Some(5)).offset(None).filter("a = {}", 1);
+    assert_eq!(
+        query.sql(),
+        "SELECT * FROM t WHERE true\n    AND a = $2\n    LIMIT $1::INTEGER"
+    );
+}
+
+#[test]
+fn test_paginate() {
+    let params = RangeParams {
+        start: Some("2020-06-01T00:00:00Z".parse().unwrap()),
+        count: Some(2),
+        offset: Some(4),
+        ..Default::default()
+    };
+    let filter = PartitionFilter {
+        min_size: Some(100),
+        name_prefix: Some("part_1".into()),
+        ..Default::default()
+    };
+    let mut query = partitions(1, &filter, &params);
+    let sort = paginate(&mut query, Target::Partition, &params, params.count).unwrap();
+    assert_eq!(sort, (SortKey::CreatedAt, SortOrder::Desc));
+    let sql = query.sql();
+    assert!(sql.ends_with(
+        "AND dataset_id = $1
+    AND partition_name LIKE $2
+    AND partition_size >= $3
+    AND created_at >= $4::TIMESTAMPTZ
+    ORDER BY created_at DESC, partition_id DESC
+    OFFSET $5::INTEGER
+    LIMIT $6::INTEGER"
+    ));
+    assert_eq!(query.params().len(), 6);
+
+    let params = RangeParams {
+        cursor: Some(Cursor {
+            sort: SortKey::Name,
+            order: SortOrder::Asc,
+            value: SortValue::Text("b".into()),
+            id: 2,
+        }),
+        ..Default::default()
+    };
+    let mut query = datasets(&DatasetFilter::default(), &params);
+    let sort = paginate(&mut query, Target::Dataset, &params, Some(3)).unwrap();
+    assert_eq!(sort, (SortKey::Name, SortOrder::Asc));
+    assert!(query.sql().ends_with(
+        "AND (dataset_name, dataset_id) > ($1, $2::INTEGER)
+    ORDER BY dataset_name ASC, dataset_id ASC
+    LIMIT $3::INTEGER"
+    ));
+
+    let invalid = vec![
+        RangeParams {
+            sort: Some(SortKey::Size),
+            ..Default::default()
+        },
+        RangeParams {
+            order: Some(SortOrder::Desc),
+            ..params.clone()
+        },
+        RangeParams {
+            cursor: Some(Cursor {
+                sort: SortKey::UpdatedAt,
+                order: SortOrder::Desc,
+                value: SortValue::Int(1),
+                id: 2,
+            }),
+            ..Default::default()
+        },
+    ];
+    for params in invalid {
+        let mut query = datasets(&DatasetFilter::default(), &params);
+        assert!(paginate(&mut query, Target::Dataset, &params, None).is_err());
+    }
+}
+
+#[test]
+fn test_prefix_pattern() {
+    assert_eq!(prefix_pattern("part_1"), "part\\_1%");
+    assert_eq!(prefix_pattern("100%"), "100\\%%");
+    assert_eq!(prefix_pattern(""), "%");
+}
@@ -1,90 +1,454 @@
 pub const REGISTER_DATASET: &str = r#"
     INSERT INTO datasets (dataset_name, manager_id, dataset_compression, dataset_format, dataset_classification, dataset_schema, dataset_desc) 
     VALUES ($1, $2, $3, $4, $5, $6, $7) 
-    RETURNING dataset_id, dataset_name, manager_id, dataset_compression, dataset_format, dataset_classification, dataset_schema, dataset_desc, created_at, updated_at
+    RETURNING dataset_id, dataset_name, manager_id, dataset_compression, dataset_format, dataset_classification, dataset_schema, dataset_sensitivity, dataset_partition_template, dataset_desc, created_at, updated_at
 "#;
 
 pub const FIND_DATASET: &str = r#"
-    SELECT dataset_id, dataset_name, datasets.manager_id, manager_email, dataset_compression, dataset_format, dataset_classification, dataset_schema, dataset_desc, datasets.created_at, datasets.updated_at
+    SELECT dataset_id, dataset_name, datasets.manager_id, manager_email, dataset_compression, dataset_format, dataset_classification, dataset_schema, dataset_sensitivity, dataset_partition_template, dataset_desc, datasets.created_at, datasets.updated_at, COALESCE((SELECT jsonb_object_agg(label_key, label_value) FROM dataset_labels WHERE dataset_labels.dataset_id = datasets.dataset_id), '{}') AS dataset_labels
     FROM datasets
     JOIN managers on datasets.manager_id = managers.manager_id
-    WHERE dataset_name = $1
+    WHERE dataset_name = $1 AND datasets.deleted_at IS NULL
 "#;
 
-pub const SEARCH_DATASETS: &str = r#"
-    SELECT dataset_id, dataset_name, datasets.manager_id, manager_email, dataset_compression, dataset_format, dataset_classification, dataset_schema, dataset_desc, datasets.created_at, datasets.updated_at
+pub const FIND_DELETED_DATASET: &str = r#"
+    SELECT dataset_id, dataset_name, datasets.manager_id, manager_email, dataset_compression, dataset_format, dataset_classification, dataset_schema, dataset_sensitivity, dataset_partition_template, dataset_desc, datasets.created_at, datasets.updated_at, COALESCE((SELECT jsonb_object_agg(label_key, label_value) FROM dataset_labels WHERE dataset_labels.dataset_id = datasets.dataset_id), '{}') AS dataset_labels
     FROM datasets
     JOIN managers on datasets.manager_id = managers.manager_id
-    WHERE dataset_name LIKE '%' || $1 || '%'
+    WHERE dataset_name = $1 AND datasets.deleted_at IS NOT NULL
+"#;
+
+pub const SEARCH_DATASETS: &str = r#"
+    WITH matches AS (
+        SELECT dataset_id, dataset_name, datasets.manager_id, manager_email, dataset_compression, dataset_format, dataset_classification, dataset_schema, dataset_sensitivity, dataset_partition_template, dataset_desc, datasets.created_at, datasets.updated_at, COALESCE((SELECT jsonb_object_agg(label_key, label_value) FROM dataset_labels WHERE dataset_labels.dataset_id = datasets.dataset_id), '{}') AS dataset_labels,
+            CASE WHEN $2::TEXT IS NULL THEN 0 ELSE ts_rank(dataset_search_document(dataset_name, dataset_desc, dataset_schema), to_tsquery('english', $2)) END
+            + CASE WHEN dataset_name ILIKE $1 THEN 1 ELSE 0 END AS search_rank
+        FROM datasets
+        JOIN managers on datasets.manager_id = managers.manager_id
+        WHERE datasets.deleted_at IS NULL
+        AND ($1::TEXT IS NULL
+            OR dataset_name ILIKE $1
+            OR ($2::TEXT IS NOT NULL AND dataset_search_document(dataset_name, dataset_desc, dataset_schema) @@ to_tsquery('english', $2)))
+        AND ($3::classification_t IS NULL OR dataset_classification = $3)
+        AND ($4::format_t IS NULL OR dataset_format = $4)
+        AND ($5::compression_t IS NULL OR dataset_compression = $5)
+        AND ($6::VARCHAR IS NULL OR manager_email = $6)
+        AND ($7::TIMESTAMPTZ IS NULL OR datasets.updated_at >= $7)
+        AND ($8::TIMESTAMPTZ IS NULL OR datasets.updated_at <= $8)
+        AND ($9::JSONB IS NULL OR datasets.dataset_id IN (
+            SELECT dataset_id FROM dataset_labels
+            JOIN jsonb_each_text($9) AS selector ON label_key = selector.key AND label_value = selector.value
+            GROUP BY dataset_id
+            HAVING COUNT(*) = (SELECT COUNT(*) FROM jsonb_object_keys($9))
+        ))
+    )
+    SELECT * FROM matches
+    ORDER BY search_rank DESC, updated_at DESC
+    OFFSET COALESCE($10::INTEGER, 0) LIMIT $11::INTEGER
+"#;
+
+pub const SEARCH_DATASET_FACETS: &str = r#"
+    WITH matches AS (
+        SELECT dataset_classification, dataset_format, dataset_compression, manager_email
+        FROM datasets
+        JOIN managers on datasets.manager_id = managers.manager_id
+        WHERE datasets.deleted_at IS NULL
+        AND ($1::TEXT IS NULL
+            OR dataset_name ILIKE $1
+            OR ($2::TEXT IS NOT NULL AND dataset_search_document(dataset_name, dataset_desc, dataset_schema) @@ to_tsquery('english', $2)))
+        AND ($3::classification_t IS NULL OR dataset_classification = $3)
+        AND ($4::format_t IS NULL OR dataset_format = $4)
+        AND ($5::compression_t IS NULL OR dataset_compression = $5)
+        AND ($6::VARCHAR IS NULL OR manager_email = $6)
+        AND ($7::TIMESTAMPTZ IS NULL OR datasets.updated_at >= $7)
+        AND ($8::TIMESTAMPTZ IS NULL OR datasets.updated_at <= $8)
+        AND ($9::JSONB IS NULL OR datasets.dataset_id IN (
+            SELECT dataset_id FROM dataset_labels
+            JOIN jsonb_each_text($9) AS selector ON label_key = selector.key AND label_value = selector.value
+            GROUP BY dataset_id
+            HAVING COUNT(*) = (SELECT COUNT(*) FROM jsonb_object_keys($9))
+        ))
+    )
+    SELECT 'classification' AS facet, dataset_classification::TEXT AS facet_value, COUNT(*) AS facet_count FROM matches GROUP BY dataset_classification
+    UNION ALL
+    SELECT 'format', dataset_format::TEXT, COUNT(*) FROM matches GROUP BY dataset_format
+    UNION ALL
+    SELECT 'compression', dataset_compression::TEXT, COUNT(*) FROM matches GROUP BY dataset_compression
+    UNION ALL
+    SELECT 'manager', manager_email, COUNT(*) FROM matches GROUP BY manager_email
 "#;
 
 pub const LIST_DATASETS: &str = r#"
-    SELECT dataset_id, dataset_name, datasets.manager_id, manager_email, dataset_compression, dataset_format, dataset_classification, dataset_schema, dataset_desc, datasets.created_at, datasets.updated_at
+    SELECT dataset_id, dataset_name, datasets.manager_id, manager_email, dataset_compression, dataset_format, dataset_classification, dataset_schema, dataset_sensitivity, dataset_partition_template, dataset_desc, datasets.created_at, datasets.updated_at, COALESCE((SELECT jsonb_object_agg(label_key, label_value) FROM dataset_labels WHERE dataset_labels.dataset_id = datasets.dataset_id), '{}') AS dataset_labels
     FROM datasets
     JOIN managers on datasets.manager_id = managers.manager_id
+    WHERE datasets.deleted_at IS NULL
+"#;
+
+pub const UPDATE_DATASET: &str = r#"
+    UPDATE datasets SET dataset_desc = $2, dataset_schema = $3
+    WHERE dataset_id = $1
+    RETURNING dataset_id, dataset_name, manager_id, dataset_compression, dataset_format, dataset_classification, dataset_schema, dataset_sensitivity, dataset_partition_template, dataset_desc, created_at, updated_at, COALESCE((SELECT jsonb_object_agg(label_key, label_value) FROM dataset_labels WHERE dataset_labels.dataset_id = datasets.dataset_id), '{}') AS dataset_labels
 "#;
 
 pub const DELETE_DATASET: &str = r#"
-    DELETE FROM datasets where dataset_name = $1
+    WITH dataset AS (
+        UPDATE datasets SET deleted_at = NOW()
+        WHERE dataset_name = $1 AND deleted_at IS NULL
+        RETURNING dataset_id, deleted_at
+    ),
+    deleted_partitions AS (
+        UPDATE partitions SET deleted_at = dataset.deleted_at
+        FROM dataset
+        WHERE partitions.dataset_id = dataset.dataset_id AND partitions.deleted_at IS NULL
+        RETURNING partition_id
+    )
+    SELECT COUNT(*) AS partition_count FROM deleted_partitions
+"#;
+
+pub const RESTORE_DATASET: &str = r#"
+    WITH dataset AS (
+        SELECT dataset_id, deleted_at FROM datasets
+        WHERE dataset_id = $1 AND deleted_at IS NOT NULL
+    ),
+    restored_partitions AS (
+        UPDATE partitions SET deleted_at = NULL
+        FROM dataset
+        WHERE partitions.dataset_id = dataset.dataset_id AND partitions.deleted_at = dataset.deleted_at
+    )
+    UPDATE datasets SET deleted_at = NULL
+    FROM dataset
+    WHERE datasets.dataset_id = dataset.dataset_id
+    RETURNING datasets.dataset_id, dataset_name, manager_id, dataset_compression, dataset_format, dataset_classification, dataset_schema, dataset_sensitivity, dataset_partition_template, dataset_desc, created_at, updated_at, COALESCE((SELECT jsonb_object_agg(label_key, label_value) FROM dataset_labels WHERE dataset_labels.dataset_id = datasets.dataset_id), '{}') AS dataset_labels
+"#;
+
+pub const PURGE_DATASETS: &str = r#"
+    DELETE FROM datasets WHERE deleted_at < $1
+    RETURNING dataset_id, dataset_name, manager_id, dataset_compression, dataset_format, dataset_classification, dataset_schema, dataset_sensitivity, dataset_partition_template, dataset_desc, created_at, updated_at
 "#;
 
 pub const REGISTER_PARTITION: &str = r#"
-    INSERT INTO partitions (partition_name, partition_url, partition_size, dataset_id)
-    VALUES ($1, $2, $3, $4)
+    INSERT INTO partitions (partition_name, partition_url, partition_size, partition_date, partition_hour, partition_shard, partition_data_start, partition_data_end, dataset_id)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+    ON CONFLICT (partition_name, dataset_id) DO UPDATE
+    SET partition_url=excluded.partition_url, partition_size=excluded.partition_size, partition_date=excluded.partition_date, partition_hour=excluded.partition_hour, partition_shard=excluded.partition_shard, partition_data_start=excluded.partition_data_start, partition_data_end=excluded.partition_data_end, deleted_at=NULL
+    RETURNING partition_id, partition_name, partition_url, partition_size, partition_date, partition_hour, partition_shard, partition_data_start, partition_data_end, dataset_id, created_at, updated_at
+"#;
+
+// registers many partitions with one statement, from arrays of each of their columns
+pub const REGISTER_PARTITIONS: &str = r#"
+    INSERT INTO partitions (partition_name, partition_url, partition_size, partition_date, partition_hour, partition_shard, partition_data_start, partition_data_end, dataset_id)
+    SELECT partition_name, partition_url, partition_size, partition_date, partition_hour, partition_shard, partition_data_start, partition_data_end, $9
+    FROM UNNEST($1::text[], $2::text[], $3::bigint[], $4::date[], $5::smallint[], $6::integer[], $7::timestamptz[], $8::timestamptz[])
+        AS p(partition_name, partition_url, partition_size, partition_date, partition_hour, partition_shard, partition_data_start, partition_data_end)
     ON CONFLICT (partition_name, dataset_id) DO UPDATE
-    SET partition_url=excluded.partition_url, partition_size=excluded.partition_size
-    RETURNING partition_id, partition_name, partition_url, partition_size, dataset_id, created_at, updated_at
+    SET partition_url=excluded.partition_url, partition_size=excluded.partition_size, partition_date=excluded.partition_date, partition_hour=excluded.partition_hour, partition_shard=excluded.partition_shard, partition_data_start=excluded.partition_data_start, partition_data_end=excluded.partition_data_end, deleted_at=NULL
+    RETURNING partition_id, partition_name, partition_url, partition_size, partition_date, partition_hour, partition_shard, partition_data_start, partition_data_end, dataset_id, created_at, updated_at
 "#;
 
 pub const DELETE_PARTITION: &str = r#"
-    DELETE FROM partitions where dataset_id = $1 AND partition_name = $2
+    UPDATE partitions SET deleted_at = NOW()
+    WHERE dataset_id = $1 AND partition_name = $2 AND deleted_at IS NULL
+    RETURNING partition_id, partition_name, partition_url, partition_size, partition_date, partition_hour, partition_shard, partition_data_start, partition_data_end, dataset_id, created_at, updated_at
+"#;
+
+pub const RESTORE_PARTITION: &str = r#"
+    UPDATE partitions SET deleted_at = NULL
+    WHERE dataset_id = $1 AND partition_name = $2 AND deleted_at IS NOT NULL
+    RETURNING partition_id, partition_name, partition_url, partition_size, partition_date, partition_hour, partition_shard, partition_data_start, partition_data_end, dataset_id, created_at, updated_at
+"#;
+
+pub const PURGE_PARTITIONS: &str = r#"
+    DELETE FROM partitions
+    USING datasets
+    WHERE partitions.dataset_id = datasets.dataset_id
+    AND partitions.deleted_at < $1
+    RETURNING partition_id, partition_name, partition_url, partition_size, partition_date, partition_hour, partition_shard, partition_data_start, partition_data_end, partitions.dataset_id, dataset_name, partitions.created_at, partitions.updated_at
 "#;
 
 pub const FIND_PARTITION: &str = r#"
-    SELECT partition_id, partition_name, partition_url, partition_size, dataset_id, created_at, updated_at
+    SELECT partition_id, partition_name, partition_url, partition_size, partition_date, partition_hour, partition_shard, partition_data_start, partition_data_end, dataset_id, created_at, updated_at
     FROM partitions 
-    WHERE partition_name = $1 AND dataset_id = $2
+    WHERE partition_name = $1 AND dataset_id = $2 AND deleted_at IS NULL
 "#;
 
 pub const FIND_PARTITION_LATEST: &str = r#"
-    SELECT partition_id, partition_name, partition_url, partition_size, dataset_id, created_at, updated_at
+    SELECT partition_id, partition_name, partition_url, partition_size, partition_date, partition_hour, partition_shard, partition_data_start, partition_data_end, dataset_id, created_at, updated_at
     FROM partitions 
-    WHERE dataset_id = $1
+    WHERE dataset_id = $1 AND deleted_at IS NULL
     ORDER BY created_at DESC
     LIMIT 1
 "#;
 
 pub const LIST_PARTITIONS: &str = r#"
-    SELECT partition_id, partition_name, partition_url, partition_size, dataset_id, created_at, updated_at
+    SELECT partition_id, partition_name, partition_url, partition_size, partition_date, partition_hour, partition_shard, partition_data_start, partition_data_end, dataset_id, created_at, updated_at
     FROM partitions 
-    WHERE dataset_id = $1
+    WHERE deleted_at IS NULL
 "#;
 
 pub const FIND_MANAGER: &str = r#"
-    SELECT manager_id, manager_email, manager_hash, manager_salt, api_key, is_admin, created_at, updated_at
+    SELECT manager_id, manager_email, manager_hash, manager_salt, api_key, is_admin, is_disabled, created_at, updated_at
     FROM managers
     WHERE api_key = $1
 "#;
 
+pub const FIND_MANAGER_BY_ID: &str = r#"
+    SELECT manager_id, manager_email, manager_hash, manager_salt, api_key, is_admin, is_disabled, created_at, updated_at
+    FROM managers
+    WHERE manager_id = $1
+"#;
+
+pub const LIST_MANAGERS: &str = r#"
+    SELECT manager_id, manager_email, manager_hash, manager_salt, api_key, is_admin, is_disabled, created_at, updated_at
+    FROM managers
+    ORDER BY manager_id
+"#;
+
+pub const UPDATE_MANAGER_ADMIN: &str = r#"
+    UPDATE managers SET is_admin = $2
+    WHERE manager_id = $1
+    RETURNING manager_id, manager_email, manager_hash, manager_salt, api_key, is_admin, is_disabled, created_at, updated_at
+"#;
+
+pub const UPDATE_MANAGER_DISABLED: &str = r#"
+    UPDATE managers SET is_disabled = $2
+    WHERE manager_id = $1
+    RETURNING manager_id, manager_email, manager_hash, manager_salt, api_key, is_admin, is_disabled, created_at, updated_at
+"#;
+
+pub const TRANSFER_DATASETS: &str = r#"
+    WITH stale_transfers AS (
+        DELETE FROM dataset_transfers WHERE from_manager_id = $1
+    )
+    UPDATE datasets SET manager_id = $2
+    WHERE manager_id = $1
+"#;
+
+pub const DELETE_MANAGER: &str = r#"
+    WITH transferred AS (
+        UPDATE datasets SET manager_id = $2 WHERE manager_id = $1
+    )
+    DELETE FROM managers WHERE manager_id = $1
+    RETURNING manager_id, manager_email, manager_hash, manager_salt, api_key, is_admin, is_disabled, created_at, updated_at
+"#;
+
 pub const MANAGED_DATASETS: &str = r#"
-    SELECT dataset_id, dataset_name, datasets.manager_id, dataset_compression, dataset_format, dataset_classification, dataset_schema, dataset_desc, datasets.created_at, datasets.updated_at
+    SELECT dataset_id, dataset_name, datasets.manager_id, dataset_compression, dataset_format, dataset_classification, dataset_schema, dataset_sensitivity, dataset_partition_template, dataset_desc, datasets.created_at, datasets.updated_at, COALESCE((SELECT jsonb_object_agg(label_key, label_value) FROM dataset_labels WHERE dataset_labels.dataset_id = datasets.dataset_id), '{}') AS dataset_labels
     FROM datasets
     JOIN managers ON managers.manager_id = datasets.manager_id
-    WHERE managers.api_key = $1
+    WHERE datasets.deleted_at IS NULL
+    AND (managers.api_key = $1 OR datasets.dataset_id IN (
+        SELECT dataset_id FROM dataset_maintainers
+        JOIN managers ON managers.manager_id = dataset_maintainers.manager_id
+        WHERE managers.api_key = $1
+    ))
+"#;
+
+pub const FIND_MANAGER_BY_EMAIL: &str = r#"
+    SELECT manager_id, manager_email, manager_hash, manager_salt, api_key, is_admin, is_disabled, created_at, updated_at
+    FROM managers
+    WHERE manager_email = $1
+"#;
+
+pub const LIST_MAINTAINERS: &str = r#"
+    SELECT dataset_id, dataset_maintainers.manager_id, manager_email, dataset_maintainers.created_at
+    FROM dataset_maintainers
+    JOIN managers ON managers.manager_id = dataset_maintainers.manager_id
+    WHERE dataset_id = $1
+    ORDER BY dataset_maintainers.created_at
+"#;
+
+pub const ADD_MAINTAINER: &str = r#"
+    INSERT INTO dataset_maintainers (dataset_id, manager_id)
+    VALUES ($1, $2)
+    ON CONFLICT (dataset_id, manager_id) DO UPDATE
+    SET manager_id = excluded.manager_id
+    RETURNING dataset_id, manager_id, created_at
+"#;
+
+pub const REMOVE_MAINTAINER: &str = r#"
+    DELETE FROM dataset_maintainers WHERE dataset_id = $1 AND manager_id = $2
+"#;
+
+pub const LIST_READ_GRANTS: &str = r#"
+    SELECT dataset_id, dataset_read_grants.manager_id, manager_email, dataset_read_grants.created_at
+    FROM dataset_read_grants
+    JOIN managers ON managers.manager_id = dataset_read_grants.manager_id
+    WHERE dataset_id = $1
+    ORDER BY dataset_read_grants.created_at
+"#;
+
+pub const ADD_READ_GRANT: &str = r#"
+    INSERT INTO dataset_read_grants (dataset_id, manager_id)
+    VALUES ($1, $2)
+    ON CONFLICT (dataset_id, manager_id) DO UPDATE
+    SET manager_id = excluded.manager_id
+    RETURNING dataset_id, manager_id, created_at
+"#;
+
+pub const REMOVE_READ_GRANT: &str = r#"
+    DELETE FROM dataset_read_grants WHERE dataset_id = $1 AND manager_id = $2
+"#;
+
+pub const REQUEST_TRANSFER: &str = r#"
+    INSERT INTO dataset_transfers (dataset_id, from_manager_id, to_manager_id)
+    VALUES ($1, $2, $3)
+    ON CONFLICT (dataset_id) DO UPDATE
+    SET from_manager_id = excluded.from_manager_id, to_manager_id = excluded.to_manager_id, created_at = NOW()
+    RETURNING transfer_id
+"#;
+
+pub const FIND_TRANSFER: &str = r#"
+    SELECT transfer_id, dataset_transfers.dataset_id, dataset_name, from_manager_id, from_managers.manager_email AS from_manager_email, to_manager_id, to_managers.manager_email AS to_manager_email, dataset_transfers.created_at
+    FROM dataset_transfers
+    JOIN datasets ON datasets.dataset_id = dataset_transfers.dataset_id
+    JOIN managers from_managers ON from_managers.manager_id = dataset_transfers.from_manager_id
+    JOIN managers to_managers ON to_managers.manager_id = dataset_transfers.to_manager_id
+    WHERE transfer_id = $1
+"#;
+
+pub const LIST_TRANSFERS: &str = r#"
+    SELECT transfer_id, dataset_transfers.dataset_id, dataset_name, from_manager_id, from_managers.manager_email AS from_manager_email, to_manager_id, to_managers.manager_email AS to_manager_email, dataset_transfers.created_at
+    FROM dataset_transfers
+    JOIN datasets ON datasets.dataset_id = dataset_transfers.dataset_id
+    JOIN managers from_managers ON from_managers.manager_id = dataset_transfers.from_manager_id
+    JOIN managers to_managers ON to_managers.manager_id = dataset_transfers.to_manager_id
+    WHERE (from_manager_id = $1 OR to_manager_id = $1) AND datasets.deleted_at IS NULL
+    ORDER BY dataset_transfers.created_at DESC
+"#;
+
+pub const FIND_DATASET_TRANSFER: &str = r#"
+    SELECT transfer_id, dataset_transfers.dataset_id, dataset_name, from_manager_id, from_managers.manager_email AS from_manager_email, to_manager_id, to_managers.manager_email AS to_manager_email, dataset_transfers.created_at
+    FROM dataset_transfers
+    JOIN datasets ON datasets.dataset_id = dataset_transfers.dataset_id
+    JOIN managers from_managers ON from_managers.manager_id = dataset_transfers.from_manager_id
+    JOIN managers to_managers ON to_managers.manager_id = dataset_transfers.to_manager_id
+    WHERE dataset_transfers.dataset_id = $1
+"#;
+
+pub const ACCEPT_TRANSFER: &str = r#"
+    WITH transfer AS (
+        DELETE FROM dataset_transfers USING datasets
+        WHERE transfer_id = $1
+        AND datasets.dataset_id = dataset_transfers.dataset_id
+        AND datasets.manager_id = dataset_transfers.from_manager_id
+        RETURNING dataset_transfers.dataset_id, from_manager_id, to_manager_id
+    ),
+    maintainer AS (
+        DELETE FROM dataset_maintainers USING transfer
+        WHERE dataset_maintainers.dataset_id = transfer.dataset_id
+        AND dataset_maintainers.manager_id = transfer.to_manager_id
+    )
+    UPDATE datasets SET manager_id = transfer.to_manager_id
+    FROM transfer
+    WHERE datasets.dataset_id = transfer.dataset_id
+    AND datasets.manager_id = transfer.from_manager_id
+    RETURNING datasets.dataset_id, dataset_name, manager_id, dataset_compression, dataset_format, dataset_classification, dataset_schema, dataset_sensitivity, dataset_partition_template, dataset_desc, created_at, updated_at, COALESCE((SELECT jsonb_object_agg(label_key, label_value) FROM dataset_labels WHERE dataset_labels.dataset_id = datasets.dataset_id), '{}') AS dataset_labels
+"#;
+
+pub const DELETE_TRANSFER: &str = r#"
+    DELETE FROM dataset_transfers WHERE transfer_id = $1
 "#;
 
 pub const REGISTER_MANAGER: &str = r#"
     INSERT INTO managers (manager_email, manager_hash, manager_salt, api_key)
     VALUES ($1, $2, $3, $4)
-    RETURNING manager_id, manager_email, api_key, manager_hash, manager_salt, is_admin, created_at, updated_at
+    RETURNING manager_id, manager_email, api_key, manager_hash, manager_salt, is_admin, is_disabled, created_at, updated_at
 "#;
 
 pub const AUTH_MANAGER: &str = r#"
-    SELECT manager_id, manager_email, api_key, manager_hash, manager_salt, is_admin, created_at, updated_at
+    SELECT manager_id, manager_email, api_key, manager_hash, manager_salt, is_admin, is_disabled, created_at, updated_at
     FROM managers
     WHERE manager_email = $1
 "#;
 
+pub const INSERT_AUDIT_EVENT: &str = r#"
+    INSERT INTO audit_events (actor_type, actor_id, event_action, target_type, target_name, snapshot_before, snapshot_after)
+    VALUES ($1, $2, $3, $4, $5, $6, $7)
+"#;
+
+pub const LIST_AUDIT_EVENTS: &str = r#"
+    SELECT event_id, actor_type, actor_id, event_action, target_type, target_name, snapshot_before, snapshot_after, created_at
+    FROM audit_events
+    WHERE ($1::VARCHAR IS NULL OR actor_type = $1)
+    AND ($2::VARCHAR IS NULL OR actor_id = $2)
+    AND ($3::VARCHAR IS NULL OR event_action = $3)
+    AND ($4::VARCHAR IS NULL OR target_type = $4)
+    AND ($5::VARCHAR IS NULL OR target_name = $5)
+    AND ($6::TIMESTAMPTZ IS NULL OR created_at >= $6)
+    AND ($7::TIMESTAMPTZ IS NULL OR created_at <= $7)
+    ORDER BY event_id DESC
+    OFFSET COALESCE($8::INTEGER, 0) LIMIT $9::INTEGER
+"#;
+
+pub const INSERT_ACCESS_DENIAL: &str = r#"
+    INSERT INTO access_log (manager_id, remote_addr, dataset_name, partition_name, access_action, denial_reason)
+    VALUES ($1, $2, $3, $4, $5, $6)
+"#;
+
+pub const LIST_ACCESS_DENIALS: &str = r#"
+    SELECT access_id, manager_id, remote_addr, dataset_name, partition_name, access_action, denial_reason, created_at
+    FROM access_log
+    WHERE ($1::INTEGER IS NULL OR manager_id = $1)
+    AND ($2::VARCHAR IS NULL OR dataset_name = $2)
+    AND ($3::TIMESTAMPTZ IS NULL OR created_at >= $3)
+    AND ($4::TIMESTAMPTZ IS NULL OR created_at <= $4)
+    ORDER BY access_id DESC
+    OFFSET COALESCE($5::INTEGER, 0) LIMIT $6::INTEGER
+"#;
+
+pub const FIND_SCHEMA_FIELDS: &str = r#"
+    WITH latest AS (
+        SELECT dataset_id, MAX(schema_version) AS schema_version
+        FROM dataset_schema_versions
+        GROUP BY dataset_id
+    )
+    SELECT fields.dataset_id, dataset_name, fields.schema_version, fields.schema_version = latest.schema_version AS is_latest, field_name, field_type, versions.created_at
+    FROM dataset_schema_fields fields
+    JOIN dataset_schema_versions versions ON versions.dataset_id = fields.dataset_id AND versions.schema_version = fields.schema_version
+    JOIN latest ON latest.dataset_id = fields.dataset_id
+    JOIN datasets ON datasets.dataset_id = fields.dataset_id
+    WHERE datasets.deleted_at IS NULL
+    AND ($1::TEXT IS NULL OR LOWER(field_name) LIKE $1)
+    AND ($2::TEXT IS NULL OR LOWER(field_type) = LOWER($2))
+    AND ($3::BOOLEAN OR fields.schema_version = latest.schema_version)
+    ORDER BY dataset_name, fields.schema_version DESC, field_name
+"#;
+
+pub const UPDATE_PARTITION_TEMPLATE: &str = r#"
+    UPDATE datasets SET dataset_partition_template = $2
+    WHERE dataset_id = $1
+"#;
+
+pub const UPDATE_DATASET_SENSITIVITY: &str = r#"
+    UPDATE datasets SET dataset_sensitivity = $2
+    WHERE dataset_id = $1
+"#;
+
+pub const LIST_PII_FIELDS: &str = r#"
+    SELECT dataset_id, dataset_name, dataset_classification, field_name, dataset_schema -> field_name AS field_type, sensitivity
+    FROM datasets, jsonb_each(dataset_sensitivity) AS fields(field_name, sensitivity)
+    WHERE deleted_at IS NULL AND ($1::JSONB IS NULL OR sensitivity = $1)
+    ORDER BY dataset_name, field_name
+"#;
+
+pub const DELETE_DATASET_LABELS: &str = r#"
+    DELETE FROM dataset_labels WHERE dataset_id = $1
+"#;
+
+pub const INSERT_DATASET_LABELS: &str = r#"
+    INSERT INTO dataset_labels (dataset_id, label_key, label_value)
+    SELECT $1, key, value FROM jsonb_each_text($2::JSONB)
+"#;
+
+pub const CATALOG_STATS: &str = r#"
+    SELECT dataset_classification, COUNT(DISTINCT datasets.dataset_id) AS dataset_count, COUNT(partition_id) AS partition_count, COALESCE(SUM(partition_size), 0)::BIGINT AS partition_bytes
+    FROM datasets
+    LEFT JOIN partitions ON partitions.dataset_id = datasets.dataset_id AND partitions.deleted_at IS NULL
+    WHERE datasets.deleted_at IS NULL
+    GROUP BY dataset_classification
+"#;
+
 pub const DATASET_ATTRIBUTES: &str = r#"
     WITH formats AS (
         SELECT json_agg(enum_range(null::format_t))->>0 as format_variants
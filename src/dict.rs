@@ -1,7 +1,11 @@
+use std::collections::{HashMap, HashSet};
+
 use crate::error::Error;
+use crate::partition_template::PartitionTemplate;
 use crate::service::DataService;
+use crate::telemetry::mask;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use log::info;
 use postgres_types::{FromSql, ToSql};
 use serde::{Deserialize, Serialize};
@@ -19,6 +23,7 @@ pub struct Manager {
     pub email: String,
     pub api_key: Uuid,
     pub admin: bool,
+    pub disabled: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     #[serde(skip_serializing)]
@@ -44,7 +49,7 @@ impl Manager {
 
     /// Retrieves a manager record from the database, if one is found.
     pub async fn find(svc: &mut impl DataService, api_key: Uuid) -> Result<Manager, Error> {
-        info!("finding manager by api key: {}", api_key);
+        info!("finding manager by api key: {}", mask(api_key));
         svc.find_manager(&api_key).await
     }
 
@@ -86,11 +91,124 @@ impl Manager {
         .await
     }
 
-    /// Retrieves all datasets managed by the current manager.
+    /// Retrieves all datasets owned or co-maintained by the current manager.
     pub async fn datasets(&self, svc: &mut impl DataService) -> Result<Vec<Dataset>, Error> {
-        info!("listing datasets managed by: {}", self.api_key);
+        info!("listing datasets managed by: {}", mask(self.api_key));
         svc.manager_datasets(&self.api_key).await
     }
+
+    /// Retrieves a manager record by its email address, regardless of whether the manager is
+    /// disabled.
+    pub async fn find_by_email(
+        svc: &mut impl DataService,
+        email: impl AsRef<str>,
+    ) -> Result<Manager, Error> {
+        info!("finding manager by email: {}", email.as_ref());
+        svc.find_manager_by_email(email.as_ref()).await
+    }
+
+    /// Retrieves all pending dataset ownership transfers sent or received by the current manager.
+    pub async fn transfers(
+        &self,
+        svc: &mut impl DataService,
+    ) -> Result<Vec<DatasetTransfer>, Error> {
+        info!("listing dataset transfers for manager: {}", self.email);
+        svc.list_transfers(self).await
+    }
+
+    /// Retrieves a manager record by its id, regardless of whether the manager is disabled.
+    pub async fn find_by_id(svc: &mut impl DataService, id: i32) -> Result<Manager, Error> {
+        info!("finding manager by id: {}", id);
+        svc.find_manager_by_id(id).await
+    }
+
+    /// Retrieves all manager records, ordered by id.
+    pub async fn list(svc: &mut impl DataService) -> Result<Vec<Manager>, Error> {
+        info!("listing managers");
+        svc.list_managers().await
+    }
+
+    /// Promotes (or demotes) the current manager to an admin.
+    pub async fn set_admin(
+        &self,
+        svc: &mut impl DataService,
+        admin: bool,
+    ) -> Result<Manager, Error> {
+        info!("setting admin={} for manager: {}", admin, self.email);
+        svc.update_manager_admin(self.id, admin).await
+    }
+
+    /// Disables (or re-enables) the current manager. Disabled managers can neither log in nor use
+    /// their API key.
+    pub async fn set_disabled(
+        &self,
+        svc: &mut impl DataService,
+        disabled: bool,
+    ) -> Result<Manager, Error> {
+        info!("setting disabled={} for manager: {}", disabled, self.email);
+        svc.update_manager_disabled(self.id, disabled).await
+    }
+
+    /// Moves ownership of every dataset managed by the current manager to another manager,
+    /// returning the number of datasets transferred.
+    pub async fn transfer_datasets(
+        &self,
+        svc: &mut impl DataService,
+        to: &Manager,
+    ) -> Result<u64, Error> {
+        info!(
+            "transferring datasets managed by: {} to: {}",
+            self.email, to.email
+        );
+        svc.transfer_datasets(self.id, to.id).await
+    }
+
+    /// Deletes the current manager, first transferring its datasets to the `successor` manager.
+    pub async fn delete(
+        self,
+        svc: &mut impl DataService,
+        successor: &Manager,
+    ) -> Result<(), Error> {
+        info!(
+            "deleting manager: {}, datasets transferred to: {}",
+            self.email, successor.email
+        );
+        svc.delete_manager(self.id, successor.id).await
+    }
+
+    /// Creates the first admin when no admin exists yet. If a manager with the same email is
+    /// already registered, it is authenticated and promoted instead, and the configuration is
+    /// rejected when it can't be. Returns `None` when an admin already exists, leaving the managers
+    /// untouched.
+    pub async fn bootstrap_admin(
+        svc: &mut impl DataService,
+        email: impl AsRef<str>,
+        password: impl AsRef<str>,
+    ) -> Result<Option<Manager>, Error> {
+        if svc.list_managers().await?.iter().any(|m| m.admin) {
+            info!("admin manager exists, skipping bootstrap");
+            return Ok(None);
+        }
+
+        info!("bootstrapping admin manager: {}", email.as_ref());
+        let manager = match svc.auth_manager(email.as_ref(), password.as_ref()).await {
+            Ok(manager) => manager,
+            Err(Error::NotFound(_)) => {
+                svc.register_manager(email.as_ref(), password.as_ref())
+                    .await?
+            }
+            Err(Error::Auth(msg)) => {
+                return Err(Error::Config(format!(
+                    "admin manager '{}' is already registered and can't be promoted: {}",
+                    email.as_ref(),
+                    msg
+                )))
+            }
+            Err(e) => return Err(e),
+        };
+
+        manager.set_admin(svc, true).await.map(Some)
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -202,6 +320,14 @@ impl FileExt for Compression {
     }
 }
 
+/// Returns the extension of a partition's file, based on its dataset's format and compression, e.g.
+/// "json.tar.gz" or "csv".
+pub fn partition_ext(format: &Format, compression: &Compression) -> String {
+    format!("{}.{}", format.to_ext(), compression.to_ext())
+        .trim_end_matches('.')
+        .into()
+}
+
 /// A Classification is used to indicate the level of security needed to protect datasets.
 #[derive(Debug, FromSql, ToSql, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 #[postgres(name = "classification_t")]
@@ -226,6 +352,47 @@ impl std::fmt::Display for Classification {
     }
 }
 
+impl Classification {
+    /// Ranks how strictly data of this classification must be protected, from public (lowest) to
+    /// restricted (highest).
+    pub fn strictness(&self) -> u8 {
+        match self {
+            Classification::Public => 0,
+            Classification::Internal => 1,
+            Classification::Confidential => 2,
+            Classification::Restricted => 3,
+        }
+    }
+
+    /// The longest a signed URL may grant access to partitions of this classification.
+    pub fn max_signed_url_ttl(&self) -> Duration {
+        match self {
+            Classification::Public => Duration::days(7),
+            Classification::Internal => Duration::days(1),
+            Classification::Confidential => Duration::hours(1),
+            Classification::Restricted => Duration::minutes(15),
+        }
+    }
+}
+
+#[test]
+fn test_max_signed_url_ttl() {
+    let classifications = &[
+        Classification::Public,
+        Classification::Internal,
+        Classification::Confidential,
+        Classification::Restricted,
+    ];
+    for pair in classifications.windows(2) {
+        assert!(pair[0].strictness() < pair[1].strictness());
+        assert!(pair[0].max_signed_url_ttl() > pair[1].max_signed_url_ttl());
+    }
+    assert!(
+        Classification::Public.max_signed_url_ttl()
+            <= Duration::seconds(crate::signed_url::MAX_SIGNED_URL_TTL_SECS)
+    );
+}
+
 #[test]
 fn test_display_classification() {
     assert_eq!("confidential", format!("{}", Classification::Confidential));
@@ -237,6 +404,165 @@ fn test_display_classification() {
 /// A DatasetSchema is the "schema" key found in a dd.json config file
 pub type DatasetSchema = std::collections::HashMap<String, Option<String>>;
 
+/// A Sensitivity annotates a schema field with the kind of personally identifiable information (PII)
+/// it holds.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Sensitivity {
+    Email,
+    Phone,
+    Card,
+    Name,
+    Address,
+    GovernmentId,
+    IpAddress,
+    DateOfBirth,
+    Other,
+}
+
+impl Sensitivity {
+    /// The least strict Classification of a dataset allowed to contain a field of this sensitivity.
+    pub fn required_classification(self) -> Classification {
+        match self {
+            Sensitivity::Card | Sensitivity::GovernmentId => Classification::Restricted,
+            _ => Classification::Confidential,
+        }
+    }
+}
+
+/// DatasetSensitivity is the "sensitivity" key found in a dd.json config file, mapping schema field
+/// names to the kind of PII they hold, e.g. "customer_email": "email".
+pub type DatasetSensitivity = HashMap<String, Sensitivity>;
+
+/// Validates that every annotated field exists in the schema, and that the classification is at
+/// least as strict as the most sensitive field requires.
+pub fn validate_sensitivity(
+    schema: &DatasetSchema,
+    classification: &Classification,
+    sensitivity: &DatasetSensitivity,
+) -> Result<(), Error> {
+    for (field, kind) in sensitivity {
+        if !schema.contains_key(field) {
+            return Err(Error::InputValidation(format!(
+                "sensitive field '{}' is not in the dataset schema",
+                field
+            )));
+        }
+
+        let required = kind.required_classification();
+        if classification.strictness() < required.strictness() {
+            return Err(Error::InputValidation(format!(
+                "field '{}' holds {:?} PII, which requires a classification of '{}' or stricter, not '{}'",
+                field, kind, required, classification
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_validate_sensitivity() {
+    let schema: DatasetSchema = vec![
+        ("email".to_string(), Some("string".to_string())),
+        ("card_number".to_string(), Some("string".to_string())),
+    ]
+    .into_iter()
+    .collect();
+    let email: DatasetSensitivity = vec![("email".to_string(), Sensitivity::Email)]
+        .into_iter()
+        .collect();
+    let card: DatasetSensitivity = vec![("card_number".to_string(), Sensitivity::Card)]
+        .into_iter()
+        .collect();
+    let missing: DatasetSensitivity = vec![("phone".to_string(), Sensitivity::Phone)]
+        .into_iter()
+        .collect();
+
+    let tests = &[
+        (&Classification::Public, &email, false),
+        (&Classification::Internal, &email, false),
+        (&Classification::Confidential, &email, true),
+        (&Classification::Restricted, &email, true),
+        (&Classification::Confidential, &card, false),
+        (&Classification::Restricted, &card, true),
+        (&Classification::Restricted, &missing, false),
+    ];
+    for (classification, sensitivity, valid) in tests {
+        assert_eq!(
+            validate_sensitivity(&schema, classification, sensitivity).is_ok(),
+            *valid,
+            "{} {:?}",
+            classification,
+            sensitivity
+        );
+    }
+    assert!(validate_sensitivity(&schema, &Classification::Public, &HashMap::new()).is_ok());
+}
+
+/// DatasetLabels are the "labels" key found in a dd.json config file, used to group datasets by
+/// free-form key/value pairs, e.g. "team": "billing" or "tier": "1".
+pub type DatasetLabels = HashMap<String, String>;
+
+const MAX_LABEL_KEY_LEN: usize = 63;
+const MAX_LABEL_VALUE_LEN: usize = 255;
+
+/// Validates that every label has a non-empty key, and that neither its key nor value are too long
+/// to be stored.
+pub fn validate_labels(labels: &DatasetLabels) -> Result<(), Error> {
+    for (key, value) in labels {
+        if key.is_empty() || key.len() > MAX_LABEL_KEY_LEN {
+            return Err(Error::InputValidation(format!(
+                "label key '{}' must be between 1 and {} characters",
+                key, MAX_LABEL_KEY_LEN
+            )));
+        }
+        if value.len() > MAX_LABEL_VALUE_LEN {
+            return Err(Error::InputValidation(format!(
+                "value of label '{}' must be at most {} characters",
+                key, MAX_LABEL_VALUE_LEN
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses a label selector in the form of "key1:value1,key2:value2", as used to filter datasets by
+/// their labels.
+pub fn parse_labels(selector: &str) -> Result<DatasetLabels, Error> {
+    let mut labels = DatasetLabels::new();
+    for pair in selector.split(',').filter(|pair| !pair.trim().is_empty()) {
+        let mut parts = pair.splitn(2, ':');
+        match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) => {
+                labels.insert(key.trim().into(), value.trim().into());
+            }
+            _ => {
+                return Err(Error::InputValidation(format!(
+                    "invalid label '{}', must be in the form of 'key:value'",
+                    pair
+                )))
+            }
+        }
+    }
+    validate_labels(&labels)?;
+
+    Ok(labels)
+}
+
+#[test]
+fn test_parse_labels() {
+    let labels = parse_labels("team:billing, tier:1,,url:https://example.com").unwrap();
+    assert_eq!(labels.len(), 3);
+    assert_eq!(labels["team"], "billing");
+    assert_eq!(labels["tier"], "1");
+    assert_eq!(labels["url"], "https://example.com");
+    assert!(parse_labels("").unwrap().is_empty());
+    assert!(parse_labels("team").is_err());
+    assert!(parse_labels(":billing").is_err());
+}
+
 /// A Dataset is the parent node of partitions, where each dataset is split up into one or many
 /// partitions, typically based on date or size.
 #[derive(Debug, Serialize, Deserialize)]
@@ -250,6 +576,12 @@ pub struct Dataset {
     pub format: Format,
     pub description: String,
     pub schema: DatasetSchema,
+    #[serde(default)]
+    pub sensitivity: DatasetSensitivity,
+    #[serde(default)]
+    pub labels: DatasetLabels,
+    #[serde(default)]
+    pub partition_template: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -263,6 +595,28 @@ pub struct DatasetConfig {
     pub format: Format,
     pub description: String,
     pub schema: DatasetSchema,
+    #[serde(default)]
+    pub sensitivity: DatasetSensitivity,
+    #[serde(default)]
+    pub labels: DatasetLabels,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partition_template: Option<String>,
+}
+
+impl From<&Dataset> for DatasetConfig {
+    fn from(d: &Dataset) -> Self {
+        Self {
+            name: d.name.clone(),
+            classification: d.classification.clone(),
+            compression: d.compression.clone(),
+            format: d.format.clone(),
+            description: d.description.clone(),
+            schema: d.schema.clone(),
+            sensitivity: d.sensitivity.clone(),
+            labels: d.labels.clone(),
+            partition_template: d.partition_template.clone(),
+        }
+    }
 }
 
 /// concrete data source. For local development and testing, a local, mocked, or in-memory database
@@ -274,13 +628,71 @@ impl Dataset {
         svc.find_dataset(name.as_ref()).await
     }
 
-    /// Searches for a Dataset based on the term provided.
+    /// Searches for datasets matching the term and filters provided, ranked by relevance. The term
+    /// is matched against each dataset's name, description, and schema field names and types.
     pub async fn search(
         svc: &mut impl DataService,
-        term: impl AsRef<str>,
+        params: &SearchParams,
+    ) -> Result<SearchResults, Error> {
+        info!("searching datasets for: {:?}", params);
+        svc.search_datasets(params).await
+    }
+
+    /// Finds the schema fields matching the name pattern and/or type provided, across every dataset.
+    pub async fn find_fields(
+        svc: &mut impl DataService,
+        search: &FieldSearch,
+    ) -> Result<Vec<SchemaField>, Error> {
+        info!("finding schema fields for: {:?}", search);
+        svc.find_schema_fields(search).await
+    }
+
+    /// Lists every field annotated as PII across the catalog, optionally only of the kind provided.
+    pub async fn list_pii_fields(
+        svc: &mut impl DataService,
+        sensitivity: Option<Sensitivity>,
+    ) -> Result<Vec<PiiField>, Error> {
+        info!("listing PII fields: {:?}", sensitivity);
+        svc.list_pii_fields(sensitivity).await
+    }
+
+    /// Retrieves the datasets which have every label provided, ordered by their updated_at timestamp.
+    pub async fn list_labeled(
+        svc: &mut impl DataService,
+        labels: &DatasetLabels,
+        params: Option<RangeParams>,
     ) -> Result<Vec<Dataset>, Error> {
-        info!("searching datasets for: {}", term.as_ref());
-        svc.search_datasets(term.as_ref()).await
+        info!("listing datasets labeled: {:?}", labels);
+        svc.list_labeled_datasets(labels, params).await
+    }
+
+    /// Retrieves a page of datasets ordered by their created_at timestamp, optionally only those
+    /// which have every label provided. Pages following the first are found by the cursor of the
+    /// last dataset on the page before it.
+    pub async fn page(
+        svc: &mut impl DataService,
+        labels: Option<&DatasetLabels>,
+        params: &RangeParams,
+    ) -> Result<DatasetPage, Error> {
+        let filter = DatasetFilter {
+            labels: labels.cloned(),
+            ..Default::default()
+        };
+        Dataset::page_filtered(svc, &filter, params).await
+    }
+
+    /// Retrieves a page of the datasets which match every filter provided, sorted as specified by
+    /// the range parameters, or by their created_at timestamp by default.
+    pub async fn page_filtered(
+        svc: &mut impl DataService,
+        filter: &DatasetFilter,
+        params: &RangeParams,
+    ) -> Result<DatasetPage, Error> {
+        info!(
+            "paging datasets filtered: {:?}, range: {:?}",
+            filter, params
+        );
+        svc.page_datasets(filter, params).await
     }
 
     /// Retrieves all datasets from the database ordered by their updated_at timestamp.
@@ -288,7 +700,7 @@ impl Dataset {
         svc: &mut impl DataService,
         params: Option<RangeParams>,
     ) -> Result<Vec<Dataset>, Error> {
-        if let Some(params) = params {
+        if let Some(params) = &params {
             info!(
                 "listing datasets for specified range start: {:?} to end: {:?}, count: {:?}, offset: {:?}",
                 params.start, params.end, params.count, params.offset,
@@ -299,11 +711,245 @@ impl Dataset {
         svc.list_datasets(params).await
     }
 
+    /// Soft deletes the dataset along with its partitions. Both are hidden from every lookup until
+    /// either restored or purged once the retention period has passed.
     pub async fn delete(self, svc: &mut impl DataService) -> Result<(), Error> {
         info!("deleting dataset '{}' and its partitions", self.name);
         svc.delete_dataset(&self).await
     }
 
+    /// Finds a dataset which has been deleted, but not yet purged, by its name.
+    pub async fn find_deleted(
+        svc: &mut impl DataService,
+        name: impl AsRef<str>,
+    ) -> Result<Dataset, Error> {
+        info!("finding deleted dataset: {}", name.as_ref());
+        svc.find_deleted_dataset(name.as_ref()).await
+    }
+
+    /// Restores a deleted dataset, including the partitions which were deleted along with it.
+    /// Partitions deleted individually before the dataset must be restored separately.
+    pub async fn restore(self, svc: &mut impl DataService) -> Result<Dataset, Error> {
+        info!("restoring dataset '{}' and its partitions", self.name);
+        svc.restore_dataset(&self).await
+    }
+
+    /// Permanently removes all datasets and partitions deleted longer than `retention` ago.
+    pub async fn purge_deleted(
+        svc: &mut impl DataService,
+        retention: Duration,
+    ) -> Result<Purged, Error> {
+        let deleted_before = Utc::now() - retention;
+        info!(
+            "purging datasets and partitions deleted before: {}",
+            deleted_before
+        );
+        svc.purge_deleted(deleted_before).await
+    }
+
+    /// Replaces the sensitivity annotations of the current dataset's fields, which must be allowed by
+    /// its schema and classification.
+    pub async fn set_sensitivity(
+        &self,
+        svc: &mut impl DataService,
+        sensitivity: &DatasetSensitivity,
+    ) -> Result<Dataset, Error> {
+        info!(
+            "setting field sensitivity for dataset '{}': {:?}",
+            self.name, sensitivity
+        );
+        svc.update_dataset_sensitivity(self, sensitivity).await
+    }
+
+    /// Replaces every label of the current dataset with the labels provided.
+    pub async fn set_labels(
+        &self,
+        svc: &mut impl DataService,
+        labels: &DatasetLabels,
+    ) -> Result<Dataset, Error> {
+        info!("setting labels for dataset '{}': {:?}", self.name, labels);
+        svc.update_dataset_labels(self, labels).await
+    }
+
+    /// Sets the template which the names of the current dataset's partitions must match, or removes
+    /// it if none is provided. Partitions which are already registered keep their keys.
+    pub async fn set_partition_template(
+        &self,
+        svc: &mut impl DataService,
+        template: Option<&str>,
+    ) -> Result<Dataset, Error> {
+        info!(
+            "setting partition template for dataset '{}': {:?}",
+            self.name, template
+        );
+        svc.update_partition_template(self, template).await
+    }
+
+    /// Validates a partition name against the current dataset's partition template, returning the
+    /// key parsed from its name. Without a template, any name is valid and its key is empty.
+    pub fn partition_key(&self, name: impl AsRef<str>) -> Result<PartitionKey, Error> {
+        match &self.partition_template {
+            Some(template) => PartitionTemplate::parse(template)?.key(
+                name.as_ref(),
+                &partition_ext(&self.format, &self.compression),
+            ),
+            None => Ok(PartitionKey::default()),
+        }
+    }
+
+    /// Returns the span of time covered by the data of a partition with the key provided, if the
+    /// current dataset's partition template includes a date.
+    pub fn partition_data_range(&self, key: &PartitionKey) -> Result<Option<DataRange>, Error> {
+        match &self.partition_template {
+            Some(template) => Ok(PartitionTemplate::parse(template)?.data_range(key)),
+            None => Ok(None),
+        }
+    }
+
+    /// Updates the description and schema of the current dataset. The remaining fields are fixed at
+    /// registration, since they determine where and how the dataset's partitions are stored.
+    pub async fn update(
+        &self,
+        svc: &mut impl DataService,
+        description: impl AsRef<str>,
+        schema: DatasetSchema,
+    ) -> Result<Dataset, Error> {
+        info!("updating dataset: {}", self.name);
+        svc.update_dataset(self, description.as_ref(), schema).await
+    }
+
+    /// Checks whether a manager may modify the current dataset, which is true for its owner, its
+    /// co-maintainers, and admins.
+    pub async fn is_maintained_by(
+        &self,
+        svc: &mut impl DataService,
+        manager: &Manager,
+    ) -> Result<bool, Error> {
+        if manager.admin || manager.id == self.manager_id {
+            return Ok(true);
+        }
+
+        Ok(self
+            .maintainers(svc)
+            .await?
+            .iter()
+            .any(|m| m.manager_id == manager.id))
+    }
+
+    /// Retrieves the co-maintainers of the current dataset, not including its owner.
+    pub async fn maintainers(&self, svc: &mut impl DataService) -> Result<Vec<Maintainer>, Error> {
+        info!("listing maintainers for dataset: {}", self.name);
+        svc.list_maintainers(self).await
+    }
+
+    /// Adds a manager as a co-maintainer of the current dataset.
+    pub async fn add_maintainer(
+        &self,
+        svc: &mut impl DataService,
+        manager: &Manager,
+    ) -> Result<Maintainer, Error> {
+        info!(
+            "adding maintainer '{}' to dataset: {}",
+            manager.email, self.name
+        );
+        svc.add_maintainer(self, manager).await
+    }
+
+    /// Removes a co-maintainer from the current dataset.
+    pub async fn remove_maintainer(
+        &self,
+        svc: &mut impl DataService,
+        manager_id: i32,
+    ) -> Result<(), Error> {
+        info!(
+            "removing maintainer '{}' from dataset: {}",
+            manager_id, self.name
+        );
+        svc.remove_maintainer(self, manager_id).await
+    }
+
+    /// Checks whether the partitions of the current dataset may be read, based on its
+    /// classification. Public datasets may be read by anyone, internal datasets by any manager, and
+    /// confidential or restricted datasets only by admins, maintainers, and managers granted read
+    /// access.
+    pub async fn is_readable_by(
+        &self,
+        svc: &mut impl DataService,
+        manager: Option<&Manager>,
+    ) -> Result<bool, Error> {
+        let manager = match (&self.classification, manager) {
+            (Classification::Public, _) => return Ok(true),
+            (_, None) => return Ok(false),
+            (Classification::Internal, Some(_)) => return Ok(true),
+            (_, Some(manager)) => manager,
+        };
+
+        if self.is_maintained_by(svc, manager).await? {
+            return Ok(true);
+        }
+
+        Ok(self
+            .read_grants(svc)
+            .await?
+            .iter()
+            .any(|g| g.manager_id == manager.id))
+    }
+
+    /// Retrieves the managers granted read access to the current dataset's partitions.
+    pub async fn read_grants(&self, svc: &mut impl DataService) -> Result<Vec<ReadGrant>, Error> {
+        info!("listing read grants for dataset: {}", self.name);
+        svc.list_read_grants(self).await
+    }
+
+    /// Grants a manager read access to the current dataset's partitions.
+    pub async fn grant_read(
+        &self,
+        svc: &mut impl DataService,
+        manager: &Manager,
+    ) -> Result<ReadGrant, Error> {
+        info!(
+            "granting read access to '{}' for dataset: {}",
+            manager.email, self.name
+        );
+        svc.add_read_grant(self, manager).await
+    }
+
+    /// Revokes a manager's read access to the current dataset's partitions.
+    pub async fn revoke_read(
+        &self,
+        svc: &mut impl DataService,
+        manager_id: i32,
+    ) -> Result<(), Error> {
+        info!(
+            "revoking read access from '{}' for dataset: {}",
+            manager_id, self.name
+        );
+        svc.remove_read_grant(self, manager_id).await
+    }
+
+    /// Requests that ownership of the current dataset moves to another manager. The transfer takes
+    /// effect once the recipient accepts it, and replaces any pending transfer of the dataset.
+    pub async fn request_transfer(
+        &self,
+        svc: &mut impl DataService,
+        to: &Manager,
+    ) -> Result<DatasetTransfer, Error> {
+        info!(
+            "requesting transfer of dataset '{}' to manager: {}",
+            self.name, to.email
+        );
+        svc.request_transfer(self, to).await
+    }
+
+    /// Retrieves the pending ownership transfer of the current dataset, if there is one.
+    pub async fn pending_transfer(
+        &self,
+        svc: &mut impl DataService,
+    ) -> Result<Option<DatasetTransfer>, Error> {
+        info!("finding pending transfer for dataset: {}", self.name);
+        svc.find_dataset_transfer(self).await
+    }
+
     /// Inserts a partition into the database, using the current dataset as its reference.
     pub async fn register_partition(
         &self,
@@ -317,10 +963,94 @@ impl Dataset {
             name.as_ref(),
             &self.name
         );
-        svc.register_partition(&self, name.as_ref(), url.as_ref(), size)
+        let key = self.partition_key(name.as_ref())?;
+        let data_range = self.partition_data_range(&key)?;
+        svc.register_partition(self, name.as_ref(), url.as_ref(), size, &key, data_range)
             .await
     }
 
+    /// Inserts a partition into the database like `register_partition`, but covering the data range
+    /// provided, e.g. one set in its object's metadata, rather than one based on its name.
+    pub async fn register_partition_covering(
+        &self,
+        svc: &mut impl DataService,
+        name: impl AsRef<str>,
+        url: impl AsRef<str>,
+        size: i64,
+        data_range: DataRange,
+    ) -> Result<Partition, Error> {
+        info!(
+            "registering partition '{}' covering {} to {} for dataset: {}",
+            name.as_ref(),
+            data_range.start,
+            data_range.end,
+            &self.name
+        );
+        data_range.validate()?;
+        let key = self.partition_key(name.as_ref())?;
+        svc.register_partition(
+            self,
+            name.as_ref(),
+            url.as_ref(),
+            size,
+            &key,
+            Some(data_range),
+        )
+        .await
+    }
+
+    /// Inserts or updates many partitions of the current dataset at once, where either all of them
+    /// are registered or none are.
+    pub async fn register_partitions(
+        &self,
+        svc: &mut impl DataService,
+        partitions: &[NewPartition],
+    ) -> Result<Vec<Partition>, Error> {
+        info!(
+            "registering {} partitions for dataset: {}",
+            partitions.len(),
+            &self.name
+        );
+        let mut names = HashSet::new();
+        let registrations = partitions
+            .iter()
+            .map(|partition| {
+                if !names.insert(partition.name.as_str()) {
+                    return Err(Error::InputValidation(format!(
+                        "partition '{}' is listed more than once",
+                        partition.name
+                    )));
+                }
+
+                let key = self.partition_key(&partition.name)?;
+                let data_range = match (partition.data_start, partition.data_end) {
+                    (Some(start), Some(end)) => {
+                        let range = DataRange { start, end };
+                        range.validate()?;
+                        Some(range)
+                    }
+                    (None, None) => self.partition_data_range(&key)?,
+                    _ => {
+                        return Err(Error::InputValidation(format!(
+                            "both data_start and data_end must be set for partition '{}'",
+                            partition.name
+                        )))
+                    }
+                };
+
+                Ok(PartitionRegistration {
+                    name: partition.name.clone(),
+                    url: partition.url.clone(),
+                    size: partition.size,
+                    key,
+                    data_range,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        svc.register_partitions(self, &registrations).await
+    }
+
     pub async fn delete_partition(
         &self,
         svc: &mut impl DataService,
@@ -334,6 +1064,21 @@ impl Dataset {
         svc.delete_partition(&self, name.as_ref()).await
     }
 
+    /// Restores a deleted partition within the current dataset. Only the partition's record is
+    /// restored, so the object it refers to must still exist in cloud storage.
+    pub async fn restore_partition(
+        &self,
+        svc: &mut impl DataService,
+        name: impl AsRef<str>,
+    ) -> Result<Partition, Error> {
+        info!(
+            "restoring partition '{}' for dataset: {}",
+            name.as_ref(),
+            &self.name
+        );
+        svc.restore_partition(self, name.as_ref()).await
+    }
+
     /// Retrieves a partition based on the name provided, within the current dataset.
     pub async fn partition(
         &self,
@@ -360,10 +1105,10 @@ impl Dataset {
         svc: &mut impl DataService,
         params: Option<RangeParams>,
     ) -> Result<Vec<Partition>, Error> {
-        if let Some(params) = params {
+        if let Some(params) = &params {
             info!(
-                "listing partitions for specified range start: {:?} to end: {:?}, count: {:?}, offset: {:?} in dataset: {}",
-                params.start, params.end, params.count, params.offset, self.id,
+                "listing partitions for specified range start: {:?} to end: {:?}, data start: {:?} to end: {:?}, count: {:?}, offset: {:?} in dataset: {}",
+                params.start, params.end, params.data_start, params.data_end, params.count, params.offset, self.id,
             );
         } else {
             info!("listing all partitions for dataset: {}", self.id);
@@ -371,6 +1116,111 @@ impl Dataset {
 
         svc.list_partitions(&self, params).await
     }
+
+    /// Retrieves a page of partitions ordered by their created_at timestamp, whose keys match every
+    /// key value provided. Pages following the first are found by the cursor of the last partition
+    /// on the page before it.
+    pub async fn partitions_page(
+        &self,
+        svc: &mut impl DataService,
+        key: &PartitionKey,
+        params: &RangeParams,
+    ) -> Result<PartitionPage, Error> {
+        let filter = PartitionFilter {
+            key: *key,
+            ..Default::default()
+        };
+        self.partitions_page_filtered(svc, &filter, params).await
+    }
+
+    /// Retrieves a page of the partitions which match every filter provided, sorted as specified by
+    /// the range parameters, or by their created_at timestamp by default.
+    pub async fn partitions_page_filtered(
+        &self,
+        svc: &mut impl DataService,
+        filter: &PartitionFilter,
+        params: &RangeParams,
+    ) -> Result<PartitionPage, Error> {
+        info!(
+            "paging partitions filtered: {:?}, range: {:?} in dataset: {}",
+            filter, params, self.id
+        );
+        svc.page_partitions(self, filter, params).await
+    }
+
+    /// Retrieves the set of partitions whose keys match every key value provided, using the same
+    /// range parameters as `partitions`.
+    pub async fn partitions_by_key(
+        &self,
+        svc: &mut impl DataService,
+        key: &PartitionKey,
+        params: Option<RangeParams>,
+    ) -> Result<Vec<Partition>, Error> {
+        info!(
+            "listing partitions with key: {:?}, range: {:?} in dataset: {}",
+            key, params, self.id
+        );
+        svc.list_keyed_partitions(self, key, params).await
+    }
+}
+
+/// A Maintainer is a manager, other than the owner, who may update a dataset's metadata.
+#[derive(Debug, Serialize)]
+pub struct Maintainer {
+    pub dataset_id: i32,
+    pub manager_id: i32,
+    pub manager_email: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A ReadGrant allows a manager to read the partitions of a confidential or restricted dataset.
+#[derive(Debug, Serialize)]
+pub struct ReadGrant {
+    pub dataset_id: i32,
+    pub manager_id: i32,
+    pub manager_email: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A DatasetTransfer is a pending request to move ownership of a dataset from one manager to
+/// another, which only takes effect once accepted by the recipient.
+#[derive(Debug, Serialize)]
+pub struct DatasetTransfer {
+    #[serde(rename(serialize = "transfer_id"))]
+    pub id: i32,
+    pub dataset_id: i32,
+    pub dataset_name: String,
+    pub from_manager_id: i32,
+    pub from_manager_email: String,
+    pub to_manager_id: i32,
+    pub to_manager_email: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl DatasetTransfer {
+    /// Retrieves a pending transfer by its id.
+    pub async fn find(svc: &mut impl DataService, id: i32) -> Result<DatasetTransfer, Error> {
+        info!("finding dataset transfer: {}", id);
+        svc.find_transfer(id).await
+    }
+
+    /// Completes the transfer, making the recipient the owner of the dataset.
+    pub async fn accept(self, svc: &mut impl DataService) -> Result<Dataset, Error> {
+        info!(
+            "accepting transfer of dataset '{}' to manager: {}",
+            self.dataset_name, self.to_manager_email
+        );
+        svc.accept_transfer(&self).await
+    }
+
+    /// Discards the transfer, leaving ownership of the dataset unchanged.
+    pub async fn cancel(self, svc: &mut impl DataService) -> Result<(), Error> {
+        info!(
+            "cancelling transfer of dataset '{}' to manager: {}",
+            self.dataset_name, self.to_manager_email
+        );
+        svc.delete_transfer(&self).await
+    }
 }
 
 /// A Partition is a partial dataset, containing a subset of data. Each partition within a Dataset
@@ -385,16 +1235,496 @@ pub struct Partition {
     pub url: String,
     #[serde(rename(serialize = "partition_size"))]
     pub size: i64,
+    #[serde(rename(serialize = "partition_key"))]
+    pub key: PartitionKey,
+    pub data_start: Option<DateTime<Utc>>,
+    pub data_end: Option<DateTime<Utc>>,
     pub dataset_id: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-/// Params specify how a Dataset's Partition results should be returned.
-#[derive(Debug, Default, Clone, Copy)]
+/// A NewPartition is a partition to register in bulk with `Dataset::register_partitions`, e.g. one
+/// from an existing listing of a dataset's objects. Like a partition registered by a bucket event,
+/// its data range is based on its name unless one is provided.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewPartition {
+    pub name: String,
+    pub url: String,
+    pub size: i64,
+    pub data_start: Option<DateTime<Utc>>,
+    pub data_end: Option<DateTime<Utc>>,
+}
+
+/// A PartitionRegistration is a partition to be registered, with the key and data range which were
+/// derived for it from its dataset.
+#[derive(Debug, Clone)]
+pub struct PartitionRegistration {
+    pub name: String,
+    pub url: String,
+    pub size: i64,
+    pub key: PartitionKey,
+    pub data_range: Option<DataRange>,
+}
+
+/// A PartitionKey holds the values parsed from a partition's name by its dataset's partition
+/// template. When used to filter partitions, only the values which are set must match.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PartitionKey {
+    pub date: Option<NaiveDate>,
+    pub hour: Option<i16>,
+    pub shard: Option<i32>,
+}
+
+impl PartitionKey {
+    pub fn is_empty(&self) -> bool {
+        self.date.is_none() && self.hour.is_none() && self.shard.is_none()
+    }
+}
+
+/// A DataRange is the span of time covered by the data within a partition, starting at `start` and
+/// ending before `end`. This is independent of when the partition was registered, so that data
+/// which is backfilled can be found by the dates it covers.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DataRange {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl DataRange {
+    /// Reads a data range from the "data_start" and "data_end" metadata of a partition's object,
+    /// which are RFC 3339 timestamps. Neither or both must be set.
+    pub fn from_metadata(metadata: &HashMap<String, String>) -> Result<Option<Self>, Error> {
+        let timestamp = |key: &str| {
+            metadata
+                .get(key)
+                .map(|value| {
+                    DateTime::parse_from_rfc3339(value)
+                        .map(|t| t.with_timezone(&Utc))
+                        .map_err(|e| {
+                            Error::InputValidation(format!(
+                                "invalid '{}' metadata '{}': {}",
+                                key, value, e
+                            ))
+                        })
+                })
+                .transpose()
+        };
+
+        match (timestamp("data_start")?, timestamp("data_end")?) {
+            (Some(start), Some(end)) => {
+                let range = Self { start, end };
+                range.validate()?;
+                Ok(Some(range))
+            }
+            (None, None) => Ok(None),
+            _ => Err(Error::InputValidation(
+                "both 'data_start' and 'data_end' metadata must be set".into(),
+            )),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.start >= self.end {
+            return Err(Error::InputValidation(format!(
+                "data range start {} must be before its end {}",
+                self.start, self.end
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_data_range_from_metadata() {
+    let metadata = |pairs: &[(&str, &str)]| {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<HashMap<_, _>>()
+    };
+
+    let range = DataRange::from_metadata(&metadata(&[
+        ("data_start", "2020-03-25T00:00:00Z"),
+        ("data_end", "2020-03-25T18:00:00-06:00"),
+        ("owner", "growth"),
+    ]))
+    .unwrap()
+    .unwrap();
+    assert_eq!(range.start.to_rfc3339(), "2020-03-25T00:00:00+00:00");
+    assert_eq!(range.end.to_rfc3339(), "2020-03-26T00:00:00+00:00");
+
+    assert_eq!(DataRange::from_metadata(&metadata(&[])).unwrap(), None);
+    for invalid in &[
+        metadata(&[("data_start", "2020-03-25T00:00:00Z")]),
+        metadata(&[("data_end", "2020-03-25T00:00:00Z")]),
+        metadata(&[("data_start", "2020-03-25"), ("data_end", "2020-03-26")]),
+        metadata(&[
+            ("data_start", "2020-03-26T00:00:00Z"),
+            ("data_end", "2020-03-25T00:00:00Z"),
+        ]),
+    ] {
+        assert!(DataRange::from_metadata(invalid).is_err());
+    }
+}
+
+/// Purged counts the datasets and partitions permanently removed after their retention period.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Purged {
+    pub datasets: u64,
+    pub partitions: u64,
+}
+
+/// CatalogStats summarizes the catalog's datasets of a single classification, counting those which
+/// aren't deleted along with their partitions, and the total size of the partitions in bytes.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct CatalogStats {
+    pub classification: Classification,
+    pub datasets: i64,
+    pub partitions: i64,
+    pub bytes: i64,
+}
+
+impl CatalogStats {
+    /// Lists the stats of every classification which has at least one dataset.
+    pub async fn list(svc: &mut impl DataService) -> Result<Vec<CatalogStats>, Error> {
+        svc.list_catalog_stats().await
+    }
+}
+
+/// An Actor is whoever is responsible for a change to the catalog, and is recorded with every
+/// AuditEvent. Changes made outside of a request (e.g. at startup) are attributed to the System.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "actor_type", rename_all = "lowercase")]
+pub enum Actor {
+    System,
+    Manager { manager_id: i32 },
+    Pubsub { message_id: String },
+}
+
+impl From<&Manager> for Actor {
+    fn from(m: &Manager) -> Self {
+        Actor::Manager { manager_id: m.id }
+    }
+}
+
+/// An AuditEvent is an append-only record of a single catalog mutation, including snapshots of
+/// the target before and after the change (where applicable).
+#[derive(Debug, Serialize)]
+pub struct AuditEvent {
+    #[serde(rename(serialize = "event_id"))]
+    pub id: i64,
+    pub actor: Actor,
+    pub action: String,
+    pub target_type: String,
+    pub target: String,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditEvent {
+    /// Retrieves audit events matching every filter provided, most recent first.
+    pub async fn list(
+        svc: &mut impl DataService,
+        filter: &AuditFilter,
+    ) -> Result<Vec<AuditEvent>, Error> {
+        info!("listing audit events: {:?}", filter);
+        svc.list_audit_events(filter).await
+    }
+}
+
+/// An AccessDenial is a record of a partition read which was refused, because the requester was not
+/// authenticated or not allowed to read the dataset's classification.
+#[derive(Debug, Serialize)]
+pub struct AccessDenial {
+    #[serde(rename(serialize = "access_id"))]
+    pub id: i64,
+    pub manager_id: Option<i32>,
+    pub remote_addr: Option<String>,
+    pub dataset_name: String,
+    pub partition_name: Option<String>,
+    pub action: String,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AccessDenial {
+    /// Appends the denial to the access log.
+    pub async fn record(&self, svc: &mut impl DataService) -> Result<(), Error> {
+        info!(
+            "recording denied '{}' of dataset '{}': {}",
+            self.action, self.dataset_name, self.reason
+        );
+        svc.log_access_denial(self).await
+    }
+
+    /// Retrieves access denials matching every filter provided, most recent first.
+    pub async fn list(
+        svc: &mut impl DataService,
+        filter: &AccessFilter,
+    ) -> Result<Vec<AccessDenial>, Error> {
+        info!("listing access denials: {:?}", filter);
+        svc.list_access_denials(filter).await
+    }
+}
+
+/// AccessFilter narrows down the access denials returned, where each field left as `None` matches
+/// any denial.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct AccessFilter {
+    pub manager_id: Option<i32>,
+    pub dataset_name: Option<String>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub offset: Option<i32>,
+    pub count: Option<i32>,
+}
+
+/// AuditFilter narrows down the audit events returned, where each field left as `None` matches any
+/// event.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct AuditFilter {
+    pub actor_type: Option<String>,
+    pub actor_id: Option<String>,
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target: Option<String>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub offset: Option<i32>,
+    pub count: Option<i32>,
+}
+
+/// SearchParams specify which datasets are matched by a search, where each filter left as `None`
+/// matches any dataset.
+#[derive(Debug, Default, Clone)]
+pub struct SearchParams {
+    pub term: Option<String>,
+    pub classification: Option<Classification>,
+    pub format: Option<Format>,
+    pub compression: Option<Compression>,
+    pub manager_email: Option<String>,
+    pub updated_after: Option<DateTime<Utc>>,
+    pub updated_before: Option<DateTime<Utc>>,
+    pub labels: Option<DatasetLabels>,
+    pub offset: Option<i32>,
+    pub count: Option<i32>,
+}
+
+/// A SortKey is the attribute which a list of datasets or partitions is sorted by. Partitions may
+/// also be sorted by their size. A dataset is updated whenever a partition is added to it, so
+/// paging through datasets sorted by updated_at may skip or repeat those which change meanwhile.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    Name,
+    CreatedAt,
+    UpdatedAt,
+    Size,
+}
+
+/// A SortOrder is the direction which a list is sorted in. Names are sorted in ascending order by
+/// default, and every other key in descending order.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl SortKey {
+    pub fn default_order(self) -> SortOrder {
+        match self {
+            SortKey::Name => SortOrder::Asc,
+            _ => SortOrder::Desc,
+        }
+    }
+}
+
+/// A SortValue is the value of a result's SortKey, kept by a Cursor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortValue {
+    Time(DateTime<Utc>),
+    Text(String),
+    Int(i64),
+}
+
+/// A Cursor marks the last result of a page, so that the next page starts after it even if results
+/// are added or removed in the meantime. Results are ordered by their sort key, e.g. `updated_at`
+/// for datasets by default, and then by id. A cursor can only continue the sort it was created by.
+/// Clients receive it as an opaque token.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cursor {
+    pub sort: SortKey,
+    pub order: SortOrder,
+    pub value: SortValue,
+    pub id: i32,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        base64::encode_config(
+            serde_json::to_vec(self).expect("failed to serialize cursor"),
+            base64::URL_SAFE_NO_PAD,
+        )
+    }
+
+    pub fn decode(token: impl AsRef<str>) -> Result<Self, Error> {
+        base64::decode_config(token.as_ref(), base64::URL_SAFE_NO_PAD)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .ok_or_else(|| Error::InputValidation(format!("invalid cursor '{}'", token.as_ref())))
+    }
+}
+
+#[test]
+fn test_cursor() {
+    let cursors = vec![
+        Cursor {
+            sort: SortKey::UpdatedAt,
+            order: SortOrder::Desc,
+            value: SortValue::Time("2020-03-25T07:00:00.123456Z".parse().unwrap()),
+            id: 42,
+        },
+        Cursor {
+            sort: SortKey::Name,
+            order: SortOrder::Asc,
+            value: SortValue::Text("part_1".into()),
+            id: 7,
+        },
+        Cursor {
+            sort: SortKey::Size,
+            order: SortOrder::Asc,
+            value: SortValue::Int(1 << 40),
+            id: 3,
+        },
+    ];
+    for cursor in cursors {
+        let token = cursor.encode();
+        assert!(token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(Cursor::decode(&token).unwrap(), cursor);
+    }
+
+    for invalid in &["", "not a cursor", "e30"] {
+        assert!(Cursor::decode(invalid).is_err());
+    }
+}
+
+/// A DatasetPage contains a page of datasets, the total number of datasets across every page, and a
+/// token for the cursor of the next page if there is one.
+#[derive(Debug, Serialize)]
+pub struct DatasetPage {
+    pub total: i64,
+    pub datasets: Vec<Dataset>,
+    pub next: Option<String>,
+}
+
+/// A PartitionPage contains a page of partitions, the total number of partitions across every page,
+/// and a token for the cursor of the next page if there is one.
+#[derive(Debug, Serialize)]
+pub struct PartitionPage {
+    pub total: i64,
+    pub partitions: Vec<Partition>,
+    pub next: Option<String>,
+}
+
+/// SearchResults contain a page of the datasets matched by a search, along with the total number
+/// of matches and their facet counts.
+#[derive(Debug, Serialize)]
+pub struct SearchResults {
+    pub total: i64,
+    pub datasets: Vec<Dataset>,
+    pub facets: SearchFacets,
+}
+
+/// SearchFacets count every dataset matched by a search, grouped by each attribute they can be
+/// filtered on.
+#[derive(Debug, Default, Serialize)]
+pub struct SearchFacets {
+    pub classification: HashMap<String, i64>,
+    pub format: HashMap<String, i64>,
+    pub compression: HashMap<String, i64>,
+    pub manager: HashMap<String, i64>,
+}
+
+/// FieldSearch specifies which schema fields to find across datasets. The name pattern is matched
+/// case-insensitively, where `*` matches any number of characters and `?` matches a single one.
+/// Only the current schema of each dataset is searched, unless `all_versions` is set.
+#[derive(Debug, Default, Clone)]
+pub struct FieldSearch {
+    pub name: Option<String>,
+    pub field_type: Option<String>,
+    pub all_versions: bool,
+}
+
+/// A SchemaField is a single field within a version of a dataset's schema. A new schema version is
+/// recorded every time a dataset's schema changes.
+#[derive(Debug, Serialize)]
+pub struct SchemaField {
+    pub dataset_id: i32,
+    pub dataset_name: String,
+    pub schema_version: i32,
+    pub latest: bool,
+    #[serde(rename(serialize = "field_name"))]
+    pub name: String,
+    pub field_type: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A PiiField is a schema field annotated with the kind of PII it holds.
+#[derive(Debug, Serialize)]
+pub struct PiiField {
+    pub dataset_id: i32,
+    pub dataset_name: String,
+    pub classification: Classification,
+    pub field_name: String,
+    pub field_type: Option<String>,
+    pub sensitivity: Sensitivity,
+}
+
+/// Params specify how a Dataset's Partition results should be returned. The `data_start` and
+/// `data_end` params only apply to partitions, and match those whose data range overlaps them.
+/// When `sort` is not set, datasets and partitions are sorted by `created_at`.
+#[derive(Debug, Default, Clone)]
 pub struct RangeParams {
     pub start: Option<DateTime<Utc>>,
     pub end: Option<DateTime<Utc>>,
     pub offset: Option<i32>,
     pub count: Option<i32>,
+    pub data_start: Option<DateTime<Utc>>,
+    pub data_end: Option<DateTime<Utc>>,
+    pub cursor: Option<Cursor>,
+    pub sort: Option<SortKey>,
+    pub order: Option<SortOrder>,
+}
+
+impl RangeParams {
+    pub fn has_data_range(&self) -> bool {
+        self.data_start.is_some() || self.data_end.is_some()
+    }
+}
+
+/// A DatasetFilter specifies which datasets to list, where only the filters which are set must
+/// match. Datasets must have every label provided, and names are matched by their prefix.
+#[derive(Debug, Default, Clone)]
+pub struct DatasetFilter {
+    pub classification: Option<Classification>,
+    pub manager_email: Option<String>,
+    pub name_prefix: Option<String>,
+    pub labels: Option<DatasetLabels>,
+}
+
+/// A PartitionFilter specifies which of a dataset's partitions to list, where only the filters
+/// which are set must match. Names are matched by their prefix, and sizes are inclusive.
+#[derive(Debug, Default, Clone)]
+pub struct PartitionFilter {
+    pub key: PartitionKey,
+    pub name_prefix: Option<String>,
+    pub min_size: Option<i64>,
+    pub max_size: Option<i64>,
 }
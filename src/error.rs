@@ -2,30 +2,392 @@ use std::error::Error as StdErr;
 use std::fmt;
 use std::fmt::Debug;
 
+use actix_http::{Response, ResponseBuilder};
+use actix_web::{
+    http::{header::RETRY_AFTER, StatusCode},
+    ResponseError,
+};
+use serde::Serialize;
+use tokio_postgres::error::{DbError, SqlState};
+
+// clients are asked to retry requests which failed because the database was unavailable after a
+// second
+const RETRY_AFTER_SECS: u32 = 1;
+
 type PgError = tokio_postgres::error::Error;
 type PoolError<E> = bb8_postgres::bb8::RunError<E>;
 
+type Source = Box<dyn StdErr + Send + Sync>;
+
+/// Error is the error of every fallible operation in the crate. Errors which wrap another keep it
+/// as their `source()`, and any error can be given context with `Error::context`, or `Context` on
+/// a `Result`.
 #[derive(Debug)]
 pub enum Error {
-    Generic(Box<dyn StdErr>),
+    Generic(Source),
     Sql(PgError),
+    NotFound(String),
+    Conflict(String),
     InputValidation(String),
     DBConversion(String),
     Utf8(std::string::FromUtf8Error),
     Auth(String),
-    Http(String),
     Pool(String),
-    Pubsub(PubsubAction),
+    Storage {
+        message: String,
+        source: Option<Source>,
+    },
+    PubSub {
+        action: PubsubAction,
+        message: String,
+        source: Option<Source>,
+    },
+    Serialization(Source),
+    Migration(Source),
+    Config(String),
+    Context {
+        context: String,
+        source: Box<Error>,
+    },
 }
 
-#[derive(Debug)]
+/// A PubsubAction is what the pubsub runtime should do with a message whose handling failed.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PubsubAction {
+    Retry,
     IgnoreAndAck,
 }
 
+impl Error {
+    pub fn storage(message: impl Into<String>) -> Self {
+        Error::Storage {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn storage_source(message: impl Into<String>, source: impl Into<Source>) -> Self {
+        Error::Storage {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn pubsub(message: impl Into<String>) -> Self {
+        Error::PubSub {
+            action: PubsubAction::Retry,
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn pubsub_source(message: impl Into<String>, source: impl Into<Source>) -> Self {
+        Error::PubSub {
+            action: PubsubAction::Retry,
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// An error for a pubsub message which can't be handled, and shouldn't be retried.
+    pub fn ignore_and_ack(message: impl Into<String>) -> Self {
+        Error::PubSub {
+            action: PubsubAction::IgnoreAndAck,
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// Wraps the error with a description of what was being done when it occurred.
+    pub fn context(self, context: impl Into<String>) -> Self {
+        Error::Context {
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// What the pubsub runtime should do with a message whose handling failed with this error,
+    /// which is to retry it unless it was explicitly ignored.
+    pub fn pubsub_action(&self) -> PubsubAction {
+        match self {
+            Error::PubSub { action, .. } => *action,
+            Error::Context { source, .. } => source.pubsub_action(),
+            _ => PubsubAction::Retry,
+        }
+    }
+}
+
 impl fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self)
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Generic(e) => write!(f, "{}", e),
+            Error::Sql(e) => write!(f, "{}", e),
+            Error::NotFound(msg)
+            | Error::Conflict(msg)
+            | Error::InputValidation(msg)
+            | Error::Auth(msg) => write!(f, "{}", msg),
+            Error::DBConversion(msg) => write!(f, "failed to convert database value: {}", msg),
+            Error::Utf8(e) => write!(f, "invalid utf-8: {}", e),
+            Error::Pool(msg) => write!(f, "database pool error: {}", msg),
+            Error::Storage { message, source }
+            | Error::PubSub {
+                message, source, ..
+            } => match source {
+                Some(source) => write!(f, "{}: {}", message, source),
+                None => write!(f, "{}", message),
+            },
+            Error::Serialization(e) => write!(f, "serialization failed: {}", e),
+            Error::Migration(e) => write!(f, "migration failed: {}", e),
+            Error::Config(msg) => write!(f, "invalid configuration: {}", msg),
+            Error::Context { context, source } => write!(f, "{}: {}", context, source),
+        }
+    }
+}
+
+impl StdErr for Error {
+    fn source(&self) -> Option<&(dyn StdErr + 'static)> {
+        match self {
+            Error::Generic(e) | Error::Serialization(e) | Error::Migration(e) => Some(e.as_ref()),
+            Error::Storage { source, .. } | Error::PubSub { source, .. } => source
+                .as_ref()
+                .map(|e| e.as_ref() as &(dyn StdErr + 'static)),
+            Error::Sql(e) => Some(e),
+            Error::Utf8(e) => Some(e),
+            Error::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Context attaches a description of what was being done to the error of a `Result`.
+pub trait Context<T> {
+    fn context(self, context: impl Into<String>) -> Result<T, Error>;
+
+    fn with_context<F: FnOnce() -> String>(self, f: F) -> Result<T, Error>;
+}
+
+impl<T, E: Into<Error>> Context<T> for Result<T, E> {
+    fn context(self, context: impl Into<String>) -> Result<T, Error> {
+        self.map_err(|e| e.into().context(context))
+    }
+
+    fn with_context<F: FnOnce() -> String>(self, f: F) -> Result<T, Error> {
+        self.map_err(|e| e.into().context(f()))
+    }
+}
+
+/// An ErrorKind is the category of a failed request, which determines its status code and the
+/// machine-readable `error_code` of its response.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorKind {
+    NotFound,
+    Conflict,
+    Validation,
+    Unauthorized,
+    Forbidden,
+    Unavailable,
+    Internal,
+}
+
+impl ErrorKind {
+    pub fn status(self) -> StatusCode {
+        match self {
+            ErrorKind::NotFound => StatusCode::NOT_FOUND,
+            ErrorKind::Conflict => StatusCode::CONFLICT,
+            ErrorKind::Validation => StatusCode::BAD_REQUEST,
+            ErrorKind::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorKind::Forbidden => StatusCode::FORBIDDEN,
+            ErrorKind::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorKind::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn error_code(self) -> &'static str {
+        match self {
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::Conflict => "conflict",
+            ErrorKind::Validation => "validation_failed",
+            ErrorKind::Unauthorized => "unauthorized",
+            ErrorKind::Forbidden => "forbidden",
+            ErrorKind::Unavailable => "unavailable",
+            ErrorKind::Internal => "internal_error",
+        }
+    }
+
+    /// Finds the kind of error responded with a status, where any other server error is internal.
+    pub fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::NOT_FOUND => ErrorKind::NotFound,
+            StatusCode::CONFLICT => ErrorKind::Conflict,
+            StatusCode::UNAUTHORIZED => ErrorKind::Unauthorized,
+            StatusCode::FORBIDDEN => ErrorKind::Forbidden,
+            StatusCode::SERVICE_UNAVAILABLE => ErrorKind::Unavailable,
+            s if s.is_client_error() => ErrorKind::Validation,
+            _ => ErrorKind::Internal,
+        }
+    }
+}
+
+impl Error {
+    /// Classifies the error, e.g. a SQL error is a conflict when it violates a unique constraint,
+    /// and unavailable when the connection to the server was lost.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Sql(e) => match e.code() {
+                Some(code) if *code == SqlState::UNIQUE_VIOLATION => ErrorKind::Conflict,
+                Some(code) if *code == SqlState::EXCLUSION_VIOLATION => ErrorKind::Conflict,
+                Some(code)
+                    if *code == SqlState::FOREIGN_KEY_VIOLATION
+                        || *code == SqlState::CHECK_VIOLATION
+                        || *code == SqlState::NOT_NULL_VIOLATION
+                        || *code == SqlState::STRING_DATA_RIGHT_TRUNCATION
+                        || *code == SqlState::INVALID_TEXT_REPRESENTATION =>
+                {
+                    ErrorKind::Validation
+                }
+                Some(code) if is_transient_state(code) => ErrorKind::Unavailable,
+                Some(_) => ErrorKind::Internal,
+                None if is_connection(e) => ErrorKind::Unavailable,
+                None => ErrorKind::Internal,
+            },
+            Error::Pool(_) => ErrorKind::Unavailable,
+            Error::NotFound(_) => ErrorKind::NotFound,
+            Error::Conflict(_) => ErrorKind::Conflict,
+            Error::InputValidation(_) => ErrorKind::Validation,
+            Error::Auth(_) => ErrorKind::Unauthorized,
+            Error::Context { source, .. } => source.kind(),
+            _ => ErrorKind::Internal,
+        }
+    }
+
+    /// Whether the operation which failed with the error may succeed if it's retried. Only errors
+    /// which are known to be permanent, i.e. the operation was rejected, aren't retryable, so that
+    /// an error which hasn't been classified is never mistaken for one.
+    pub fn is_retryable(&self) -> bool {
+        !matches!(
+            self.kind(),
+            ErrorKind::NotFound | ErrorKind::Conflict | ErrorKind::Validation
+        )
+    }
+
+    /// The message of an error which can be shown to clients, which internal errors don't have.
+    pub fn public_message(&self) -> Option<String> {
+        match self {
+            Error::NotFound(msg)
+            | Error::Conflict(msg)
+            | Error::InputValidation(msg)
+            | Error::Auth(msg) => Some(msg.clone()),
+            Error::Sql(e) if self.kind() == ErrorKind::Validation => {
+                db_error(e).map(|db| db.message().to_string())
+            }
+            Error::Context { source, .. } => source.public_message(),
+            _ => None,
+        }
+    }
+
+    /// Details which help a client handle the error, without exposing any internals.
+    pub fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            Error::Sql(e) => db_error(e).map(|db| {
+                serde_json::json!({
+                    "sql_state": db.code().code(),
+                    "constraint": db.constraint(),
+                })
+            }),
+            Error::Context { source, .. } => source.details(),
+            _ => None,
+        }
+    }
+}
+
+fn db_error(e: &PgError) -> Option<&DbError> {
+    e.source()
+        .and_then(|source| source.downcast_ref::<DbError>())
+}
+
+// reaching or talking to the server failed. tokio-postgres 0.5 doesn't tell whether an error is
+// caused by the connection having been closed, so those are internal, which is still retryable.
+fn is_connection(e: &PgError) -> bool {
+    matches!(e.source(), Some(source) if source.is::<std::io::Error>())
+}
+
+// connection exceptions (class 08), transactions rolled back by e.g. a deadlock (class 40), the
+// server running out of connections, memory, or disk (class 53), a lock which couldn't be taken,
+// a statement which was cancelled or timed out, and the server shutting down or being unable to
+// accept connections (class 57P)
+fn is_transient_state(code: &SqlState) -> bool {
+    let code = code.code();
+    code.starts_with("08")
+        || code.starts_with("40")
+        || code.starts_with("53")
+        || code == SqlState::LOCK_NOT_AVAILABLE.code()
+        || code == SqlState::QUERY_CANCELED.code()
+        || code.starts_with("57P")
+}
+
+/// An ErrorBody is the response to every failed API request. The `request_id` is set by the
+/// `RequestIds` middleware, which stores the body in the response's extensions to fill it in.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorBody {
+    pub code: u16,
+    pub status: &'static str,
+    pub error_code: &'static str,
+    pub message: String,
+    pub details: Option<serde_json::Value>,
+    pub request_id: Option<String>,
+}
+
+impl ErrorBody {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        let status = kind.status();
+        Self {
+            code: status.as_u16(),
+            status: status.canonical_reason().unwrap_or_default(),
+            error_code: kind.error_code(),
+            message: message.into(),
+            details: None,
+            request_id: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: Option<serde_json::Value>) -> Self {
+        self.details = details;
+        self
+    }
+
+    /// Builds a response with the body's status, keeping the body in its extensions.
+    pub fn respond(self, mut builder: ResponseBuilder) -> Response {
+        builder
+            .status(StatusCode::from_u16(self.code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR));
+        if self.code == StatusCode::SERVICE_UNAVAILABLE.as_u16() {
+            builder.header(RETRY_AFTER, RETRY_AFTER_SECS.to_string());
+        }
+        builder.extensions_mut().insert(self.clone());
+        builder.json(self)
+    }
+}
+
+impl ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        self.kind().status()
+    }
+
+    fn error_response(&self) -> Response {
+        let kind = self.kind();
+        // internal errors are logged, rather than described to clients
+        let message = self.public_message().unwrap_or_else(|| match kind {
+            ErrorKind::NotFound => "not found".into(),
+            ErrorKind::Conflict => "conflicts with an existing record".into(),
+            ErrorKind::Validation => "invalid input".into(),
+            ErrorKind::Unavailable => "temporarily unavailable, please retry".into(),
+            _ => {
+                log::error!("internal error: {}", self);
+                "internal error".into()
+            }
+        });
+        ErrorBody::new(kind, message)
+            .with_details(self.details())
+            .respond(Response::build(kind.status()))
     }
 }
 
@@ -58,3 +420,91 @@ impl From<std::io::Error> for Error {
         Error::Generic(Box::new(e))
     }
 }
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Serialization(Box::new(e))
+    }
+}
+
+impl From<base64::DecodeError> for Error {
+    fn from(e: base64::DecodeError) -> Self {
+        Error::Serialization(Box::new(e))
+    }
+}
+
+impl From<refinery::Error> for Error {
+    fn from(e: refinery::Error) -> Self {
+        Error::Migration(Box::new(e))
+    }
+}
+
+#[test]
+fn test_error_kind() {
+    let err = Error::InputValidation("bad".into());
+    assert_eq!(err.kind(), ErrorKind::Validation);
+    assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+    assert_eq!(Error::Auth("no".into()).kind().error_code(), "unauthorized");
+    assert_eq!(Error::NotFound("no".into()).kind(), ErrorKind::NotFound);
+    assert!(Error::Pool("timed out".into()).is_retryable());
+    assert!(Error::Generic("unclassified".into()).is_retryable());
+    assert!(!Error::NotFound("no".into()).is_retryable());
+    assert!(!Error::Conflict("no".into()).is_retryable());
+    assert!(!Error::InputValidation("no".into()).is_retryable());
+
+    for code in &[
+        SqlState::TOO_MANY_CONNECTIONS,
+        SqlState::DISK_FULL,
+        SqlState::LOCK_NOT_AVAILABLE,
+        SqlState::QUERY_CANCELED,
+        SqlState::T_R_DEADLOCK_DETECTED,
+    ] {
+        assert!(is_transient_state(code));
+    }
+    assert!(!is_transient_state(&SqlState::UNIQUE_VIOLATION));
+
+    for kind in &[
+        ErrorKind::NotFound,
+        ErrorKind::Conflict,
+        ErrorKind::Validation,
+        ErrorKind::Unauthorized,
+        ErrorKind::Forbidden,
+        ErrorKind::Unavailable,
+        ErrorKind::Internal,
+    ] {
+        assert_eq!(ErrorKind::from_status(kind.status()), *kind);
+    }
+}
+
+#[test]
+fn test_error_context() {
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+    assert_send_sync::<Error>();
+
+    let err: Result<(), Error> = Err(Error::NotFound("no dataset found with name 'a'".into()));
+    let err = err.context("failed to register partition").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "failed to register partition: no dataset found with name 'a'"
+    );
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert_eq!(
+        err.public_message().as_deref(),
+        Some("no dataset found with name 'a'")
+    );
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        "no dataset found with name 'a'"
+    );
+
+    let err = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+    let err = Error::from(err);
+    assert!(matches!(err, Error::Serialization(_)));
+    assert!(err.source().is_some());
+    assert_eq!(err.kind(), ErrorKind::Internal);
+    assert_eq!(err.public_message(), None);
+
+    assert_eq!(err.pubsub_action(), PubsubAction::Retry);
+    let err = Error::ignore_and_ack("object 'a/dd.json' is a dataset").context("handling event");
+    assert_eq!(err.pubsub_action(), PubsubAction::IgnoreAndAck);
+}
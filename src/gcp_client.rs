@@ -1,6 +1,8 @@
+use std::path::Path;
+
 use crate::error::Error;
 
-use gouth::Token;
+use gouth::{Builder, Token};
 use reqwest::{
     header::{HeaderMap, AUTHORIZATION},
     IntoUrl, Method, RequestBuilder,
@@ -12,6 +14,21 @@ pub struct GcpClient {
 }
 
 impl GcpClient {
+    /// Creates a client which authenticates with the service account key at `credentials`, or else
+    /// with the environment's default credentials.
+    pub fn new(credentials: Option<&Path>) -> Result<Self, Error> {
+        let token = match credentials {
+            Some(path) => Builder::new().file(path).build(),
+            None => Token::new(),
+        }
+        .map_err(|e| Error::Generic(Box::new(e)))?;
+
+        Ok(GcpClient {
+            client: reqwest::Client::new(),
+            token,
+        })
+    }
+
     pub fn request(&self, method: Method, url: impl IntoUrl) -> Result<RequestBuilder, Error> {
         let mut headers = HeaderMap::new();
         headers.insert(
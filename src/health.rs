@@ -0,0 +1,173 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time;
+
+use crate::bucket::BucketManager;
+use crate::db::{Db, LATEST_MIGRATION};
+use crate::error::Error;
+
+use actix_rt::time::timeout;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Serialize;
+
+/// The pubsub runtime is considered stalled when it hasn't pulled messages for this long.
+pub const PUBSUB_STALE_AFTER_SECS: i64 = 60;
+
+// each dependency must respond within this long to be considered ready
+const CHECK_TIMEOUT_SECS: u64 = 5;
+
+/// A Heartbeat is beaten by a background runtime each time it makes progress, so that other threads
+/// can tell whether it's still running. Clones share the same heartbeat.
+#[derive(Debug, Clone, Default)]
+pub struct Heartbeat(Arc<AtomicI64>);
+
+impl Heartbeat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn beat(&self) {
+        self.0
+            .store(Utc::now().timestamp_millis(), Ordering::SeqCst);
+    }
+
+    /// Returns when the heartbeat was last beaten, if it ever was.
+    pub fn last(&self) -> Option<DateTime<Utc>> {
+        match self.0.load(Ordering::SeqCst) {
+            0 => None,
+            millis => Some(Utc.timestamp_millis(millis)),
+        }
+    }
+}
+
+/// The result of checking a single dependency of the server. Failures are described without their
+/// underlying error, which is logged instead, since readiness isn't authenticated.
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Readiness is whether the server can serve requests, which requires every check to pass.
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub checks: Vec<Check>,
+}
+
+/// Checks the database pool, that every migration has been applied, that the storage buckets can
+/// be reached and that the pubsub runtime has pulled messages recently, unless it isn't running.
+/// The checks run concurrently, and each fails if it doesn't complete within a few seconds.
+pub async fn readiness(
+    db: &Db,
+    bucket_manager: &BucketManager,
+    pubsub: Option<&Heartbeat>,
+) -> Readiness {
+    let migrated = async {
+        match db.migration_version().await? {
+            Some(version) if version >= LATEST_MIGRATION => Ok(()),
+            version => Err(Error::Generic(
+                format!(
+                    "database is at migration {:?}, expected {}",
+                    version, LATEST_MIGRATION
+                )
+                .into(),
+            )),
+        }
+    };
+    let (database, migrations, storage) = tokio::join!(
+        check("database", "failed to query the database", db.ping()),
+        check(
+            "migrations",
+            "database migrations are not applied",
+            migrated
+        ),
+        check(
+            "storage",
+            "failed to reach the storage buckets",
+            bucket_manager.check_buckets()
+        ),
+    );
+    let mut checks = vec![database, migrations, storage];
+    if let Some(pubsub) = pubsub {
+        checks.push(check_pubsub(pubsub, Utc::now()));
+    }
+
+    Readiness {
+        ready: checks.iter().all(|check| check.ok),
+        checks,
+    }
+}
+
+async fn check(
+    name: &'static str,
+    failure: &str,
+    fut: impl Future<Output = Result<(), Error>>,
+) -> Check {
+    let result = match timeout(time::Duration::from_secs(CHECK_TIMEOUT_SECS), fut).await {
+        Ok(result) => result,
+        Err(_) => Err(Error::Generic(
+            format!("timed out after {} seconds", CHECK_TIMEOUT_SECS).into(),
+        )),
+    };
+
+    match result {
+        Ok(_) => Check {
+            name,
+            ok: true,
+            message: None,
+        },
+        Err(e) => {
+            log::error!("readiness check '{}' failed: {}", name, e);
+            Check {
+                name,
+                ok: false,
+                message: Some(failure.into()),
+            }
+        }
+    }
+}
+
+fn check_pubsub(pubsub: &Heartbeat, now: DateTime<Utc>) -> Check {
+    let message = match pubsub.last() {
+        None => Some("pubsub has not pulled any messages".to_string()),
+        Some(last) if (now - last).num_seconds() > PUBSUB_STALE_AFTER_SECS => Some(format!(
+            "pubsub last pulled messages {} seconds ago",
+            (now - last).num_seconds()
+        )),
+        Some(_) => None,
+    };
+    if let Some(message) = &message {
+        log::error!("readiness check 'pubsub' failed: {}", message);
+    }
+
+    Check {
+        name: "pubsub",
+        ok: message.is_none(),
+        message,
+    }
+}
+
+#[test]
+fn test_check_pubsub() {
+    let heartbeat = Heartbeat::new();
+    let now = Utc::now();
+    assert!(!check_pubsub(&heartbeat, now).ok);
+
+    heartbeat.beat();
+    assert!(check_pubsub(&heartbeat, Utc::now()).ok);
+
+    // a runtime which stopped beating is stale
+    let later = Utc::now() + chrono::Duration::seconds(PUBSUB_STALE_AFTER_SECS + 1);
+    let check = check_pubsub(&heartbeat, later);
+    assert!(!check.ok);
+    assert!(check.message.unwrap().contains("seconds ago"));
+
+    // clones share the same heartbeat
+    let clone = heartbeat.clone();
+    clone.beat();
+    assert_eq!(heartbeat.last(), clone.last());
+}
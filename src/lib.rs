@@ -1,10 +1,17 @@
 pub mod api;
 pub mod bucket;
+pub mod config;
 pub mod db;
 pub mod dict;
 pub mod error;
 pub mod gcp_client;
+pub mod health;
+pub mod metrics;
+pub mod partition_template;
 pub mod pubsub;
 pub mod pubsub_rt;
+pub mod purge_rt;
 pub mod service;
+pub mod signed_url;
+pub mod telemetry;
 pub mod util;
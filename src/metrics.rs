@@ -0,0 +1,152 @@
+use crate::db::Db;
+use crate::dict::CatalogStats;
+use crate::error::Error;
+use crate::pubsub::Event;
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use prometheus::{
+    register_gauge, register_histogram_vec, register_int_counter_vec, register_int_gauge,
+    register_int_gauge_vec, Encoder, Gauge, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec,
+    TextEncoder,
+};
+
+lazy_static! {
+    static ref HTTP_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "dd_http_request_duration_seconds",
+        "Time taken to respond to HTTP requests, by method, route and status.",
+        &["method", "route", "status"]
+    )
+    .expect("failed to register http request metric");
+    static ref PUBSUB_MESSAGES: IntCounterVec = register_int_counter_vec!(
+        "dd_pubsub_messages_total",
+        "Pub/Sub messages handled, by event and outcome (ack, ignore or retry).",
+        &["event", "outcome"]
+    )
+    .expect("failed to register pubsub message metric");
+    static ref PUBSUB_OLDEST_MESSAGE_AGE: Gauge = register_gauge!(
+        "dd_pubsub_oldest_message_age_seconds",
+        "Age of the oldest message in the last pull from Pub/Sub, or 0 if none were pulled."
+    )
+    .expect("failed to register pubsub message age metric");
+    static ref DB_POOL_CONNECTIONS: IntGauge = register_int_gauge!(
+        "dd_db_pool_connections",
+        "Connections currently held by the database pool."
+    )
+    .expect("failed to register database pool metric");
+    static ref DB_POOL_IDLE_CONNECTIONS: IntGauge = register_int_gauge!(
+        "dd_db_pool_idle_connections",
+        "Connections of the database pool which are idle."
+    )
+    .expect("failed to register database pool metric");
+    static ref CATALOG_DATASETS: IntGaugeVec = register_int_gauge_vec!(
+        "dd_catalog_datasets",
+        "Datasets in the catalog, by classification.",
+        &["classification"]
+    )
+    .expect("failed to register catalog metric");
+    static ref CATALOG_PARTITIONS: IntGaugeVec = register_int_gauge_vec!(
+        "dd_catalog_partitions",
+        "Partitions in the catalog, by the classification of their dataset.",
+        &["classification"]
+    )
+    .expect("failed to register catalog metric");
+    static ref CATALOG_BYTES: IntGaugeVec = register_int_gauge_vec!(
+        "dd_catalog_bytes",
+        "Total size of the catalog's partitions in bytes, by the classification of their dataset.",
+        &["classification"]
+    )
+    .expect("failed to register catalog metric");
+}
+
+/// The outcome of handling a Pub/Sub message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Ack,
+    Ignore,
+    Retry,
+}
+
+impl Outcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Ack => "ack",
+            Outcome::Ignore => "ignore",
+            Outcome::Retry => "retry",
+        }
+    }
+}
+
+/// Records how long it took to respond to a request. The route is its pattern rather than its path,
+/// e.g. "/dataset/{dataset_name}", so that the number of series doesn't grow with the catalog.
+pub fn observe_request(method: &str, route: &str, status: u16, seconds: f64) {
+    HTTP_REQUEST_DURATION
+        .with_label_values(&[method, route, &status.to_string()])
+        .observe(seconds);
+}
+
+/// Counts a Pub/Sub message of the event provided which was handled with the outcome provided.
+pub fn observe_message(event: &Event, outcome: Outcome) {
+    PUBSUB_MESSAGES
+        .with_label_values(&[event.as_str(), outcome.as_str()])
+        .inc();
+}
+
+/// Records the age of the oldest message pulled from Pub/Sub, given the publish time of every
+/// message pulled. The lag is 0 when no messages were pulled.
+pub fn observe_pull<'a>(published: impl Iterator<Item = &'a DateTime<Utc>>, now: DateTime<Utc>) {
+    let age = published
+        .map(|time| (now - *time).num_milliseconds().max(0))
+        .max()
+        .unwrap_or(0);
+    PUBSUB_OLDEST_MESSAGE_AGE.set(age as f64 / 1000.0);
+}
+
+/// Records the state of the database pool and the size of the catalog, followed by encoding every
+/// metric in the Prometheus text format. A catalog which can't be counted leaves its gauges at their
+/// last values, so that the remaining metrics are still reported.
+pub async fn gather(db: &Db) -> Result<(String, Vec<u8>), Error> {
+    let state = db.client.state();
+    DB_POOL_CONNECTIONS.set(state.connections.into());
+    DB_POOL_IDLE_CONNECTIONS.set(state.idle_connections.into());
+
+    match CatalogStats::list(&mut db.clone()).await {
+        Ok(stats) => {
+            // classifications without any datasets are no longer reported
+            CATALOG_DATASETS.reset();
+            CATALOG_PARTITIONS.reset();
+            CATALOG_BYTES.reset();
+            for stats in stats {
+                let classification = stats.classification.to_string();
+                let labels = &[classification.as_str()];
+                CATALOG_DATASETS
+                    .with_label_values(labels)
+                    .set(stats.datasets);
+                CATALOG_PARTITIONS
+                    .with_label_values(labels)
+                    .set(stats.partitions);
+                CATALOG_BYTES.with_label_values(labels).set(stats.bytes);
+            }
+        }
+        Err(e) => log::error!("failed to count the catalog for metrics: {}", e),
+    }
+
+    let encoder = TextEncoder::new();
+    let mut buf = vec![];
+    encoder
+        .encode(&prometheus::gather(), &mut buf)
+        .map_err(|e| Error::Generic(Box::new(e)))?;
+
+    Ok((encoder.format_type().to_string(), buf))
+}
+
+#[test]
+fn test_observe_pull() {
+    let now = Utc::now();
+    let published = [now - chrono::Duration::seconds(90), now];
+    observe_pull(published.iter(), now);
+    assert_eq!(PUBSUB_OLDEST_MESSAGE_AGE.get(), 90.0);
+
+    observe_pull([].iter(), now);
+    assert_eq!(PUBSUB_OLDEST_MESSAGE_AGE.get(), 0.0);
+}
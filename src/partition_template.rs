@@ -0,0 +1,405 @@
+use crate::dict::{DataRange, PartitionKey};
+use crate::error::Error;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+
+/// The placeholders a partition template may contain, each matching one part of a partition name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Year,
+    Month,
+    Day,
+    Hour,
+    Shard,
+    Name,
+    Ext,
+}
+
+impl Field {
+    fn from_placeholder(placeholder: &str) -> Option<Self> {
+        match placeholder {
+            "yyyy" => Some(Field::Year),
+            "mm" => Some(Field::Month),
+            "dd" => Some(Field::Day),
+            "hh" => Some(Field::Hour),
+            "shard" => Some(Field::Shard),
+            "name" => Some(Field::Name),
+            "ext" => Some(Field::Ext),
+            _ => None,
+        }
+    }
+
+    // the fixed number of digits matched by a date or time placeholder
+    fn digits(self) -> Option<usize> {
+        match self {
+            Field::Year => Some(4),
+            Field::Month | Field::Day | Field::Hour => Some(2),
+            _ => None,
+        }
+    }
+
+    // shard and name placeholders match any number of characters, so they can't follow each other
+    fn is_variable(self) -> bool {
+        self == Field::Shard || self == Field::Name
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Literal(String),
+    Field(Field),
+}
+
+/// A PartitionTemplate describes the names of a dataset's partitions, e.g.
+/// "{yyyy}/{mm}/{dd}/{name}.{ext}". Supported placeholders are {yyyy}, {mm}, {dd}, {hh}, {shard},
+/// {name}, and {ext}, which is the extension of the dataset's format and compression.
+#[derive(Debug)]
+pub struct PartitionTemplate {
+    template: String,
+    segments: Vec<Segment>,
+}
+
+impl PartitionTemplate {
+    /// Parses and validates a partition template.
+    pub fn parse(template: &str) -> Result<Self, Error> {
+        let invalid = |reason: &str| {
+            Error::InputValidation(format!(
+                "invalid partition template '{}': {}",
+                template, reason
+            ))
+        };
+        if template.trim().is_empty() {
+            return Err(invalid("template is empty"));
+        }
+        if template.starts_with('/') || template.ends_with('/') || template.contains("//") {
+            return Err(invalid("template must not contain empty path segments"));
+        }
+
+        let mut segments = vec![];
+        let mut rest = template;
+        while !rest.is_empty() {
+            if let Some(open) = rest.find('{') {
+                let close = match rest[open..].find('}') {
+                    Some(close) => open + close,
+                    None => return Err(invalid("placeholder is missing a closing '}'")),
+                };
+                if open > 0 {
+                    segments.push(Segment::Literal(rest[..open].into()));
+                }
+                let placeholder = &rest[open + 1..close];
+                match Field::from_placeholder(placeholder) {
+                    Some(field) => segments.push(Segment::Field(field)),
+                    None => {
+                        return Err(invalid(&format!("unknown placeholder {{{}}}", placeholder)))
+                    }
+                }
+                rest = &rest[close + 1..];
+            } else {
+                if rest.contains('}') {
+                    return Err(invalid("placeholder is missing an opening '{'"));
+                }
+                segments.push(Segment::Literal(rest.into()));
+                rest = "";
+            }
+        }
+
+        let fields = segments
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Field(field) => Some(*field),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for (i, field) in fields.iter().enumerate() {
+            if fields[..i].contains(field) {
+                return Err(invalid("placeholders may only be used once"));
+            }
+        }
+        let requires = &[
+            (Field::Month, Field::Year, "{mm} requires {yyyy}"),
+            (Field::Day, Field::Month, "{dd} requires {mm}"),
+            (Field::Hour, Field::Day, "{hh} requires {dd}"),
+        ];
+        for (field, required, reason) in requires {
+            if fields.contains(field) && !fields.contains(required) {
+                return Err(invalid(reason));
+            }
+        }
+        for pair in segments.windows(2) {
+            if let (Segment::Field(a), Segment::Field(b)) = (&pair[0], &pair[1]) {
+                if a.is_variable() && b.is_variable() {
+                    return Err(invalid("{shard} and {name} must be separated"));
+                }
+            }
+        }
+
+        Ok(Self {
+            template: template.into(),
+            segments,
+        })
+    }
+
+    /// Matches a partition name against the template, returning the key parsed from its name. The
+    /// `ext` provided is the extension matched by an {ext} placeholder.
+    pub fn key(&self, partition_name: &str, ext: &str) -> Result<PartitionKey, Error> {
+        let mismatch = |reason: &str| {
+            Error::InputValidation(format!(
+                "partition name '{}' does not match template '{}'{}",
+                partition_name, self.template, reason
+            ))
+        };
+
+        let mut captures = vec![];
+        if !match_segments(&self.segments, partition_name, ext, &mut captures) {
+            return Err(mismatch(""));
+        }
+
+        let capture = |field| {
+            captures
+                .iter()
+                .find(|(f, _)| *f == field)
+                .map(|(_, value)| *value)
+        };
+        // every captured date, time, and shard value consists of digits only
+        let number = |field| capture(field).and_then(|value: &str| value.parse::<u32>().ok());
+
+        let mut key = PartitionKey::default();
+        if let Some(year) = number(Field::Year) {
+            let month = number(Field::Month).unwrap_or(1);
+            let day = number(Field::Day).unwrap_or(1);
+            match NaiveDate::from_ymd_opt(year as i32, month, day) {
+                Some(date) => key.date = Some(date),
+                None => return Err(mismatch(", its date is invalid")),
+            }
+        }
+        if let Some(hour) = number(Field::Hour) {
+            if hour > 23 {
+                return Err(mismatch(", its hour is invalid"));
+            }
+            key.hour = Some(hour as i16);
+        }
+        if let Some(shard) = capture(Field::Shard) {
+            match shard.parse::<i32>() {
+                Ok(shard) => key.shard = Some(shard),
+                Err(_) => return Err(mismatch(", its shard is too large")),
+            }
+        }
+
+        Ok(key)
+    }
+
+    /// Returns the span of time covered by a partition with the key provided, based on the most
+    /// precise date or time placeholder in the template, e.g. one day for "{yyyy}/{mm}/{dd}/{name}".
+    pub fn data_range(&self, key: &PartitionKey) -> Option<DataRange> {
+        let date = key.date?;
+        let has = |field| self.segments.contains(&Segment::Field(field));
+        let start =
+            DateTime::<Utc>::from_utc(date.and_hms(key.hour.unwrap_or(0) as u32, 0, 0), Utc);
+        let end = if has(Field::Hour) {
+            start + Duration::hours(1)
+        } else if has(Field::Day) {
+            start + Duration::days(1)
+        } else if has(Field::Month) {
+            let (year, month) = match date.month() {
+                12 => (date.year() + 1, 1),
+                month => (date.year(), month + 1),
+            };
+            DateTime::from_utc(NaiveDate::from_ymd(year, month, 1).and_hms(0, 0, 0), Utc)
+        } else {
+            DateTime::from_utc(
+                NaiveDate::from_ymd(date.year() + 1, 1, 1).and_hms(0, 0, 0),
+                Utc,
+            )
+        };
+
+        Some(DataRange { start, end })
+    }
+}
+
+// Matches the input against each segment in turn, backtracking over the possible lengths of shard
+// and name placeholders until the remaining segments match.
+fn match_segments<'a>(
+    segments: &[Segment],
+    input: &'a str,
+    ext: &str,
+    captures: &mut Vec<(Field, &'a str)>,
+) -> bool {
+    let (segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => return input.is_empty(),
+    };
+
+    match segment {
+        Segment::Literal(literal) => {
+            input.starts_with(literal.as_str())
+                && match_segments(rest, &input[literal.len()..], ext, captures)
+        }
+        Segment::Field(Field::Ext) => {
+            !ext.is_empty()
+                && input.starts_with(ext)
+                && match_segments(rest, &input[ext.len()..], ext, captures)
+        }
+        Segment::Field(field) => {
+            let candidates = match field.digits() {
+                Some(digits) => {
+                    let len = input.bytes().take_while(u8::is_ascii_digit).count();
+                    if len < digits {
+                        return false;
+                    }
+                    vec![digits]
+                }
+                None => {
+                    let len = if *field == Field::Shard {
+                        input.bytes().take_while(u8::is_ascii_digit).count()
+                    } else {
+                        input.find('/').unwrap_or(input.len())
+                    };
+                    // only split the input on character boundaries, trying the longest match first
+                    (1..=len)
+                        .rev()
+                        .filter(|i| input.is_char_boundary(*i))
+                        .collect()
+                }
+            };
+
+            for len in candidates {
+                captures.push((*field, &input[..len]));
+                if match_segments(rest, &input[len..], ext, captures) {
+                    return true;
+                }
+                captures.pop();
+            }
+            false
+        }
+    }
+}
+
+#[test]
+fn test_partition_template_parse() {
+    let valid = &[
+        "{yyyy}/{mm}/{dd}/{name}.{ext}",
+        "{yyyy}/{mm}/{dd}/{hh}/part-{shard}.{ext}",
+        "{yyyy}-{mm}/{shard}-{name}",
+        "snapshots/{name}",
+    ];
+    for template in valid {
+        assert!(PartitionTemplate::parse(template).is_ok(), "{}", template);
+    }
+
+    let invalid = &[
+        "",
+        "/{yyyy}/{name}",
+        "{yyyy}//{name}",
+        "{yyyy}/{name",
+        "{yyyy}/name}",
+        "{yyyy}/{week}",
+        "{yyyy}/{yyyy}",
+        "{mm}/{dd}/{name}",
+        "{yyyy}/{dd}/{name}",
+        "{yyyy}/{mm}/{hh}",
+        "{yyyy}/{shard}{name}",
+    ];
+    for template in invalid {
+        assert!(PartitionTemplate::parse(template).is_err(), "{}", template);
+    }
+}
+
+#[test]
+fn test_partition_template_key() {
+    let date = |y, m, d| Some(NaiveDate::from_ymd(y, m, d));
+    let template = PartitionTemplate::parse("{yyyy}/{mm}/{dd}/{hh}/part-{shard}.{ext}").unwrap();
+    assert_eq!(
+        template
+            .key("2020/03/25/07/part-0012.json.tar.gz", "json.tar.gz")
+            .unwrap(),
+        PartitionKey {
+            date: date(2020, 3, 25),
+            hour: Some(7),
+            shard: Some(12),
+        }
+    );
+
+    let template = PartitionTemplate::parse("{yyyy}/{mm}/{dd}/{name}.{ext}").unwrap();
+    assert_eq!(
+        template
+            .key("2020/03/25/some.partition.csv", "csv")
+            .unwrap(),
+        PartitionKey {
+            date: date(2020, 3, 25),
+            hour: None,
+            shard: None,
+        }
+    );
+
+    let template = PartitionTemplate::parse("{yyyy}-{mm}/{name}").unwrap();
+    assert_eq!(
+        template.key("2020-06/all", "csv").unwrap().date,
+        date(2020, 6, 1)
+    );
+
+    let template = PartitionTemplate::parse("{yyyy}/{mm}/{dd}/{hh}/{name}.{ext}").unwrap();
+    let mismatched = &[
+        "2020/03/25/07/part.json",
+        "2020/03/25/part.csv",
+        "2020/3/25/07/part.csv",
+        "20200/03/25/07/part.csv",
+        "2020/03/25/07/nested/part.csv",
+        "2020/03/25/07/.csv",
+        "2020/02/30/07/part.csv",
+        "2020/13/01/07/part.csv",
+        "2020/03/25/24/part.csv",
+    ];
+    for name in mismatched {
+        assert!(template.key(name, "csv").is_err(), "{}", name);
+    }
+
+    let template = PartitionTemplate::parse("part-{shard}").unwrap();
+    assert!(template.key("part-99999999999", "csv").is_err());
+    assert!(template.key("part-", "csv").is_err());
+}
+
+#[test]
+fn test_partition_template_data_range() {
+    let at = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+    let tests = &[
+        (
+            "{yyyy}/{mm}/{dd}/{hh}/{name}",
+            "2020/03/25/07/part",
+            "2020-03-25T07:00:00Z",
+            "2020-03-25T08:00:00Z",
+        ),
+        (
+            "{yyyy}/{mm}/{dd}/{name}",
+            "2020/02/29/part",
+            "2020-02-29T00:00:00Z",
+            "2020-03-01T00:00:00Z",
+        ),
+        (
+            "{yyyy}/{mm}/{name}",
+            "2020/12/part",
+            "2020-12-01T00:00:00Z",
+            "2021-01-01T00:00:00Z",
+        ),
+        (
+            "{yyyy}/{name}",
+            "2020/part",
+            "2020-01-01T00:00:00Z",
+            "2021-01-01T00:00:00Z",
+        ),
+    ];
+    for (template, name, start, end) in tests {
+        let template = PartitionTemplate::parse(template).unwrap();
+        let key = template.key(name, "csv").unwrap();
+        assert_eq!(
+            template.data_range(&key),
+            Some(DataRange {
+                start: at(start),
+                end: at(end),
+            })
+        );
+    }
+
+    let template = PartitionTemplate::parse("part-{shard}").unwrap();
+    let key = template.key("part-1", "csv").unwrap();
+    assert_eq!(template.data_range(&key), None);
+}
@@ -1,7 +1,7 @@
 use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::env;
 
+use crate::config::Config;
 use crate::error::Error;
 use crate::gcp_client::GcpClient;
 
@@ -17,13 +17,6 @@ struct SubscriptionCreatePayload {
     // https://cloud.google.com/pubsub/docs/reference/rest/v1/projects.subscriptions/create#request-body
 }
 
-fn max_messages_from_env() -> Result<usize, Error> {
-    match env::var("DD_TOPIC_MAX_MESSAGES") {
-        Ok(v) => v.parse().map_err(|e| Error::Generic(Box::new(e))),
-        Err(e) => Err(Error::Generic(Box::new(e))),
-    }
-}
-
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Attributes {
@@ -92,24 +85,16 @@ pub struct Subscriber<'a> {
 }
 
 impl<'a> Subscriber<'a> {
-    /// Creates a pub/sub subscription from the available environment variables. Requires each of
-    /// DD_GCP_PROJECT_ID, DD_TOPIC_NAME, DD_SUBSCRIPTION_NAME, PUBSUB_SERVICE to be set.
-    pub async fn from_env(client: &'a GcpClient) -> Result<Subscriber<'a>, Error> {
-        // TODO: clean this up, it has become disgusting...
-        let sub = Subscriber {
-            name: env::var("DD_SUBSCRIPTION_NAME")
-                .expect("DD_SUBSCRIPTION_NAME environment variable not set"),
-            project_id: env::var("DD_GCP_PROJECT_ID")
-                .expect("DD_GCP_PROJECT_ID environment variable not set"),
-            topic: env::var("DD_TOPIC_NAME").expect("DD_TOPIC_NAME environment variable not set"),
-            service_endpoint: env::var("DD_PUBSUB_SERVICE")
-                .expect("DD_PUBSUB_SERVICE environment variable not set"),
-
-            max_messages: max_messages_from_env()?,
+    /// Creates a subscriber of the subscription, topic and project in the configuration provided.
+    pub fn new(config: &Config, client: &'a GcpClient) -> Subscriber<'a> {
+        Subscriber {
+            name: config.pubsub.subscription.clone(),
+            project_id: config.gcp.project_id.clone(),
+            topic: config.pubsub.topic.clone(),
+            service_endpoint: config.pubsub.service.clone(),
+            max_messages: config.pubsub.max_messages,
             client,
-        };
-
-        Ok(sub)
+        }
     }
 
     /// Creates a subscription using the "pull" method.
@@ -124,17 +109,15 @@ impl<'a> Subscriber<'a> {
             .json(&sub_payload)
             .send()
             .await
-            .map_err(|e| {
-                Error::Http(format!("failed to make subscription create request: {}", e))
-            })?;
+            .map_err(|e| Error::pubsub_source("failed to make subscription create request", e))?;
 
         match resp.status() {
             StatusCode::OK | StatusCode::CONFLICT => Ok(()),
-            StatusCode::NOT_FOUND => Err(Error::Http(format!(
+            StatusCode::NOT_FOUND => Err(Error::pubsub(format!(
                 "pubsub subscription failed, topic '{}' does not exist",
                 self.topic()
             ))),
-            _ => Err(Error::Http(format!(
+            _ => Err(Error::pubsub(format!(
             "pubsub subscription failed, unexpected response with status code: {}, and body: {:?}",
             resp.status(),
             resp.text().await
@@ -151,11 +134,14 @@ impl<'a> Subscriber<'a> {
             .json(&serde_json::json!({ "maxMessages": self.max_messages }))
             .send()
             .await
-            .map_err(|e| Error::Http(format!("failed to make subscription pull request: {}", e)))?;
+            .map_err(|e| Error::pubsub_source("failed to make subscription pull request", e))?;
 
         match resp.status() {
-            StatusCode::OK => resp.json().await.map_err(|e| Error::Generic(Box::new(e))),
-            _ => Err(Error::Http(format!(
+            StatusCode::OK => resp
+                .json()
+                .await
+                .map_err(|e| Error::pubsub_source("failed to decode subscription pull response", e)),
+            _ => Err(Error::pubsub(format!(
                 "subscription pull response error code: {}",
                 resp.status()
             ))),
@@ -170,7 +156,7 @@ impl<'a> Subscriber<'a> {
             .send()
             .await
             .map(|_| ())
-            .map_err(|e| Error::Http(format!("failed to make ack request: {}", e)))
+            .map_err(|e| Error::pubsub_source("failed to make ack request", e))
     }
 
     pub fn topic(&self) -> String {
@@ -211,6 +197,18 @@ pub enum Event {
     ObjectArchive,
 }
 
+impl Event {
+    /// Returns the name of the event, as it's sent by Cloud Storage.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Event::ObjectFinalize => "OBJECT_FINALIZE",
+            Event::ObjectMetadataUpdate => "OBJECT_METADATA_UPDATE",
+            Event::ObjectDelete => "OBJECT_DELETE",
+            Event::ObjectArchive => "OBJECT_ARCHIVE",
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub enum PayloadFormat {
     #[serde(rename = "JSON_API_V1")]
@@ -1,20 +1,29 @@
 use std::time;
 use std::thread;
 
+use crate::config::Config;
+use crate::gcp_client::GcpClient;
 use crate::pubsub::Subscriber;
 use crate::util;
 use crate::db::Db;
-use crate::error::{Error, PubsubAction};
+use crate::dict::Actor;
+use crate::error::PubsubAction;
+use crate::health::Heartbeat;
+use crate::metrics::{self, Outcome};
 
+use chrono::Utc;
 use tokio::runtime::Runtime;
+use tracing_futures::Instrument;
 
-pub fn start(mut rt: Runtime, mut db: Db, ms_pull_delay: u64) {
+/// Pulls bucket notifications from pubsub and handles them, beating `heartbeat` after every pull
+/// and message handled, so that the server stops being ready if this runtime stalls or dies. Each
+/// message is handled within a `pubsub_message` span, which is correlated by its `message_id`.
+pub fn start(mut rt: Runtime, db: Db, heartbeat: Heartbeat, client: GcpClient, config: Config) {
     rt.block_on(async move {
-        let gcp_client = Default::default();
-        let sub = Subscriber::from_env(&gcp_client).await.unwrap();
+        let sub = Subscriber::new(&config, &client);
         log::info!("subscription '{}' created", sub.name());
         loop {
-            thread::sleep(time::Duration::from_millis(ms_pull_delay));
+            thread::sleep(time::Duration::from_millis(config.pubsub.pull_delay_ms));
 
             let resp = match sub.pull().await {
                 Ok(resp) => resp,
@@ -23,6 +32,14 @@ pub fn start(mut rt: Runtime, mut db: Db, ms_pull_delay: u64) {
                     continue;  
                 }
             };
+            heartbeat.beat();
+            metrics::observe_pull(
+                resp.received_messages
+                    .iter()
+                    .flatten()
+                    .map(|msg| &msg.message.publish_time),
+                Utc::now(),
+            );
 
             if let Some(mut messages) = resp.received_messages {
                 // In the event that multiple partitions are added in a very short period of time, 
@@ -34,26 +51,43 @@ pub fn start(mut rt: Runtime, mut db: Db, ms_pull_delay: u64) {
                 messages.sort();
 
                 for msg in messages.iter() {
-                    match util::handle_payload(
-                        &mut db,
-                        &msg.message.data,
-                        &msg.message.attributes,
-                    )
-                    .await 
-                    {
-                        Ok(_) => {
-                            if let Err(e) = sub.ack(&msg.ack_id).await {
-                                log::error!(
-                                    "failed to ack pubsub message with ack_id '{}': {}",
-                                    &msg.ack_id,
-                                    e
-                                )
+                    heartbeat.beat();
+                    // attribute any changes made by the message to it in the audit log
+                    let mut db = db.with_actor(Actor::Pubsub {
+                        message_id: msg.message.message_id.clone(),
+                    });
+                    let span = tracing::info_span!(
+                        "pubsub_message",
+                        message_id = %msg.message.message_id,
+                        correlation_id = %msg.message.message_id,
+                        event = msg.message.attributes.event_type.as_str(),
+                        object = %msg.message.attributes.object_id,
+                    );
+                    async {
+                        match util::handle_payload(
+                            &mut db,
+                            &msg.message.data,
+                            &msg.message.attributes,
+                        )
+                        .await 
+                        {
+                            Ok(_) => {
+                                metrics::observe_message(&msg.message.attributes.event_type, Outcome::Ack);
+                                if let Err(e) = sub.ack(&msg.ack_id).await {
+                                    log::error!(
+                                        "failed to ack pubsub message with ack_id '{}': {}",
+                                        &msg.ack_id,
+                                        e
+                                    )
+                                }
                             }
-                        }
-                        Err(e) => {
-                            match e {
-                                Error::Pubsub(action) => match action {
+                            Err(e) => {
+                                match e.pubsub_action() {
                                     PubsubAction::IgnoreAndAck => {
+                                        metrics::observe_message(
+                                            &msg.message.attributes.event_type,
+                                            Outcome::Ignore,
+                                        );
                                         if let Err(e) = sub.ack(&msg.ack_id).await {
                                             log::error!(
                                                 "failed to ack pubsub message with ack_id '{}': {}",
@@ -62,16 +96,24 @@ pub fn start(mut rt: Runtime, mut db: Db, ms_pull_delay: u64) {
                                             )
                                         } 
                                     }
-                                },
-                                _ => log::error!(
-                                    "failed to handle event '{:?}' message_id = '{}', will be retried: {}",
-                                    msg.message.attributes.event_type,
-                                    msg.message.message_id,
-                                    e
-                                )
-                            }
-                        },
+                                    PubsubAction::Retry => {
+                                        metrics::observe_message(
+                                            &msg.message.attributes.event_type,
+                                            Outcome::Retry,
+                                        );
+                                        log::error!(
+                                            "failed to handle event '{:?}' message_id = '{}', will be retried: {}",
+                                            msg.message.attributes.event_type,
+                                            msg.message.message_id,
+                                            e
+                                        )
+                                    }
+                                }
+                            },
+                        }
                     }
+                    .instrument(span)
+                    .await;
                 }
             }
         }
@@ -0,0 +1,27 @@
+use std::thread;
+use std::time;
+
+use crate::db::Db;
+use crate::dict::Dataset;
+
+use chrono::Duration;
+use tokio::runtime::Runtime;
+
+/// Periodically purges datasets and partitions which were deleted longer than `retention` ago,
+/// checking every `interval`.
+pub fn start(mut rt: Runtime, mut db: Db, retention: Duration, interval: time::Duration) {
+    rt.block_on(async move {
+        loop {
+            match Dataset::purge_deleted(&mut db, retention).await {
+                Ok(purged) => log::info!(
+                    "purged {} deleted datasets and {} deleted partitions",
+                    purged.datasets,
+                    purged.partitions
+                ),
+                Err(e) => log::error!("failed to purge deleted datasets and partitions: {}", e),
+            }
+
+            thread::sleep(interval);
+        }
+    });
+}
@@ -1,12 +1,22 @@
 use crate::dict::{
-    Attributes, Classification, Compression, Dataset, DatasetSchema, Format, Manager, Partition,
-    RangeParams,
+    AccessDenial, AccessFilter, Attributes, AuditEvent, AuditFilter, CatalogStats, Classification,
+    Compression, DataRange, Dataset, DatasetFilter, DatasetLabels, DatasetPage, DatasetSchema,
+    DatasetSensitivity, DatasetTransfer, FieldSearch, Format, Maintainer, Manager, Partition,
+    PartitionFilter, PartitionKey, PartitionPage, PartitionRegistration, PiiField, Purged,
+    RangeParams, ReadGrant, SchemaField, SearchParams, SearchResults, Sensitivity,
 };
 use crate::error::Error;
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
+/// DataService is the storage of the data dictionary. Lookups of a single record (`find_*`) fail
+/// with `Error::NotFound` when it doesn't exist, so that callers can tell a missing record apart
+/// from a failure of the storage itself.
+///
+/// It's implemented by both `Db` and `DbTx`, so that operations which make several changes can run
+/// them within `Db::transaction`, where they are committed or rolled back together.
 #[async_trait]
 pub trait DataService {
     async fn register_dataset(
@@ -22,28 +32,95 @@ pub trait DataService {
 
     async fn find_dataset(&mut self, name: &str) -> Result<Dataset, Error>;
 
-    async fn search_datasets(&mut self, term: &str) -> Result<Vec<Dataset>, Error>;
+    async fn search_datasets(&mut self, params: &SearchParams) -> Result<SearchResults, Error>;
 
     async fn list_datasets(&mut self, params: Option<RangeParams>) -> Result<Vec<Dataset>, Error>;
 
+    async fn list_pii_fields(
+        &mut self,
+        sensitivity: Option<Sensitivity>,
+    ) -> Result<Vec<PiiField>, Error>;
+
+    async fn update_dataset_sensitivity(
+        &mut self,
+        dataset: &Dataset,
+        sensitivity: &DatasetSensitivity,
+    ) -> Result<Dataset, Error>;
+
+    async fn update_partition_template(
+        &mut self,
+        dataset: &Dataset,
+        template: Option<&str>,
+    ) -> Result<Dataset, Error>;
+
+    async fn page_datasets(
+        &mut self,
+        filter: &DatasetFilter,
+        params: &RangeParams,
+    ) -> Result<DatasetPage, Error>;
+
+    async fn list_labeled_datasets(
+        &mut self,
+        labels: &DatasetLabels,
+        params: Option<RangeParams>,
+    ) -> Result<Vec<Dataset>, Error>;
+
+    async fn update_dataset_labels(
+        &mut self,
+        dataset: &Dataset,
+        labels: &DatasetLabels,
+    ) -> Result<Dataset, Error>;
+
+    async fn find_schema_fields(&mut self, search: &FieldSearch)
+        -> Result<Vec<SchemaField>, Error>;
+
+    async fn update_dataset(
+        &mut self,
+        dataset: &Dataset,
+        description: &str,
+        schema: DatasetSchema,
+    ) -> Result<Dataset, Error>;
+
     async fn delete_dataset(&mut self, dataset: &Dataset) -> Result<(), Error>;
 
+    async fn find_deleted_dataset(&mut self, name: &str) -> Result<Dataset, Error>;
+
+    async fn restore_dataset(&mut self, dataset: &Dataset) -> Result<Dataset, Error>;
+
+    async fn purge_deleted(&mut self, deleted_before: DateTime<Utc>) -> Result<Purged, Error>;
+
     async fn list_dataset_attributes(&mut self) -> Result<Attributes, Error>;
 
+    async fn list_catalog_stats(&mut self) -> Result<Vec<CatalogStats>, Error>;
+
     async fn register_partition(
         &mut self,
         dataset: &Dataset,
         partition_name: &str,
         partition_url: &str,
         partition_size: i64,
+        partition_key: &PartitionKey,
+        data_range: Option<DataRange>,
     ) -> Result<Partition, Error>;
 
+    async fn register_partitions(
+        &mut self,
+        dataset: &Dataset,
+        partitions: &[PartitionRegistration],
+    ) -> Result<Vec<Partition>, Error>;
+
     async fn delete_partition(
         &mut self,
         dataset: &Dataset,
         partition_name: &str,
     ) -> Result<(), Error>;
 
+    async fn restore_partition(
+        &mut self,
+        dataset: &Dataset,
+        partition_name: &str,
+    ) -> Result<Partition, Error>;
+
     async fn find_partition(
         &mut self,
         dataset: &Dataset,
@@ -56,6 +133,20 @@ pub trait DataService {
         params: Option<RangeParams>,
     ) -> Result<Vec<Partition>, Error>;
 
+    async fn page_partitions(
+        &mut self,
+        dataset: &Dataset,
+        filter: &PartitionFilter,
+        params: &RangeParams,
+    ) -> Result<PartitionPage, Error>;
+
+    async fn list_keyed_partitions(
+        &mut self,
+        dataset: &Dataset,
+        key: &PartitionKey,
+        params: Option<RangeParams>,
+    ) -> Result<Vec<Partition>, Error>;
+
     async fn register_manager(&mut self, email: &str, password: &str) -> Result<Manager, Error>;
 
     async fn find_manager(&mut self, api_key: &Uuid) -> Result<Manager, Error>;
@@ -63,4 +154,78 @@ pub trait DataService {
     async fn auth_manager(&mut self, email: &str, password: &str) -> Result<Manager, Error>;
 
     async fn manager_datasets(&mut self, api_key: &Uuid) -> Result<Vec<Dataset>, Error>;
+
+    async fn find_manager_by_id(&mut self, manager_id: i32) -> Result<Manager, Error>;
+
+    async fn list_managers(&mut self) -> Result<Vec<Manager>, Error>;
+
+    async fn update_manager_admin(
+        &mut self,
+        manager_id: i32,
+        admin: bool,
+    ) -> Result<Manager, Error>;
+
+    async fn update_manager_disabled(
+        &mut self,
+        manager_id: i32,
+        disabled: bool,
+    ) -> Result<Manager, Error>;
+
+    async fn transfer_datasets(
+        &mut self,
+        from_manager_id: i32,
+        to_manager_id: i32,
+    ) -> Result<u64, Error>;
+
+    async fn delete_manager(&mut self, manager_id: i32, successor_id: i32) -> Result<(), Error>;
+
+    async fn find_manager_by_email(&mut self, email: &str) -> Result<Manager, Error>;
+
+    async fn list_maintainers(&mut self, dataset: &Dataset) -> Result<Vec<Maintainer>, Error>;
+
+    async fn add_maintainer(
+        &mut self,
+        dataset: &Dataset,
+        manager: &Manager,
+    ) -> Result<Maintainer, Error>;
+
+    async fn remove_maintainer(&mut self, dataset: &Dataset, manager_id: i32) -> Result<(), Error>;
+
+    async fn list_read_grants(&mut self, dataset: &Dataset) -> Result<Vec<ReadGrant>, Error>;
+
+    async fn add_read_grant(
+        &mut self,
+        dataset: &Dataset,
+        manager: &Manager,
+    ) -> Result<ReadGrant, Error>;
+
+    async fn remove_read_grant(&mut self, dataset: &Dataset, manager_id: i32) -> Result<(), Error>;
+
+    async fn log_access_denial(&mut self, denial: &AccessDenial) -> Result<(), Error>;
+
+    async fn list_access_denials(
+        &mut self,
+        filter: &AccessFilter,
+    ) -> Result<Vec<AccessDenial>, Error>;
+
+    async fn request_transfer(
+        &mut self,
+        dataset: &Dataset,
+        to: &Manager,
+    ) -> Result<DatasetTransfer, Error>;
+
+    async fn find_transfer(&mut self, transfer_id: i32) -> Result<DatasetTransfer, Error>;
+
+    async fn find_dataset_transfer(
+        &mut self,
+        dataset: &Dataset,
+    ) -> Result<Option<DatasetTransfer>, Error>;
+
+    async fn list_transfers(&mut self, manager: &Manager) -> Result<Vec<DatasetTransfer>, Error>;
+
+    async fn accept_transfer(&mut self, transfer: &DatasetTransfer) -> Result<Dataset, Error>;
+
+    async fn delete_transfer(&mut self, transfer: &DatasetTransfer) -> Result<(), Error>;
+
+    async fn list_audit_events(&mut self, filter: &AuditFilter) -> Result<Vec<AuditEvent>, Error>;
 }
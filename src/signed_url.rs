@@ -0,0 +1,267 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::error::Error;
+
+use chrono::{DateTime, Duration, Utc};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use ring::{
+    digest,
+    rand::SystemRandom,
+    signature::{RsaKeyPair, RSA_PKCS1_SHA256},
+};
+use serde::{Deserialize, Serialize};
+
+const SIGNING_ALGORITHM: &str = "GOOG4-RSA-SHA256";
+const STORAGE_HOST: &str = "storage.googleapis.com";
+
+/// The longest a V4 signed URL may be valid for, as allowed by Cloud Storage.
+pub const MAX_SIGNED_URL_TTL_SECS: i64 = 604_800;
+
+// only unreserved characters are left unescaped in the canonical request, and "/" within paths
+const UNRESERVED: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+const UNRESERVED_PATH: &AsciiSet = &UNRESERVED.remove(b'/');
+
+/// The fields of a service account key file needed to sign URLs.
+#[derive(Deserialize)]
+struct ServiceAccountKey {
+    client_email: String,
+    private_key: String,
+}
+
+/// A SignedUrl grants temporary access to a single Cloud Storage object, without any other
+/// credentials. Requests to the URL must use its method, and include every header provided.
+#[derive(Debug, Serialize)]
+pub struct SignedUrl {
+    pub object: String,
+    pub method: String,
+    pub url: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A UrlSigner creates V4 signed URLs for Cloud Storage objects using a service account's private
+/// key, see: https://cloud.google.com/storage/docs/access-control/signing-urls-manually
+pub struct UrlSigner {
+    client_email: String,
+    key_pair: RsaKeyPair,
+}
+
+impl UrlSigner {
+    /// Creates a signer from the service account key file found at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_service_account_json(&fs::read_to_string(path)?)
+    }
+
+    /// Creates a signer from the contents of a service account key file.
+    pub fn from_service_account_json(json: &str) -> Result<Self, Error> {
+        let key: ServiceAccountKey = serde_json::from_str(json)?;
+        let pem = pem::parse(&key.private_key)
+            .map_err(|e| Error::Auth(format!("invalid service account private key: {:?}", e)))?;
+        let key_pair = RsaKeyPair::from_pkcs8(&pem.contents)
+            .map_err(|e| Error::Auth(format!("invalid service account private key: {}", e)))?;
+
+        Ok(Self {
+            client_email: key.client_email,
+            key_pair,
+        })
+    }
+
+    /// Signs a URL allowing `method` requests, which include the headers provided, to an object in
+    /// the bucket provided, starting at `now` and expiring after `ttl`.
+    pub fn sign(
+        &self,
+        method: &str,
+        bucket: &str,
+        object: &str,
+        headers: &[(&str, &str)],
+        ttl: Duration,
+        now: DateTime<Utc>,
+    ) -> Result<SignedUrl, Error> {
+        if ttl < Duration::seconds(1) || ttl > Duration::seconds(MAX_SIGNED_URL_TTL_SECS) {
+            return Err(Error::InputValidation(format!(
+                "signed URLs must expire within 1 to {} seconds",
+                MAX_SIGNED_URL_TTL_SECS
+            )));
+        }
+
+        let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let scope = format!("{}/auto/storage/goog4_request", now.format("%Y%m%d"));
+        // headers are signed in order of their lowercase names, and must always include the host
+        let headers = headers
+            .iter()
+            .map(|(k, v)| (k.to_lowercase(), v.trim().to_string()))
+            .collect::<BTreeMap<_, _>>();
+        let mut canonical_headers = headers.clone();
+        canonical_headers.insert("host".into(), STORAGE_HOST.into());
+        let signed_headers = canonical_headers
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(";");
+        let query = [
+            ("X-Goog-Algorithm", SIGNING_ALGORITHM.to_string()),
+            (
+                "X-Goog-Credential",
+                format!("{}/{}", self.client_email, scope),
+            ),
+            ("X-Goog-Date", timestamp.clone()),
+            ("X-Goog-Expires", ttl.num_seconds().to_string()),
+            ("X-Goog-SignedHeaders", signed_headers.clone()),
+        ]
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, utf8_percent_encode(v, UNRESERVED)))
+        .collect::<Vec<_>>()
+        .join("&");
+        let path = format!(
+            "/{}/{}",
+            bucket,
+            utf8_percent_encode(object, UNRESERVED_PATH)
+        );
+
+        let canonical_request = [
+            method,
+            &path,
+            &query,
+            &canonical_headers
+                .iter()
+                .map(|(k, v)| format!("{}:{}\n", k, v))
+                .collect::<String>(),
+            &signed_headers,
+            "UNSIGNED-PAYLOAD",
+        ]
+        .join("\n");
+        let string_to_sign = [
+            SIGNING_ALGORITHM,
+            &timestamp,
+            &scope,
+            &to_hex(digest::digest(&digest::SHA256, canonical_request.as_bytes()).as_ref()),
+        ]
+        .join("\n");
+
+        let mut signature = vec![0; self.key_pair.public_modulus_len()];
+        self.key_pair
+            .sign(
+                &RSA_PKCS1_SHA256,
+                &SystemRandom::new(),
+                string_to_sign.as_bytes(),
+                &mut signature,
+            )
+            .map_err(|_| Error::Auth("failed to sign URL".into()))?;
+
+        Ok(SignedUrl {
+            object: object.into(),
+            method: method.into(),
+            url: format!(
+                "https://{}{}?{}&X-Goog-Signature={}",
+                STORAGE_HOST,
+                path,
+                query,
+                to_hex(&signature)
+            ),
+            headers,
+            expires_at: now + ttl,
+        })
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn test_sign_url() {
+    use ring::signature::{KeyPair, UnparsedPublicKey, RSA_PKCS1_2048_8192_SHA256};
+
+    let signer =
+        UrlSigner::from_service_account_json(include_str!("../tests/json/service_account.json"))
+            .unwrap();
+    let now = "2020-06-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+    let signed = signer
+        .sign(
+            "GET",
+            "dd-public",
+            "example_dataset/2020/06/01/part 1.json.gz",
+            &[],
+            Duration::minutes(15),
+            now,
+        )
+        .unwrap();
+    assert_eq!(signed.object, "example_dataset/2020/06/01/part 1.json.gz");
+    assert_eq!(signed.method, "GET");
+    assert!(signed.headers.is_empty());
+    assert_eq!(signed.expires_at, now + Duration::minutes(15));
+
+    let path = "/dd-public/example_dataset/2020/06/01/part%201.json.gz";
+    let query = "X-Goog-Algorithm=GOOG4-RSA-SHA256\
+        &X-Goog-Credential=data-dictionary%40data-dictionary-test.iam.gserviceaccount.com%2F20200601%2Fauto%2Fstorage%2Fgoog4_request\
+        &X-Goog-Date=20200601T120000Z\
+        &X-Goog-Expires=900\
+        &X-Goog-SignedHeaders=host";
+    let (url, signature) = signed.url.split_at(signed.url.len() - 512);
+    assert_eq!(
+        url,
+        format!(
+            "https://storage.googleapis.com{}?{}&X-Goog-Signature=",
+            path, query
+        )
+    );
+
+    // the signature must be verifiable by the service account's public key
+    let canonical_request = format!(
+        "GET\n{}\n{}\nhost:storage.googleapis.com\n\nhost\nUNSIGNED-PAYLOAD",
+        path, query
+    );
+    let string_to_sign = format!(
+        "GOOG4-RSA-SHA256\n20200601T120000Z\n20200601/auto/storage/goog4_request\n{}",
+        to_hex(digest::digest(&digest::SHA256, canonical_request.as_bytes()).as_ref())
+    );
+    let signature = (0..signature.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&signature[i..i + 2], 16).unwrap())
+        .collect::<Vec<_>>();
+    UnparsedPublicKey::new(
+        &RSA_PKCS1_2048_8192_SHA256,
+        signer.key_pair.public_key().as_ref(),
+    )
+    .verify(string_to_sign.as_bytes(), &signature)
+    .unwrap();
+
+    for ttl in &[Duration::seconds(0), Duration::days(8)] {
+        assert!(signer
+            .sign("GET", "dd-public", "o", &[], *ttl, now)
+            .is_err());
+    }
+    assert!(UrlSigner::from_service_account_json("{}").is_err());
+}
+
+#[test]
+fn test_sign_url_headers() {
+    let signer =
+        UrlSigner::from_service_account_json(include_str!("../tests/json/service_account.json"))
+            .unwrap();
+    let now = "2020-06-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+    let signed = signer
+        .sign(
+            "POST",
+            "dd-internal",
+            "example_dataset/part.csv",
+            &[("X-Goog-Resumable", " start ")],
+            Duration::minutes(15),
+            now,
+        )
+        .unwrap();
+
+    assert_eq!(signed.method, "POST");
+    assert_eq!(signed.headers.len(), 1);
+    assert_eq!(signed.headers["x-goog-resumable"], "start");
+    assert!(signed
+        .url
+        .contains("&X-Goog-SignedHeaders=host%3Bx-goog-resumable&"));
+}
@@ -0,0 +1,129 @@
+use std::fmt;
+
+use crate::config::TelemetryConfig;
+use crate::error::Error;
+
+use opentelemetry::{
+    global,
+    sdk::{propagation::TraceContextPropagator, trace, Resource},
+    KeyValue,
+};
+use serde::Deserialize;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+/// Telemetry keeps the exporter of spans running until it's dropped, at which point the spans which
+/// are yet to be exported are flushed.
+pub struct Telemetry {
+    runtime: Option<tokio1::runtime::Runtime>,
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if self.runtime.is_some() {
+            global::shutdown_tracer_provider();
+        }
+    }
+}
+
+/// Installs the subscriber of every log and span, which writes them to stdout as JSON lines. Logs
+/// made with the `log` crate are included, along with the fields of the spans they were made in,
+/// such as the `request_id` of a request or the `message_id` of a pubsub message.
+///
+/// Spans are also exported by OTLP if the configuration has an endpoint, e.g.
+/// "http://localhost:4317" for a local collector. The export runs on a runtime of its own, which
+/// is kept by the `Telemetry` returned.
+pub fn init(config: &TelemetryConfig) -> Result<Telemetry, Error> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let filter =
+        EnvFilter::try_new(&config.log_filter).map_err(|e| Error::Config(e.to_string()))?;
+    let logs = tracing_subscriber::fmt::layer()
+        .json()
+        .with_current_span(true)
+        .with_span_list(true);
+
+    let (runtime, spans) = if let Some(endpoint) = &config.otlp_endpoint {
+        let runtime = tokio1::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("otlp-exporter")
+            .enable_all()
+            .build()
+            .map_err(|e| Error::Generic(Box::new(e)))?;
+        let tracer = {
+            // the batch exporter is spawned onto the runtime which it's installed within
+            let _guard = runtime.enter();
+            opentelemetry_otlp::new_pipeline()
+                .with_env()
+                .with_endpoint(endpoint.as_str())
+                .with_trace_config(trace::config().with_resource(Resource::new(vec![
+                    KeyValue::new("service.name", config.service_name.clone()),
+                ])))
+                .with_tonic()
+                .install_batch(opentelemetry::runtime::Tokio)
+                .map_err(|e| Error::Generic(Box::new(e)))?
+        };
+        (
+            Some(runtime),
+            Some(tracing_opentelemetry::layer().with_tracer(tracer)),
+        )
+    } else {
+        (None, None)
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(logs)
+        .with(spans)
+        .try_init()
+        .map_err(|e| Error::Generic(Box::new(e)))?;
+
+    Ok(Telemetry { runtime })
+}
+
+/// A Secret is a value which must never be logged, such as a password. It's formatted as
+/// "[redacted]" no matter how it's logged, and is deserialized from the value it wraps.
+#[derive(Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Secret<T>(pub T);
+
+impl<T: AsRef<str>> AsRef<str> for Secret<T> {
+    fn as_ref(&self) -> &str {
+        self.0.as_ref()
+    }
+}
+
+impl<T> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[redacted]")
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[redacted]")
+    }
+}
+
+/// Masks a credential which logs must be able to tell apart without revealing, such as an API key,
+/// by keeping only its first 8 characters, e.g. "1b4e28ba-****".
+pub fn mask(credential: impl fmt::Display) -> String {
+    let credential = credential.to_string();
+    let shown: String = credential.chars().take(8).collect();
+    format!("{}-****", shown)
+}
+
+#[test]
+fn test_redaction() {
+    let password = Secret("hunter2");
+    assert_eq!(format!("{}", password), "[redacted]");
+    assert_eq!(format!("{:?}", password), "[redacted]");
+    assert!(!format!("{:?}", Some(password.clone())).contains("hunter2"));
+    assert_eq!(password.as_ref(), "hunter2");
+
+    let password: Secret<String> = serde_json::from_str(r#""hunter2""#).unwrap();
+    assert_eq!(password.as_ref(), "hunter2");
+
+    let api_key = uuid::Uuid::parse_str("1b4e28ba-2fa1-11d2-883f-0016d3cca427").unwrap();
+    assert_eq!(mask(api_key), "1b4e28ba-****");
+    assert_eq!(mask("abc"), "abc-****");
+}
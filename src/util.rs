@@ -1,8 +1,8 @@
 use std::path::Path;
 
 use crate::db::Db;
-use crate::dict::Dataset;
-use crate::error::{Error, PubsubAction};
+use crate::dict::{DataRange, Dataset};
+use crate::error::Error;
 use crate::pubsub::{Attributes, Event, Payload};
 
 pub const FILENAME_DD_JSON: &str = "dd.json";
@@ -23,21 +23,57 @@ pub async fn handle_payload(db: &mut Db, b64_data: &str, attrs: &Attributes) ->
     );
 
     let path = Path::new(&payload.name);
-    let dataset = Dataset::find(db, dataset_name(path)?).await;
-    if let Ok(dataset) = dataset {
+    // only a dataset which doesn't exist is handled below: any other failure to find it is
+    // returned, so that the event is retried rather than ignored
+    let dataset = match Dataset::find(db, dataset_name(path)?).await {
+        Ok(dataset) => Some(dataset),
+        Err(Error::NotFound(_)) => None,
+        Err(e) => {
+            log::error!("failed to find dataset for object {}: {}", payload.name, e);
+            return Err(e);
+        }
+    };
+    if let Some(dataset) = dataset {
         match attrs.event_type {
             Event::ObjectFinalize | Event::ObjectMetadataUpdate | Event::ObjectArchive => {
                 if let Some(name) = partition_name(path)? {
-                    if let Err(e) = dataset
-                        .register_partition(db, &name, payload.self_link, size)
-                        .await
-                    {
+                    // a data range set in the object's metadata takes precedence over one based on
+                    // the partition's name
+                    let data_range = match &payload.metadata {
+                        Some(metadata) => DataRange::from_metadata(metadata),
+                        None => Ok(None),
+                    };
+                    let registered = match data_range {
+                        Ok(Some(data_range)) => {
+                            dataset
+                                .register_partition_covering(
+                                    db,
+                                    &name,
+                                    payload.self_link,
+                                    size,
+                                    data_range,
+                                )
+                                .await
+                        }
+                        Ok(None) => {
+                            dataset
+                                .register_partition(db, &name, payload.self_link, size)
+                                .await
+                        }
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = registered {
                         log::error!(
                             "failed to register partition '{}' for dataset '{}': {}",
                             name,
                             dataset.name,
                             e
                         );
+                        // a partition which can't be registered is ignored, unless registering it
+                        // again may succeed
+                        if e.is_retryable() {
+                            return Err(e);
+                        }
                     }
                 } else {
                 }
@@ -78,18 +114,23 @@ pub async fn handle_payload(db: &mut Db, b64_data: &str, attrs: &Attributes) ->
                 "new object is a dataset, handled outside of pubsub: {:?}, ignore and acking",
                 payload.name
             );
-            return Err(Error::Pubsub(PubsubAction::IgnoreAndAck));
+            return Err(Error::ignore_and_ack(format!(
+                "object '{}' is a dataset, handled outside of pubsub",
+                payload.name
+            )));
         }
 
-        Err(dataset
-            .err()
-            .expect("no error for dataset failure specified"))
+        // the partition may be of a dataset which is yet to be registered, so it's retried
+        Err(Error::NotFound(format!(
+            "no dataset found for partition object {}",
+            payload.name
+        )))
     }
 }
 
 fn base64_dec<T: serde::de::DeserializeOwned>(data: &str) -> Result<T, Error> {
-    let data = base64::decode(data).map_err(|e| Error::Generic(Box::new(e)))?;
-    serde_json::from_slice(data.as_slice()).map_err(|e| Error::Generic(Box::new(e)))
+    let data = base64::decode(data)?;
+    Ok(serde_json::from_slice(data.as_slice())?)
 }
 
 fn dataset_name(path: &Path) -> Result<String, Error> {
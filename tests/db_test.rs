@@ -1,11 +1,24 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
 mod testutil;
 use testutil::Rand::{Email, PartitionName, PartitionUrl, Password, String};
 
+use data_dictionary::dict::{
+    AccessDenial, AccessFilter, Actor, AuditEvent, AuditFilter, CatalogStats,
+};
 use data_dictionary::dict::{Attributes, Classification, Compression, Format};
-use data_dictionary::dict::{Dataset, DatasetConfig, Manager, Partition};
+use data_dictionary::dict::{Cursor, RangeParams, SearchParams, SearchResults};
+use data_dictionary::dict::{
+    DataRange, DatasetLabels, DatasetSchema, DatasetSensitivity, FieldSearch,
+};
+use data_dictionary::dict::{Dataset, DatasetConfig, Manager, NewPartition, Partition, Purged};
+use data_dictionary::dict::{DatasetFilter, PartitionFilter, SortKey, SortOrder};
+use data_dictionary::dict::{PartitionKey, PiiField, Sensitivity};
+use data_dictionary::error::{Error, ErrorKind};
 use data_dictionary::service::DataService;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use uuid::Uuid;
 
 #[tokio::test]
@@ -77,6 +90,8 @@ async fn test_dataset() {
                     Classification::Restricted,
                 )),
                 testutil::rand_size(),
+                &PartitionKey::default(),
+                None,
             )
             .await;
         assert!(partition_result.is_ok());
@@ -435,9 +450,9 @@ async fn test_range_query_partitions() {
     }
 
     // test no parameters
-    let mut params = Default::default();
+    let mut params = RangeParams::default();
     let partitions = dataset
-        .partitions(&mut test_db.db, Some(params))
+        .partitions(&mut test_db.db, Some(params.clone()))
         .await
         .unwrap();
     assert_eq!(partitions.len(), partition_count);
@@ -448,7 +463,7 @@ async fn test_range_query_partitions() {
     params = Default::default();
     params.start = Some(midpoint);
     let after_midpoint = dataset
-        .partitions(&mut test_db.db, Some(params))
+        .partitions(&mut test_db.db, Some(params.clone()))
         .await
         .unwrap();
     for p in after_midpoint.iter() {
@@ -459,7 +474,7 @@ async fn test_range_query_partitions() {
     params = Default::default();
     params.end = Some(midpoint);
     let before_midpoint = dataset
-        .partitions(&mut test_db.db, Some(params))
+        .partitions(&mut test_db.db, Some(params.clone()))
         .await
         .unwrap();
     for p in before_midpoint.iter() {
@@ -471,7 +486,7 @@ async fn test_range_query_partitions() {
     for test_count in 1..partition_count {
         params.count = Some(test_count as i32);
         let specific_count = dataset
-            .partitions(&mut test_db.db, Some(params))
+            .partitions(&mut test_db.db, Some(params.clone()))
             .await
             .unwrap();
         assert_eq!(specific_count.len(), test_count as usize);
@@ -480,7 +495,7 @@ async fn test_range_query_partitions() {
     // test that only the available amount of partitions is returned, even if a higher count is used
     params.count = Some((partition_count + 1) as i32);
     let actual_count = dataset
-        .partitions(&mut test_db.db, Some(params))
+        .partitions(&mut test_db.db, Some(params.clone()))
         .await
         .unwrap();
     assert_eq!(actual_count.len(), partition_count as usize);
@@ -489,7 +504,7 @@ async fn test_range_query_partitions() {
     params = Default::default();
     params.offset = Some(20);
     let actual_count = dataset
-        .partitions(&mut test_db.db, Some(params))
+        .partitions(&mut test_db.db, Some(params.clone()))
         .await
         .unwrap();
     assert_eq!(
@@ -500,7 +515,7 @@ async fn test_range_query_partitions() {
     // test an offset higher than the available number of records
     params.offset = Some((partition_count * 2) as i32);
     let actual_count = dataset
-        .partitions(&mut test_db.db, Some(params))
+        .partitions(&mut test_db.db, Some(params.clone()))
         .await
         .unwrap();
     assert_eq!(actual_count.len(), 0);
@@ -511,7 +526,7 @@ async fn test_range_query_partitions() {
     // use an the first partition of the full set of partitions, since it is the most recent time
     params.end = Some(partitions.first().unwrap().created_at);
     let actual_count = dataset
-        .partitions(&mut test_db.db, Some(params))
+        .partitions(&mut test_db.db, Some(params.clone()))
         .await
         .unwrap();
     assert!(actual_count.len() > 0);
@@ -526,7 +541,7 @@ async fn test_range_query_partitions() {
     // test start + end + count parameters
     params.count = Some(3);
     let actual_count_limit = dataset
-        .partitions(&mut test_db.db, Some(params))
+        .partitions(&mut test_db.db, Some(params.clone()))
         .await
         .unwrap();
     assert_eq!(actual_count_limit.len(), 3 as usize);
@@ -534,7 +549,7 @@ async fn test_range_query_partitions() {
     // test start + end + count + offset parameters
     params.offset = Some(1);
     let actual_count_limit_offset = dataset
-        .partitions(&mut test_db.db, Some(params))
+        .partitions(&mut test_db.db, Some(params.clone()))
         .await
         .unwrap();
     assert!(actual_count_limit
@@ -546,7 +561,7 @@ async fn test_range_query_partitions() {
     // test start + end + offset
     params.count = None;
     let actual_count_offset = dataset
-        .partitions(&mut test_db.db, Some(params))
+        .partitions(&mut test_db.db, Some(params.clone()))
         .await
         .unwrap();
     assert!(actual_count_limit
@@ -583,19 +598,25 @@ async fn test_range_query_datasets() {
     // test that all are returned when the params are None and when all param fields are None
     let all_datasets = Dataset::list(&mut test_db.db, None).await.unwrap();
     assert_eq!(all_datasets.len(), dataset_count);
-    let mut params = Default::default();
-    let all_datasets = Dataset::list(&mut test_db.db, Some(params)).await.unwrap();
+    let mut params = RangeParams::default();
+    let all_datasets = Dataset::list(&mut test_db.db, Some(params.clone()))
+        .await
+        .unwrap();
     assert_eq!(all_datasets.len(), dataset_count);
 
     // test that the correct number of datasets are returned when there is a count param set
     let count = 5;
     params.count = Some(count);
-    let limited_datasets = Dataset::list(&mut test_db.db, Some(params)).await.unwrap();
+    let limited_datasets = Dataset::list(&mut test_db.db, Some(params.clone()))
+        .await
+        .unwrap();
     assert_eq!(limited_datasets.len(), count as usize);
 
     // test count + offset
     params.offset = Some(5);
-    let limited_offset_datasets = Dataset::list(&mut test_db.db, Some(params)).await.unwrap();
+    let limited_offset_datasets = Dataset::list(&mut test_db.db, Some(params.clone()))
+        .await
+        .unwrap();
     for (i, offset_dataset) in limited_offset_datasets.iter().enumerate() {
         assert!(offset_dataset
             .updated_at
@@ -633,9 +654,10 @@ async fn test_manager() {
 
     // set test email validation domain so validation fails
     // check that invalid email address patterns fail registration
-    std::env::set_var("DD_MANAGER_EMAIL_DOMAIN", "test.com");
     let invalid = test_db
         .db
+        .clone()
+        .with_email_domain(Some("test.com".to_string()))
         .register_manager("bad@validation.com", "12345678")
         .await;
     assert!(invalid.is_err());
@@ -657,88 +679,2405 @@ async fn test_manager() {
 }
 
 #[tokio::test]
-async fn test_dataset_from_config() {
+async fn test_manager_admin() {
     let mut test_db = testutil::new_test_db().await.unwrap();
+
+    // bootstrap the first admin, and expect subsequent bootstraps to be skipped
+    let admin_email = testutil::get_rand(Email);
+    let admin_password = testutil::get_rand(Password);
+    let admin = Manager::bootstrap_admin(&mut test_db.db, &admin_email, &admin_password)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(admin.admin);
+    assert_eq!(admin.email, admin_email);
+    assert!(Manager::bootstrap_admin(
+        &mut test_db.db,
+        testutil::get_rand(Email),
+        testutil::get_rand(Password)
+    )
+    .await
+    .unwrap()
+    .is_none());
+
+    // promote and demote a manager
     let manager = testutil::create_manager(&mut test_db).await.unwrap();
-    let dd: &str = include_str!("json/dd.json");
-    let config: DatasetConfig = serde_json::from_str(dd).unwrap();
-    let dataset = manager
+    assert!(!manager.admin);
+    assert!(!manager.disabled);
+    let manager = manager.set_admin(&mut test_db.db, true).await.unwrap();
+    assert!(manager.admin);
+    let manager = manager.set_admin(&mut test_db.db, false).await.unwrap();
+    assert!(!manager.admin);
+
+    let managers = Manager::list(&mut test_db.db).await.unwrap();
+    assert_eq!(managers.len(), 2);
+    assert_eq!(managers.iter().filter(|m| m.admin).count(), 1);
+
+    // disabled managers can't authenticate or be found by their API key
+    let email = testutil::get_rand(Email);
+    let password = testutil::get_rand(Password);
+    let disabled = Manager::register(&mut test_db.db, &email, &password)
+        .await
+        .unwrap()
+        .set_disabled(&mut test_db.db, true)
+        .await
+        .unwrap();
+    assert!(disabled.disabled);
+    assert!(Manager::authenticate(&mut test_db.db, &email, &password)
+        .await
+        .is_err());
+    assert!(Manager::find(&mut test_db.db, disabled.api_key)
+        .await
+        .is_err());
+    let found = Manager::find_by_id(&mut test_db.db, disabled.id)
+        .await
+        .unwrap();
+    assert!(found.disabled);
+
+    // re-enabled managers can authenticate again
+    let enabled = found.set_disabled(&mut test_db.db, false).await.unwrap();
+    assert!(!enabled.disabled);
+    assert!(Manager::authenticate(&mut test_db.db, &email, &password)
+        .await
+        .is_ok());
+
+    // transfer datasets from one manager to another
+    for _ in 0..3 {
+        manager
+            .register_dataset(
+                &mut test_db.db,
+                testutil::get_rand(String(20)),
+                Compression::Gzip,
+                Format::Csv,
+                Classification::Internal,
+                testutil::rand_schema(),
+                testutil::get_rand(String(40)),
+            )
+            .await
+            .unwrap();
+    }
+    let transferred = manager
+        .transfer_datasets(&mut test_db.db, &enabled)
+        .await
+        .unwrap();
+    assert_eq!(transferred, 3);
+    assert_eq!(manager.datasets(&mut test_db.db).await.unwrap().len(), 0);
+    assert_eq!(enabled.datasets(&mut test_db.db).await.unwrap().len(), 3);
+
+    // delete a manager which owns datasets, and expect them to move to the successor
+    let enabled_id = enabled.id;
+    enabled.delete(&mut test_db.db, &admin).await.unwrap();
+    assert!(Manager::find_by_id(&mut test_db.db, enabled_id)
+        .await
+        .is_err());
+    assert_eq!(admin.datasets(&mut test_db.db).await.unwrap().len(), 3);
+
+    // expect deleting a manager in favor of itself to fail
+    let manager_id = manager.id;
+    let self_successor = Manager::find_by_id(&mut test_db.db, manager_id)
+        .await
+        .unwrap();
+    assert!(manager
+        .delete(&mut test_db.db, &self_successor)
+        .await
+        .is_err());
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_bootstrap_registered_admin() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+
+    // a registered manager is only promoted with its own password, and never registered again
+    let email = testutil::get_rand(Email);
+    let password = testutil::get_rand(Password);
+    let registered = Manager::register(&mut test_db.db, &email, &password)
+        .await
+        .unwrap();
+    let err = Manager::bootstrap_admin(&mut test_db.db, &email, "invalidPassword")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Config(_)));
+    assert_eq!(Manager::list(&mut test_db.db).await.unwrap().len(), 1);
+
+    let admin = Manager::bootstrap_admin(&mut test_db.db, &email, &password)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(admin.admin);
+    assert_eq!(admin.id, registered.id);
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_dataset_maintainers_and_transfers() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+    let owner = testutil::create_manager(&mut test_db).await.unwrap();
+    let maintainer = testutil::create_manager(&mut test_db).await.unwrap();
+    let recipient = testutil::create_manager(&mut test_db).await.unwrap();
+
+    let dataset = owner
         .register_dataset(
             &mut test_db.db,
-            config.name,
-            config.compression,
-            config.format,
-            config.classification,
-            config.schema,
-            config.description,
+            testutil::get_rand(String(20)),
+            Compression::Gzip,
+            Format::Json,
+            Classification::Internal,
+            testutil::rand_schema(),
+            testutil::get_rand(String(40)),
         )
         .await
         .unwrap();
 
-    assert_ne!(dataset.id, 0);
-    for key_value in &[
-        ("id", "integer"),
-        ("merchant_name", "string"),
-        ("mrr_cents", "integer"),
-        ("churn_rate", "float"),
-        ("subs_gained", "integer"),
-        ("subs_lost", "integer"),
-    ] {
-        assert_eq!(
-            dataset.schema.get(key_value.0).unwrap(),
-            &Some(key_value.1.to_owned())
-        );
-    }
+    // only the owner maintains a new dataset
+    assert!(dataset
+        .is_maintained_by(&mut test_db.db, &owner)
+        .await
+        .unwrap());
+    assert!(!dataset
+        .is_maintained_by(&mut test_db.db, &maintainer)
+        .await
+        .unwrap());
 
-    // find the same dataset in the database and check its values
+    // add a co-maintainer, which can then update the dataset and sees it in its datasets
+    let added = dataset
+        .add_maintainer(&mut test_db.db, &maintainer)
+        .await
+        .unwrap();
+    assert_eq!(added.manager_id, maintainer.id);
+    assert_eq!(added.manager_email, maintainer.email);
+    assert!(dataset
+        .add_maintainer(&mut test_db.db, &owner)
+        .await
+        .is_err());
+    let maintainers = dataset.maintainers(&mut test_db.db).await.unwrap();
+    assert_eq!(maintainers.len(), 1);
+    assert!(dataset
+        .is_maintained_by(&mut test_db.db, &maintainer)
+        .await
+        .unwrap());
+    let maintained = maintainer.datasets(&mut test_db.db).await.unwrap();
+    assert_eq!(maintained.len(), 1);
+    assert_eq!(maintained.first().unwrap().id, dataset.id);
+
+    let mut schema = testutil::rand_schema();
+    schema.insert("region".into(), Some("string".into()));
+    let updated = dataset
+        .update(&mut test_db.db, "updated description", schema.clone())
+        .await
+        .unwrap();
+    assert_eq!(updated.id, dataset.id);
+    assert_eq!(updated.description, "updated description");
+    assert_eq!(updated.schema, schema);
+
+    // request an ownership transfer, which is visible to both managers
+    assert!(dataset
+        .request_transfer(&mut test_db.db, &owner)
+        .await
+        .is_err());
+    let transfer = dataset
+        .request_transfer(&mut test_db.db, &maintainer)
+        .await
+        .unwrap();
+    assert_eq!(transfer.from_manager_id, owner.id);
+    assert_eq!(transfer.to_manager_id, maintainer.id);
+    assert_eq!(transfer.dataset_name, dataset.name);
+
+    // a new request replaces the pending transfer
+    let transfer = dataset
+        .request_transfer(&mut test_db.db, &recipient)
+        .await
+        .unwrap();
+    assert_eq!(transfer.to_manager_id, recipient.id);
+    assert_eq!(owner.transfers(&mut test_db.db).await.unwrap().len(), 1);
+    assert_eq!(recipient.transfers(&mut test_db.db).await.unwrap().len(), 1);
+    assert_eq!(
+        maintainer.transfers(&mut test_db.db).await.unwrap().len(),
+        0
+    );
+    let pending = dataset
+        .pending_transfer(&mut test_db.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(pending.id, transfer.id);
+
+    // cancel the transfer, and expect ownership to be unchanged
+    transfer.cancel(&mut test_db.db).await.unwrap();
+    assert!(dataset
+        .pending_transfer(&mut test_db.db)
+        .await
+        .unwrap()
+        .is_none());
     let found = Dataset::find(&mut test_db.db, &dataset.name).await.unwrap();
-    assert_eq!(found.name, dataset.name);
-    assert_eq!(found.id, dataset.id);
-    assert_eq!(found.schema, dataset.schema);
+    assert_eq!(found.manager_id, owner.id);
+
+    // accept a transfer to the co-maintainer, which is no longer a co-maintainer once it owns
+    // the dataset
+    let transfer = dataset
+        .request_transfer(&mut test_db.db, &maintainer)
+        .await
+        .unwrap();
+    let transferred = transfer.accept(&mut test_db.db).await.unwrap();
+    assert_eq!(transferred.id, dataset.id);
+    assert_eq!(transferred.manager_id, maintainer.id);
+    assert_eq!(transferred.manager_email, maintainer.email);
+    assert_eq!(
+        transferred
+            .maintainers(&mut test_db.db)
+            .await
+            .unwrap()
+            .len(),
+        0
+    );
+    assert_eq!(owner.datasets(&mut test_db.db).await.unwrap().len(), 0);
+    assert_eq!(maintainer.datasets(&mut test_db.db).await.unwrap().len(), 1);
+    assert!(transferred
+        .pending_transfer(&mut test_db.db)
+        .await
+        .unwrap()
+        .is_none());
+
+    // a pending transfer can't be accepted once its sender no longer owns the dataset
+    let stale = transferred
+        .request_transfer(&mut test_db.db, &recipient)
+        .await
+        .unwrap();
+    maintainer
+        .transfer_datasets(&mut test_db.db, &owner)
+        .await
+        .unwrap();
+    assert!(transferred
+        .pending_transfer(&mut test_db.db)
+        .await
+        .unwrap()
+        .is_none());
+    let err = stale.accept(&mut test_db.db).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Conflict);
+    let found = Dataset::find(&mut test_db.db, &dataset.name).await.unwrap();
+    assert_eq!(found.manager_id, owner.id);
+
+    // remove a co-maintainer
+    transferred
+        .add_maintainer(&mut test_db.db, &recipient)
+        .await
+        .unwrap();
+    transferred
+        .remove_maintainer(&mut test_db.db, recipient.id)
+        .await
+        .unwrap();
+    assert_eq!(
+        transferred
+            .maintainers(&mut test_db.db)
+            .await
+            .unwrap()
+            .len(),
+        0
+    );
 
     testutil::drop_test_db(test_db).await.unwrap();
 }
 
 #[tokio::test]
-async fn test_dataset_search() {
+async fn test_audit_events() {
     let mut test_db = testutil::new_test_db().await.unwrap();
     let manager = testutil::create_manager(&mut test_db).await.unwrap();
-    let known_names = &["dataset_1_teamA", "dataset_2_teamA", "dataset_1_teamB"];
-    for name in known_names {
-        manager
-            .register_dataset(
-                &mut test_db.db,
-                name,
-                Compression::Uncompressed,
-                Format::PlainText,
-                Classification::Public,
-                testutil::rand_schema(),
-                testutil::get_rand(String(50)),
+
+    // mutations made on behalf of a manager are attributed to it
+    let mut manager_db = test_db.db.with_actor(Actor::from(&manager));
+    let dataset = manager
+        .register_dataset(
+            &mut manager_db,
+            testutil::get_rand(String(20)),
+            Compression::Gzip,
+            Format::Json,
+            Classification::Confidential,
+            testutil::rand_schema(),
+            testutil::get_rand(String(40)),
+        )
+        .await
+        .unwrap();
+
+    // partitions are typically registered from pubsub notifications
+    let mut pubsub_db = test_db.db.with_actor(Actor::Pubsub {
+        message_id: "123456".into(),
+    });
+    let partition_name = testutil::get_rand(PartitionName(Format::Json, Compression::Gzip));
+    for size in &[100, 200] {
+        dataset
+            .register_partition(
+                &mut pubsub_db,
+                partition_name.clone(),
+                testutil::get_rand(PartitionUrl(
+                    Format::Json,
+                    Compression::Gzip,
+                    Classification::Confidential,
+                )),
+                *size,
             )
             .await
             .unwrap();
     }
+    dataset
+        .delete_partition(&mut pubsub_db, &partition_name)
+        .await
+        .unwrap();
+    // deleting a partition which no longer exists is not recorded
+    dataset
+        .delete_partition(&mut pubsub_db, &partition_name)
+        .await
+        .unwrap();
+    manager_db.delete_dataset(&dataset).await.unwrap();
 
-    let tests = &[("team", 3), ("teamA", 2), ("teamB", 1), ("no_results", 0)];
-    for t in tests {
-        let matches = Dataset::search(&mut test_db.db, t.0).await.unwrap();
-        assert_eq!(matches.len(), t.1);
+    let events = AuditEvent::list(
+        &mut test_db.db,
+        &AuditFilter {
+            target: Some(dataset.name.clone()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].action, "delete_dataset");
+    assert_eq!(events[0].actor, Actor::from(&manager));
+    let before = events[0].before.as_ref().unwrap();
+    assert_eq!(before["name"], dataset.name.as_str());
+    assert_eq!(before["partition_count"], 0);
+    assert!(events[0].after.is_none());
+    assert_eq!(events[1].action, "register_dataset");
+    assert!(events[1].before.is_none());
+    assert_eq!(events[1].after.as_ref().unwrap()["id"], dataset.id);
+
+    let events = AuditEvent::list(
+        &mut test_db.db,
+        &AuditFilter {
+            actor_type: Some("pubsub".into()),
+            target_type: Some("partition".into()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    let actions = events.iter().map(|e| e.action.as_str()).collect::<Vec<_>>();
+    assert_eq!(
+        actions,
+        vec![
+            "delete_partition",
+            "register_partition",
+            "register_partition"
+        ]
+    );
+    for event in &events {
+        assert_eq!(
+            event.actor,
+            Actor::Pubsub {
+                message_id: "123456".into()
+            }
+        );
+        assert_eq!(event.target, format!("{}/{}", dataset.name, partition_name));
     }
+    // overwriting a partition records its previous state
+    assert_eq!(events[1].before.as_ref().unwrap()["partition_size"], 100);
+    assert_eq!(events[1].after.as_ref().unwrap()["partition_size"], 200);
+    assert!(events[2].before.is_none());
+
+    // manager snapshots never include the api key
+    let events = AuditEvent::list(
+        &mut test_db.db,
+        &AuditFilter {
+            action: Some("register_manager".into()),
+            target: Some(manager.email.clone()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].actor, Actor::System);
+    let after = events[0].after.as_ref().unwrap();
+    assert_eq!(after["email"], manager.email.as_str());
+    assert!(after.get("api_key").is_none());
+
+    // pagination and time range
+    let page = AuditEvent::list(
+        &mut test_db.db,
+        &AuditFilter {
+            offset: Some(1),
+            count: Some(2),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(page.len(), 2);
+    assert_eq!(page[0].action, "delete_partition");
+    let future = AuditEvent::list(
+        &mut test_db.db,
+        &AuditFilter {
+            start: Some(Utc::now() + chrono::Duration::hours(1)),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert!(future.is_empty());
+
+    // audit events are append-only
+    let conn = test_db.db.client.get().await.unwrap();
+    assert!(conn
+        .execute("UPDATE audit_events SET event_action = 'changed'", &[])
+        .await
+        .is_err());
+    assert!(conn.execute("DELETE FROM audit_events", &[]).await.is_err());
+    drop(conn);
+
     testutil::drop_test_db(test_db).await.unwrap();
 }
 
 #[tokio::test]
-async fn test_attributes() {
+async fn test_soft_delete_restore_and_purge() {
     let mut test_db = testutil::new_test_db().await.unwrap();
-    let attrs = Attributes::list(&mut test_db.db).await.unwrap();
-    for expected in &[
-        "protobuf",
-        "ndjson",
-        "internal",
-        "uncompressed",
-        "confidential",
-    ] {
-        assert!(serde_json::to_string(&attrs).unwrap().contains(expected))
+    let manager = testutil::create_manager(&mut test_db).await.unwrap();
+    let mut datasets = vec![];
+    for _ in 0..2 {
+        let dataset = manager
+            .register_dataset(
+                &mut test_db.db,
+                testutil::get_rand(String(20)),
+                Compression::Gzip,
+                Format::Json,
+                Classification::Internal,
+                testutil::rand_schema(),
+                testutil::get_rand(String(40)),
+            )
+            .await
+            .unwrap();
+        for name in &["2020/01/01.json.gz", "2020/01/02.json.gz"] {
+            dataset
+                .register_partition(&mut test_db.db, name, testutil::get_rand(String(40)), 10)
+                .await
+                .unwrap();
+        }
+        datasets.push(dataset);
     }
+    let (kept, dataset) = (datasets.remove(0), datasets.remove(0));
+
+    // a deleted partition is hidden, but can be restored
+    dataset
+        .delete_partition(&mut test_db.db, "2020/01/01.json.gz")
+        .await
+        .unwrap();
+    assert!(dataset
+        .partition(&mut test_db.db, "2020/01/01.json.gz")
+        .await
+        .is_err());
+    assert_eq!(
+        dataset
+            .partitions(&mut test_db.db, None)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    // a deleted dataset is hidden from every lookup, along with its partitions
+    let name = dataset.name.clone();
+    dataset.delete(&mut test_db.db).await.unwrap();
+    assert!(Dataset::find(&mut test_db.db, &name).await.is_err());
+    let params = SearchParams {
+        term: Some(name.clone()),
+        ..Default::default()
+    };
+    assert!(Dataset::search(&mut test_db.db, &params)
+        .await
+        .unwrap()
+        .datasets
+        .is_empty());
+    let listed = Dataset::list(&mut test_db.db, None).await.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].id, kept.id);
+    let managed = manager.datasets(&mut test_db.db).await.unwrap();
+    assert_eq!(managed.len(), 1);
+    assert_eq!(managed[0].id, kept.id);
+
+    // restoring the dataset only restores the partitions deleted along with it
+    let deleted = Dataset::find_deleted(&mut test_db.db, &name).await.unwrap();
+    assert_eq!(deleted.manager_email, manager.email);
+    let restored = deleted.restore(&mut test_db.db).await.unwrap();
+    assert_eq!(restored.name, name);
+    assert!(Dataset::find_deleted(&mut test_db.db, &name).await.is_err());
+    let partitions = restored.partitions(&mut test_db.db, None).await.unwrap();
+    assert_eq!(partitions.len(), 1);
+    assert_eq!(partitions[0].name, "2020/01/02.json.gz");
+    let partition = restored
+        .restore_partition(&mut test_db.db, "2020/01/01.json.gz")
+        .await
+        .unwrap();
+    assert_eq!(partition.name, "2020/01/01.json.gz");
+    let err = restored
+        .restore_partition(&mut test_db.db, "2020/01/01.json.gz")
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    // registering a deleted partition again also restores it
+    restored
+        .delete_partition(&mut test_db.db, "2020/01/01.json.gz")
+        .await
+        .unwrap();
+    restored
+        .register_partition(
+            &mut test_db.db,
+            "2020/01/01.json.gz",
+            testutil::get_rand(String(40)),
+            20,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        restored
+            .partition(&mut test_db.db, "2020/01/01.json.gz")
+            .await
+            .unwrap()
+            .size,
+        20
+    );
+
+    // rows are only purged after the retention period has passed
+    restored.delete(&mut test_db.db).await.unwrap();
+    let purged = Dataset::purge_deleted(&mut test_db.db, chrono::Duration::days(1))
+        .await
+        .unwrap();
+    assert_eq!(purged, Purged::default());
+    assert!(Dataset::find_deleted(&mut test_db.db, &name).await.is_ok());
+
+    let purged = Dataset::purge_deleted(&mut test_db.db, chrono::Duration::zero())
+        .await
+        .unwrap();
+    assert_eq!(
+        purged,
+        Purged {
+            datasets: 1,
+            partitions: 2
+        }
+    );
+    assert!(Dataset::find_deleted(&mut test_db.db, &name).await.is_err());
+    assert_eq!(
+        kept.partitions(&mut test_db.db, None).await.unwrap().len(),
+        2
+    );
+
+    let events = AuditEvent::list(
+        &mut test_db.db,
+        &AuditFilter {
+            action: Some("purge_dataset".into()),
+            target: Some(name),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].actor, Actor::System);
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_dataset_from_config() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+    let manager = testutil::create_manager(&mut test_db).await.unwrap();
+    let dd: &str = include_str!("json/dd.json");
+    let config: DatasetConfig = serde_json::from_str(dd).unwrap();
+    let dataset = manager
+        .register_dataset(
+            &mut test_db.db,
+            &config.name,
+            config.compression.clone(),
+            config.format.clone(),
+            config.classification.clone(),
+            config.schema.clone(),
+            &config.description,
+        )
+        .await
+        .unwrap();
+
+    assert_ne!(dataset.id, 0);
+    assert_eq!(config.labels["team"], "growth");
+    for key_value in &[
+        ("id", "integer"),
+        ("merchant_name", "string"),
+        ("mrr_cents", "integer"),
+        ("churn_rate", "float"),
+        ("subs_gained", "integer"),
+        ("subs_lost", "integer"),
+    ] {
+        assert_eq!(
+            dataset.schema.get(key_value.0).unwrap(),
+            &Some(key_value.1.to_owned())
+        );
+    }
+
+    // find the same dataset in the database and check its values
+    let found = Dataset::find(&mut test_db.db, &dataset.name).await.unwrap();
+    assert_eq!(found.name, dataset.name);
+    assert_eq!(found.id, dataset.id);
+    assert_eq!(found.schema, dataset.schema);
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_dataset_search() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+    let manager = testutil::create_manager(&mut test_db).await.unwrap();
+    let known_names = &["dataset_1_teamA", "dataset_2_teamA", "dataset_1_teamB"];
+    for name in known_names {
+        manager
+            .register_dataset(
+                &mut test_db.db,
+                name,
+                Compression::Uncompressed,
+                Format::PlainText,
+                Classification::Public,
+                testutil::rand_schema(),
+                testutil::get_rand(String(50)),
+            )
+            .await
+            .unwrap();
+    }
+
+    let tests = &[("team", 3), ("teamA", 2), ("teamB", 1), ("no_results", 0)];
+    for t in tests {
+        let params = SearchParams {
+            term: Some(t.0.into()),
+            ..Default::default()
+        };
+        let matches = Dataset::search(&mut test_db.db, &params).await.unwrap();
+        assert_eq!(matches.datasets.len(), t.1);
+        assert_eq!(matches.total, t.1 as i64);
+    }
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_dataset_full_text_search() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+    let manager = testutil::create_manager(&mut test_db).await.unwrap();
+    let other_manager = testutil::create_manager(&mut test_db).await.unwrap();
+    let datasets = &[
+        (
+            &manager,
+            "merchant_revenue",
+            "Monthly recurring revenue per merchant",
+            &[("mrr_cents", "integer"), ("merchant_id", "string")],
+            Classification::Confidential,
+            Format::Json,
+        ),
+        (
+            &manager,
+            "subscriber_churn",
+            "Churned subscribers for every merchant, by day",
+            &[("churn_rate", "float"), ("churned_on", "timestamp")],
+            Classification::Internal,
+            Format::Csv,
+        ),
+        (
+            &other_manager,
+            "plan_catalog",
+            "Plans offered to subscribers",
+            &[("plan_code", "string"), ("price_cents", "integer")],
+            Classification::Public,
+            Format::Json,
+        ),
+    ];
+    for (owner, name, description, fields, classification, format) in datasets {
+        owner
+            .register_dataset(
+                &mut test_db.db,
+                *name,
+                Compression::Gzip,
+                format.clone(),
+                classification.clone(),
+                fields
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), Some(v.to_string())))
+                    .collect(),
+                *description,
+            )
+            .await
+            .unwrap();
+    }
+
+    let search = |term: &str| SearchParams {
+        term: Some(term.into()),
+        ..Default::default()
+    };
+    let names = |results: &SearchResults| {
+        results
+            .datasets
+            .iter()
+            .map(|d| d.name.clone())
+            .collect::<Vec<_>>()
+    };
+
+    // descriptions, schema field names and types are matched by word and word prefix
+    let tests: &[(&str, &[&str])] = &[
+        ("recurring", &["merchant_revenue"]),
+        ("churned", &["subscriber_churn"]),
+        ("subscriber", &["subscriber_churn", "plan_catalog"]),
+        ("price", &["plan_catalog"]),
+        ("timestamp", &["subscriber_churn"]),
+        ("merch", &["merchant_revenue", "subscriber_churn"]),
+        ("merchant churn", &["subscriber_churn"]),
+        ("no_results", &[]),
+        // LIKE wildcards in a term only match themselves
+        ("%", &[]),
+    ];
+    for (term, expected) in tests {
+        let mut found = names(
+            &Dataset::search(&mut test_db.db, &search(term))
+                .await
+                .unwrap(),
+        );
+        found.sort();
+        let mut expected = expected.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(found, expected, "term: {}", term);
+    }
+
+    // matches in a dataset's name rank above matches in its description
+    let results = Dataset::search(&mut test_db.db, &search("merchant"))
+        .await
+        .unwrap();
+    assert_eq!(
+        names(&results),
+        vec!["merchant_revenue", "subscriber_churn"]
+    );
+
+    // facets count every match, regardless of the page returned
+    let results = Dataset::search(
+        &mut test_db.db,
+        &SearchParams {
+            count: Some(1),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(results.datasets.len(), 1);
+    assert_eq!(results.total, 3);
+    assert_eq!(results.facets.format.get("json"), Some(&2));
+    assert_eq!(results.facets.format.get("csv"), Some(&1));
+    assert_eq!(results.facets.compression.get("gzip"), Some(&3));
+    assert_eq!(results.facets.classification.get("public"), Some(&1));
+    assert_eq!(results.facets.manager.get(&manager.email), Some(&2));
+    assert_eq!(results.facets.manager.get(&other_manager.email), Some(&1));
+
+    // filters narrow down both the matches and their facets
+    let results = Dataset::search(
+        &mut test_db.db,
+        &SearchParams {
+            term: Some("subscribers".into()),
+            format: Some(Format::Json),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(names(&results), vec!["plan_catalog"]);
+    assert_eq!(results.facets.manager.get(&other_manager.email), Some(&1));
+    assert!(!results.facets.manager.contains_key(&manager.email));
+
+    let results = Dataset::search(
+        &mut test_db.db,
+        &SearchParams {
+            manager_email: Some(manager.email.clone()),
+            classification: Some(Classification::Internal),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(names(&results), vec!["subscriber_churn"]);
+
+    let results = Dataset::search(
+        &mut test_db.db,
+        &SearchParams {
+            updated_after: Some(Utc::now() + chrono::Duration::hours(1)),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(results.total, 0);
+    assert!(results.facets.classification.is_empty());
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_find_schema_fields() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+    let manager = testutil::create_manager(&mut test_db).await.unwrap();
+    let schema = |fields: &[(&str, &str)]| -> DatasetSchema {
+        fields
+            .iter()
+            .map(|(k, v)| (k.to_string(), Some(v.to_string())))
+            .collect()
+    };
+
+    let customers = manager
+        .register_dataset(
+            &mut test_db.db,
+            "customers",
+            Compression::Gzip,
+            Format::Json,
+            Classification::Confidential,
+            schema(&[("merchant_id", "string"), ("email", "string")]),
+            "Customers of every merchant",
+        )
+        .await
+        .unwrap();
+    manager
+        .register_dataset(
+            &mut test_db.db,
+            "merchants",
+            Compression::Gzip,
+            Format::Json,
+            Classification::Internal,
+            schema(&[("merchant_id", "integer"), ("billing_email", "string")]),
+            "Merchants",
+        )
+        .await
+        .unwrap();
+    manager
+        .register_dataset(
+            &mut test_db.db,
+            "merchantXid",
+            Compression::Gzip,
+            Format::Json,
+            Classification::Public,
+            schema(&[("merchantXid", "string")]),
+            "Not a match for an exact field name",
+        )
+        .await
+        .unwrap();
+
+    // the email field is removed from the current schema, but remains in its first version
+    customers
+        .update(
+            &mut test_db.db,
+            &customers.description,
+            schema(&[("merchant_id", "string"), ("email_hash", "string")]),
+        )
+        .await
+        .unwrap();
+    // updating the description alone does not create a new schema version
+    customers
+        .update(
+            &mut test_db.db,
+            "Customers of each merchant",
+            schema(&[("merchant_id", "string"), ("email_hash", "string")]),
+        )
+        .await
+        .unwrap();
+
+    let find = |name: Option<&str>, field_type: Option<&str>, all_versions: bool| FieldSearch {
+        name: name.map(|n| n.to_string()),
+        field_type: field_type.map(|t| t.to_string()),
+        all_versions,
+    };
+    // dataset name, schema version and field name of each expected match, in order
+    type FieldMatch = (&'static str, i32, &'static str);
+    let tests: &[(FieldSearch, &[FieldMatch])] = &[
+        (
+            find(Some("merchant_id"), None, false),
+            &[
+                ("customers", 2, "merchant_id"),
+                ("merchants", 1, "merchant_id"),
+            ],
+        ),
+        (
+            find(Some("Merchant_ID"), Some("INTEGER"), false),
+            &[("merchants", 1, "merchant_id")],
+        ),
+        (
+            find(Some("*email*"), None, false),
+            &[
+                ("customers", 2, "email_hash"),
+                ("merchants", 1, "billing_email"),
+            ],
+        ),
+        (find(Some("email"), None, false), &[]),
+        (
+            find(Some("email"), None, true),
+            &[("customers", 1, "email")],
+        ),
+        (
+            find(Some("email*"), Some("string"), true),
+            &[("customers", 2, "email_hash"), ("customers", 1, "email")],
+        ),
+        (
+            find(Some("merchant?id"), None, false),
+            &[
+                ("customers", 2, "merchant_id"),
+                ("merchantXid", 1, "merchantXid"),
+                ("merchants", 1, "merchant_id"),
+            ],
+        ),
+    ];
+    for (search, expected) in tests {
+        let found = Dataset::find_fields(&mut test_db.db, search)
+            .await
+            .unwrap()
+            .iter()
+            .map(|f| (f.dataset_name.clone(), f.schema_version, f.name.clone()))
+            .collect::<Vec<_>>();
+        let expected = expected
+            .iter()
+            .map(|(d, v, n)| (d.to_string(), *v, n.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(found, expected, "search: {:?}", search);
+    }
+
+    let fields = Dataset::find_fields(&mut test_db.db, &find(Some("merchant_id"), None, true))
+        .await
+        .unwrap();
+    assert_eq!(fields.len(), 3);
+    assert!(fields[0].latest);
+    assert!(!fields[1].latest);
+    assert_eq!(fields[0].field_type, Some("string".into()));
+
+    // deleted datasets are not searched
+    customers.delete(&mut test_db.db).await.unwrap();
+    let fields = Dataset::find_fields(&mut test_db.db, &find(Some("merchant_id"), None, true))
+        .await
+        .unwrap();
+    assert_eq!(fields.len(), 1);
+    assert_eq!(fields[0].dataset_name, "merchants");
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_dataset_labels() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+    let manager = testutil::create_manager(&mut test_db).await.unwrap();
+    let labels = |pairs: &[(&str, &str)]| {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<DatasetLabels>()
+    };
+
+    let mut datasets = vec![];
+    for (name, dataset_labels) in &[
+        (
+            "billing_invoices",
+            labels(&[("team", "billing"), ("tier", "1")]),
+        ),
+        (
+            "billing_refunds",
+            labels(&[("team", "billing"), ("tier", "2")]),
+        ),
+        (
+            "growth_signups",
+            labels(&[("team", "growth"), ("tier", "1")]),
+        ),
+        ("unlabeled", labels(&[])),
+    ] {
+        let dataset = manager
+            .register_dataset(
+                &mut test_db.db,
+                *name,
+                Compression::Gzip,
+                Format::Json,
+                Classification::Internal,
+                DatasetSchema::new(),
+                "labeled dataset",
+            )
+            .await
+            .unwrap();
+        assert!(dataset.labels.is_empty());
+        let dataset = dataset
+            .set_labels(&mut test_db.db, dataset_labels)
+            .await
+            .unwrap();
+        assert_eq!(&dataset.labels, dataset_labels);
+        datasets.push(dataset);
+    }
+
+    // labels are returned with the dataset
+    let found = Dataset::find(&mut test_db.db, "billing_refunds")
+        .await
+        .unwrap();
+    assert_eq!(found.labels, labels(&[("team", "billing"), ("tier", "2")]));
+
+    // datasets must have every label in the selector
+    let names = |datasets: Vec<Dataset>| {
+        let mut names = datasets.into_iter().map(|d| d.name).collect::<Vec<_>>();
+        names.sort();
+        names
+    };
+    type Selector = &'static [(&'static str, &'static str)];
+    let tests: &[(Selector, &[&str])] = &[
+        (
+            &[("team", "billing")],
+            &["billing_invoices", "billing_refunds"],
+        ),
+        (&[("tier", "1")], &["billing_invoices", "growth_signups"]),
+        (&[("team", "billing"), ("tier", "1")], &["billing_invoices"]),
+        (&[("team", "growth"), ("tier", "2")], &[]),
+        (&[("owner", "billing")], &[]),
+    ];
+    for (selector, expected) in tests {
+        let listed = Dataset::list_labeled(&mut test_db.db, &labels(selector), None)
+            .await
+            .unwrap();
+        assert_eq!(names(listed), *expected, "selector: {:?}", selector);
+
+        let results = Dataset::search(
+            &mut test_db.db,
+            &SearchParams {
+                labels: Some(labels(selector)),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(results.total, expected.len() as i64);
+        assert_eq!(
+            names(results.datasets),
+            *expected,
+            "selector: {:?}",
+            selector
+        );
+    }
+
+    // labeled range queries are paginated like any other dataset list
+    let listed = Dataset::list_labeled(
+        &mut test_db.db,
+        &labels(&[("team", "billing")]),
+        Some(RangeParams {
+            start: None,
+            end: None,
+            count: Some(1),
+            offset: Some(1),
+            ..Default::default()
+        }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(listed.len(), 1);
+
+    // replacing labels removes any which are not provided
+    let updated = datasets[0]
+        .set_labels(&mut test_db.db, &labels(&[("team", "finance")]))
+        .await
+        .unwrap();
+    assert_eq!(updated.labels, labels(&[("team", "finance")]));
+    let listed = Dataset::list_labeled(&mut test_db.db, &labels(&[("tier", "1")]), None)
+        .await
+        .unwrap();
+    assert_eq!(names(listed), &["growth_signups"]);
+
+    // invalid labels are rejected
+    let long_key = "k".repeat(64);
+    assert!(datasets[1]
+        .set_labels(&mut test_db.db, &labels(&[(&long_key, "v")]))
+        .await
+        .is_err());
+    assert!(datasets[1]
+        .set_labels(&mut test_db.db, &labels(&[("", "v")]))
+        .await
+        .is_err());
+
+    let events = AuditEvent::list(
+        &mut test_db.db,
+        &AuditFilter {
+            action: Some("update_dataset_labels".into()),
+            target: Some("billing_invoices".into()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].before.as_ref().unwrap()["labels"]["tier"], "1");
+    assert_eq!(
+        events[0].after.as_ref().unwrap()["labels"]["team"],
+        "finance"
+    );
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_dataset_field_sensitivity() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+    let manager = testutil::create_manager(&mut test_db).await.unwrap();
+    let schema = |fields: &[&str]| {
+        fields
+            .iter()
+            .map(|field| (field.to_string(), Some("string".to_string())))
+            .collect::<DatasetSchema>()
+    };
+    let sensitivity = |fields: &[(&str, Sensitivity)]| {
+        fields
+            .iter()
+            .map(|(field, kind)| (field.to_string(), *kind))
+            .collect::<DatasetSensitivity>()
+    };
+
+    let mut datasets = vec![];
+    for (name, classification) in &[
+        ("customers", Classification::Confidential),
+        ("payments", Classification::Restricted),
+        ("page_views", Classification::Internal),
+    ] {
+        let dataset = manager
+            .register_dataset(
+                &mut test_db.db,
+                *name,
+                Compression::Gzip,
+                Format::Json,
+                classification.clone(),
+                schema(&["id", "email", "phone", "card_number"]),
+                "dataset with PII",
+            )
+            .await
+            .unwrap();
+        assert!(dataset.sensitivity.is_empty());
+        datasets.push(dataset);
+    }
+
+    let customers = datasets[0]
+        .set_sensitivity(
+            &mut test_db.db,
+            &sensitivity(&[("email", Sensitivity::Email), ("phone", Sensitivity::Phone)]),
+        )
+        .await
+        .unwrap();
+    assert_eq!(customers.sensitivity["email"], Sensitivity::Email);
+    let payments = datasets[1]
+        .set_sensitivity(
+            &mut test_db.db,
+            &sensitivity(&[("card_number", Sensitivity::Card)]),
+        )
+        .await
+        .unwrap();
+
+    // sensitivity is returned with the dataset
+    let found = Dataset::find(&mut test_db.db, "customers").await.unwrap();
+    assert_eq!(found.sensitivity, customers.sensitivity);
+
+    // the classification must be at least as strict as the most sensitive field
+    assert!(datasets[0]
+        .set_sensitivity(
+            &mut test_db.db,
+            &sensitivity(&[("card_number", Sensitivity::Card)]),
+        )
+        .await
+        .is_err());
+    assert!(datasets[2]
+        .set_sensitivity(
+            &mut test_db.db,
+            &sensitivity(&[("email", Sensitivity::Email)])
+        )
+        .await
+        .is_err());
+    // and only fields in the schema may be annotated
+    assert!(payments
+        .set_sensitivity(
+            &mut test_db.db,
+            &sensitivity(&[("billing_address", Sensitivity::Address)]),
+        )
+        .await
+        .is_err());
+    // nor may a schema update remove an annotated field
+    assert!(customers
+        .update(&mut test_db.db, "no more email", schema(&["id", "phone"]))
+        .await
+        .is_err());
+    customers
+        .update(
+            &mut test_db.db,
+            "no more card",
+            schema(&["id", "email", "phone"]),
+        )
+        .await
+        .unwrap();
+
+    let fields = |fields: Vec<PiiField>| {
+        fields
+            .into_iter()
+            .map(|f| (f.dataset_name, f.field_name, f.sensitivity))
+            .collect::<Vec<_>>()
+    };
+    let all = Dataset::list_pii_fields(&mut test_db.db, None)
+        .await
+        .unwrap();
+    assert_eq!(all[0].classification, Classification::Confidential);
+    assert_eq!(all[0].field_type.as_deref(), Some("string"));
+    assert_eq!(
+        fields(all),
+        vec![
+            ("customers".into(), "email".into(), Sensitivity::Email),
+            ("customers".into(), "phone".into(), Sensitivity::Phone),
+            ("payments".into(), "card_number".into(), Sensitivity::Card),
+        ]
+    );
+    let cards = Dataset::list_pii_fields(&mut test_db.db, Some(Sensitivity::Card))
+        .await
+        .unwrap();
+    assert_eq!(
+        fields(cards),
+        vec![("payments".into(), "card_number".into(), Sensitivity::Card)]
+    );
+
+    // deleted datasets are not part of the catalog
+    test_db.db.delete_dataset(&payments).await.unwrap();
+    let cards = Dataset::list_pii_fields(&mut test_db.db, Some(Sensitivity::Card))
+        .await
+        .unwrap();
+    assert!(cards.is_empty());
+
+    let events = AuditEvent::list(
+        &mut test_db.db,
+        &AuditFilter {
+            action: Some("update_dataset_sensitivity".into()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].target, "payments");
+    assert_eq!(
+        events[0].after.as_ref().unwrap()["sensitivity"]["card_number"],
+        "card"
+    );
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_partition_read_access() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+    let owner = testutil::create_manager(&mut test_db).await.unwrap();
+    let maintainer = testutil::create_manager(&mut test_db).await.unwrap();
+    let grantee = testutil::create_manager(&mut test_db).await.unwrap();
+    let other = testutil::create_manager(&mut test_db).await.unwrap();
+    let admin = testutil::create_manager(&mut test_db).await.unwrap();
+    let admin = admin.set_admin(&mut test_db.db, true).await.unwrap();
+
+    let mut datasets = vec![];
+    for classification in &[
+        Classification::Public,
+        Classification::Internal,
+        Classification::Confidential,
+        Classification::Restricted,
+    ] {
+        let dataset = owner
+            .register_dataset(
+                &mut test_db.db,
+                format!("{}_dataset", classification),
+                Compression::Gzip,
+                Format::Json,
+                classification.clone(),
+                DatasetSchema::new(),
+                "dataset readable by classification",
+            )
+            .await
+            .unwrap();
+        dataset
+            .add_maintainer(&mut test_db.db, &maintainer)
+            .await
+            .unwrap();
+        datasets.push(dataset);
+    }
+    let grant = datasets[3]
+        .grant_read(&mut test_db.db, &grantee)
+        .await
+        .unwrap();
+    assert_eq!(grant.manager_id, grantee.id);
+    assert_eq!(grant.manager_email, grantee.email);
+
+    // readable by: anonymous, owner, maintainer, admin, grantee, other
+    let tests: &[(Classification, [bool; 6])] = &[
+        (Classification::Public, [true, true, true, true, true, true]),
+        (
+            Classification::Internal,
+            [false, true, true, true, true, true],
+        ),
+        (
+            Classification::Confidential,
+            [false, true, true, true, false, false],
+        ),
+        (
+            Classification::Restricted,
+            [false, true, true, true, true, false],
+        ),
+    ];
+    for (dataset, (classification, expected)) in datasets.iter().zip(tests) {
+        assert_eq!(&dataset.classification, classification);
+        let readers = [
+            None,
+            Some(&owner),
+            Some(&maintainer),
+            Some(&admin),
+            Some(&grantee),
+            Some(&other),
+        ];
+        for (reader, readable) in readers.iter().zip(expected) {
+            assert_eq!(
+                dataset
+                    .is_readable_by(&mut test_db.db, *reader)
+                    .await
+                    .unwrap(),
+                *readable,
+                "{} dataset read by {:?}",
+                classification,
+                reader.map(|m| &m.email)
+            );
+        }
+    }
+
+    // revoking a grant removes read access
+    datasets[3]
+        .revoke_read(&mut test_db.db, grantee.id)
+        .await
+        .unwrap();
+    assert!(datasets[3]
+        .read_grants(&mut test_db.db)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(!datasets[3]
+        .is_readable_by(&mut test_db.db, Some(&grantee))
+        .await
+        .unwrap());
+
+    // denied reads are kept in the access log
+    for (manager_id, partition_name) in &[(None, None), (Some(other.id), Some("partition.json"))] {
+        AccessDenial {
+            id: 0,
+            manager_id: *manager_id,
+            remote_addr: Some("127.0.0.1".into()),
+            dataset_name: datasets[2].name.clone(),
+            partition_name: partition_name.map(|name| name.to_string()),
+            action: "find_partition".into(),
+            reason: "read access must be granted".into(),
+            created_at: Utc::now(),
+        }
+        .record(&mut test_db.db)
+        .await
+        .unwrap();
+    }
+    let denials = AccessDenial::list(
+        &mut test_db.db,
+        &AccessFilter {
+            dataset_name: Some(datasets[2].name.clone()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(denials.len(), 2);
+    assert_eq!(denials[0].manager_id, Some(other.id));
+    assert_eq!(denials[0].partition_name.as_deref(), Some("partition.json"));
+    assert_eq!(denials[1].manager_id, None);
+    assert_eq!(denials[1].remote_addr.as_deref(), Some("127.0.0.1"));
+    let denials = AccessDenial::list(
+        &mut test_db.db,
+        &AccessFilter {
+            manager_id: Some(other.id),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(denials.len(), 1);
+
+    let events = AuditEvent::list(
+        &mut test_db.db,
+        &AuditFilter {
+            target: Some(datasets[3].name.clone()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    let actions = events.iter().map(|e| e.action.as_str()).collect::<Vec<_>>();
+    assert_eq!(actions[..2], ["remove_read_grant", "add_read_grant"]);
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_attributes() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+    let attrs = Attributes::list(&mut test_db.db).await.unwrap();
+    for expected in &[
+        "protobuf",
+        "ndjson",
+        "internal",
+        "uncompressed",
+        "confidential",
+    ] {
+        assert!(serde_json::to_string(&attrs).unwrap().contains(expected))
+    }
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_partition_template() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+    let manager = testutil::create_manager(&mut test_db).await.unwrap();
+    let dataset = manager
+        .register_dataset(
+            &mut test_db.db,
+            "hourly_events",
+            Compression::Gzip,
+            Format::Json,
+            Classification::Internal,
+            DatasetSchema::new(),
+            "templated dataset",
+        )
+        .await
+        .unwrap();
+    assert_eq!(dataset.partition_template, None);
+
+    // without a template, any partition name is accepted and has an empty key
+    let partition = dataset
+        .register_partition(&mut test_db.db, "any/name", "gs://events/any/name", 10)
+        .await
+        .unwrap();
+    assert!(partition.key.is_empty());
+
+    assert!(dataset
+        .set_partition_template(&mut test_db.db, Some("{yyyy}/{mm}/{week}"))
+        .await
+        .is_err());
+    let template = "{yyyy}/{mm}/{dd}/{hh}/part-{shard}.{ext}";
+    let dataset = dataset
+        .set_partition_template(&mut test_db.db, Some(template))
+        .await
+        .unwrap();
+    assert_eq!(dataset.partition_template.as_deref(), Some(template));
+    let found = Dataset::find(&mut test_db.db, "hourly_events")
+        .await
+        .unwrap();
+    assert_eq!(found.partition_template.as_deref(), Some(template));
+
+    for name in &[
+        "2020/03/25/part-1.json.tar.gz",
+        "2020/03/25/07/part-1.csv",
+        "2020/02/30/07/part-1.json.tar.gz",
+    ] {
+        assert!(dataset
+            .register_partition(&mut test_db.db, name, "gs://events/bad", 10)
+            .await
+            .is_err());
+    }
+
+    let date = |y, m, d| Some(NaiveDate::from_ymd(y, m, d));
+    for (name, day, hour, shard) in &[
+        ("2020/03/25/07/part-0.json.tar.gz", 25, 7, 0),
+        ("2020/03/25/07/part-1.json.tar.gz", 25, 7, 1),
+        ("2020/03/25/08/part-0.json.tar.gz", 25, 8, 0),
+        ("2020/03/26/07/part-0.json.tar.gz", 26, 7, 0),
+    ] {
+        let partition = dataset
+            .register_partition(&mut test_db.db, name, format!("gs://events/{}", name), 10)
+            .await
+            .unwrap();
+        assert_eq!(
+            partition.key,
+            PartitionKey {
+                date: date(2020, 3, *day),
+                hour: Some(*hour),
+                shard: Some(*shard),
+            }
+        );
+    }
+
+    // keys are returned with the partition, and filter partition lists by every value set
+    let partition = dataset
+        .partition(&mut test_db.db, "2020/03/25/08/part-0.json.tar.gz")
+        .await
+        .unwrap();
+    assert_eq!(partition.key.hour, Some(8));
+
+    let names = |partitions: Vec<Partition>| {
+        let mut names = partitions.into_iter().map(|p| p.name).collect::<Vec<_>>();
+        names.sort();
+        names
+    };
+    let by_date = dataset
+        .partitions_by_key(
+            &mut test_db.db,
+            &PartitionKey {
+                date: date(2020, 3, 25),
+                ..Default::default()
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        names(by_date),
+        [
+            "2020/03/25/07/part-0.json.tar.gz",
+            "2020/03/25/07/part-1.json.tar.gz",
+            "2020/03/25/08/part-0.json.tar.gz",
+        ]
+    );
+    let by_hour_and_shard = dataset
+        .partitions_by_key(
+            &mut test_db.db,
+            &PartitionKey {
+                date: None,
+                hour: Some(7),
+                shard: Some(0),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        names(by_hour_and_shard),
+        [
+            "2020/03/25/07/part-0.json.tar.gz",
+            "2020/03/26/07/part-0.json.tar.gz",
+        ]
+    );
+    let limited = dataset
+        .partitions_by_key(
+            &mut test_db.db,
+            &PartitionKey {
+                date: date(2020, 3, 25),
+                ..Default::default()
+            },
+            Some(RangeParams {
+                count: Some(2),
+                ..Default::default()
+            }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(limited.len(), 2);
+    assert_eq!(
+        dataset
+            .partitions(&mut test_db.db, None)
+            .await
+            .unwrap()
+            .len(),
+        5
+    );
+
+    // removing the template accepts any name again, and existing keys are kept
+    let dataset = dataset
+        .set_partition_template(&mut test_db.db, None)
+        .await
+        .unwrap();
+    assert_eq!(dataset.partition_template, None);
+    assert!(dataset
+        .register_partition(&mut test_db.db, "other", "gs://events/other", 10)
+        .await
+        .unwrap()
+        .key
+        .is_empty());
+    let partition = dataset
+        .partition(&mut test_db.db, "2020/03/26/07/part-0.json.tar.gz")
+        .await
+        .unwrap();
+    assert_eq!(partition.key.date, date(2020, 3, 26));
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_partition_data_range() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+    let manager = testutil::create_manager(&mut test_db).await.unwrap();
+    let dataset = manager
+        .register_dataset(
+            &mut test_db.db,
+            "daily_orders",
+            Compression::Uncompressed,
+            Format::Csv,
+            Classification::Internal,
+            DatasetSchema::new(),
+            "backfilled dataset",
+        )
+        .await
+        .unwrap()
+        .set_partition_template(&mut test_db.db, Some("{yyyy}/{mm}/{dd}/{name}.{ext}"))
+        .await
+        .unwrap();
+    let at = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+
+    // the data range is based on the partition's name, regardless of when it is registered
+    for day in 1..=5 {
+        let name = format!("2019/01/0{}/orders.csv", day);
+        let partition = dataset
+            .register_partition(&mut test_db.db, &name, format!("gs://orders/{}", name), 10)
+            .await
+            .unwrap();
+        assert_eq!(
+            partition.data_start,
+            Some(at(&format!("2019-01-0{}T00:00:00Z", day)))
+        );
+        assert_eq!(
+            partition.data_end,
+            Some(at(&format!("2019-01-0{}T00:00:00Z", day + 1)))
+        );
+        assert!(partition.created_at > partition.data_end.unwrap());
+    }
+
+    // a data range provided explicitly, e.g. from object metadata, overrides the template's
+    let covering = DataRange {
+        start: at("2018-12-31T12:00:00Z"),
+        end: at("2019-01-01T12:00:00Z"),
+    };
+    let partition = dataset
+        .register_partition_covering(
+            &mut test_db.db,
+            "2019/01/01/late.csv",
+            "gs://orders/late",
+            10,
+            covering,
+        )
+        .await
+        .unwrap();
+    assert_eq!(partition.data_start, Some(covering.start));
+    assert_eq!(partition.data_end, Some(covering.end));
+    assert!(dataset
+        .register_partition_covering(
+            &mut test_db.db,
+            "2019/01/01/invalid.csv",
+            "gs://orders/invalid",
+            10,
+            DataRange {
+                start: covering.end,
+                end: covering.start,
+            },
+        )
+        .await
+        .is_err());
+
+    // partitions are matched when their data range overlaps the range requested
+    let names = |partitions: Vec<Partition>| {
+        let mut names = partitions.into_iter().map(|p| p.name).collect::<Vec<_>>();
+        names.sort();
+        names
+    };
+    let range = |start: Option<&str>, end: Option<&str>| {
+        Some(RangeParams {
+            data_start: start.map(at),
+            data_end: end.map(at),
+            ..Default::default()
+        })
+    };
+    let listed = dataset
+        .partitions(
+            &mut test_db.db,
+            range(Some("2019-01-02T00:00:00Z"), Some("2019-01-04T00:00:00Z")),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        names(listed),
+        ["2019/01/02/orders.csv", "2019/01/03/orders.csv"]
+    );
+    let listed = dataset
+        .partitions(&mut test_db.db, range(None, Some("2019-01-01T06:00:00Z")))
+        .await
+        .unwrap();
+    assert_eq!(
+        names(listed),
+        ["2019/01/01/late.csv", "2019/01/01/orders.csv"]
+    );
+    let listed = dataset
+        .partitions(&mut test_db.db, range(Some("2019-01-05T00:00:00Z"), None))
+        .await
+        .unwrap();
+    assert_eq!(names(listed), ["2019/01/05/orders.csv"]);
+
+    // data ranges combine with partition keys
+    let listed = dataset
+        .partitions_by_key(
+            &mut test_db.db,
+            &PartitionKey {
+                date: Some(NaiveDate::from_ymd(2019, 1, 1)),
+                ..Default::default()
+            },
+            range(Some("2018-12-31T00:00:00Z"), Some("2019-01-01T00:00:00Z")),
+        )
+        .await
+        .unwrap();
+    assert_eq!(names(listed), ["2019/01/01/late.csv"]);
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_pagination() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+    let manager = testutil::create_manager(&mut test_db).await.unwrap();
+    let mut labels = DatasetLabels::new();
+    labels.insert("team".to_string(), "paging".to_string());
+
+    for i in 0..5 {
+        let dataset = manager
+            .register_dataset(
+                &mut test_db.db,
+                format!("paged_dataset_{}", i),
+                Compression::Uncompressed,
+                Format::Csv,
+                Classification::Internal,
+                DatasetSchema::new(),
+                "paged dataset",
+            )
+            .await
+            .unwrap();
+        if i % 2 == 0 {
+            dataset.set_labels(&mut test_db.db, &labels).await.unwrap();
+        }
+    }
+
+    // following each page's cursor visits every dataset once, most recently created first, even
+    // when a dataset is updated by a partition registered between pages
+    let mut params = RangeParams {
+        count: Some(2),
+        ..Default::default()
+    };
+    let mut names = vec![];
+    loop {
+        let page = Dataset::page(&mut test_db.db, None, &params).await.unwrap();
+        assert_eq!(page.total, 5);
+        assert!(page.datasets.len() <= 2);
+        names.extend(page.datasets.into_iter().map(|d| d.name));
+        if names.len() == 2 {
+            Dataset::find(&mut test_db.db, "paged_dataset_1")
+                .await
+                .unwrap()
+                .register_partition(&mut test_db.db, "between_pages", "gs://paged/between", 10)
+                .await
+                .unwrap();
+        }
+        match page.next {
+            Some(next) => params.cursor = Some(Cursor::decode(next).unwrap()),
+            None => break,
+        }
+    }
+    assert_eq!(
+        names,
+        [
+            "paged_dataset_4",
+            "paged_dataset_3",
+            "paged_dataset_2",
+            "paged_dataset_1",
+            "paged_dataset_0",
+        ]
+    );
+
+    let page = Dataset::page(
+        &mut test_db.db,
+        Some(&labels),
+        &RangeParams {
+            count: Some(2),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(page.total, 3);
+    assert_eq!(page.datasets.len(), 2);
+    assert!(page.next.is_some());
+
+    let page = Dataset::page(
+        &mut test_db.db,
+        None,
+        &RangeParams {
+            end: Some(Utc::now() - chrono::Duration::days(1)),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(page.total, 0);
+    assert!(page.datasets.is_empty());
+    assert_eq!(page.next, None);
+
+    let dataset = Dataset::find(&mut test_db.db, "paged_dataset_0")
+        .await
+        .unwrap();
+    for i in 0..5 {
+        let name = format!("part_{}", i);
+        dataset
+            .register_partition(&mut test_db.db, &name, format!("gs://paged/{}", name), 10)
+            .await
+            .unwrap();
+    }
+
+    let params = RangeParams {
+        count: Some(3),
+        ..Default::default()
+    };
+    let key = PartitionKey::default();
+    let first = dataset
+        .partitions_page(&mut test_db.db, &key, &params)
+        .await
+        .unwrap();
+    assert_eq!(first.total, 5);
+    let names = |page: &[Partition]| page.iter().map(|p| p.name.clone()).collect::<Vec<_>>();
+    assert_eq!(names(&first.partitions), ["part_4", "part_3", "part_2"]);
+
+    // partitions registered between pages don't shift the following pages
+    dataset
+        .register_partition(&mut test_db.db, "part_5", "gs://paged/part_5", 10)
+        .await
+        .unwrap();
+    let second = dataset
+        .partitions_page(
+            &mut test_db.db,
+            &key,
+            &RangeParams {
+                cursor: Some(Cursor::decode(first.next.unwrap()).unwrap()),
+                ..params
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(second.total, 6);
+    assert_eq!(names(&second.partitions), ["part_1", "part_0"]);
+    assert_eq!(second.next, None);
+
+    let start = second.partitions[0].created_at;
+    let ranged = dataset
+        .partitions_page(
+            &mut test_db.db,
+            &key,
+            &RangeParams {
+                start: Some(start),
+                offset: Some(1),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(ranged.total, 5);
+    assert_eq!(
+        names(&ranged.partitions),
+        ["part_4", "part_3", "part_2", "part_1"]
+    );
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_filter_and_sort() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+
+    let manager = testutil::create_manager(&mut test_db).await.unwrap();
+    for (name, classification) in &[
+        ("sorted_b", Classification::Internal),
+        ("sorted_a", Classification::Public),
+        ("sorted_c", Classification::Internal),
+        ("other_d", Classification::Internal),
+    ] {
+        manager
+            .register_dataset(
+                &mut test_db.db,
+                name,
+                Compression::Uncompressed,
+                Format::Csv,
+                classification.clone(),
+                DatasetSchema::new(),
+                "sorted dataset",
+            )
+            .await
+            .unwrap();
+    }
+
+    // following each page's cursor keeps the sort which the first page was requested with
+    let filter = DatasetFilter {
+        name_prefix: Some("sorted_".to_string()),
+        ..Default::default()
+    };
+    let mut params = RangeParams {
+        count: Some(2),
+        sort: Some(SortKey::Name),
+        ..Default::default()
+    };
+    let mut names = vec![];
+    loop {
+        let page = Dataset::page_filtered(&mut test_db.db, &filter, &params)
+            .await
+            .unwrap();
+        assert_eq!(page.total, 3);
+        names.extend(page.datasets.into_iter().map(|d| d.name));
+        match page.next {
+            Some(next) => {
+                params = RangeParams {
+                    count: Some(2),
+                    cursor: Some(Cursor::decode(next).unwrap()),
+                    ..Default::default()
+                }
+            }
+            None => break,
+        }
+    }
+    assert_eq!(names, ["sorted_a", "sorted_b", "sorted_c"]);
+
+    let filter = DatasetFilter {
+        classification: Some(Classification::Internal),
+        manager_email: Some(manager.email.clone()),
+        ..Default::default()
+    };
+    let page = Dataset::page_filtered(
+        &mut test_db.db,
+        &filter,
+        &RangeParams {
+            sort: Some(SortKey::Name),
+            order: Some(SortOrder::Desc),
+            offset: Some(1),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(page.total, 3);
+    let names = page
+        .datasets
+        .iter()
+        .map(|d| d.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["sorted_b", "other_d"]);
+
+    // datasets have no size to sort by, and a cursor must match the sort requested
+    let invalid = vec![
+        RangeParams {
+            sort: Some(SortKey::Size),
+            ..Default::default()
+        },
+        RangeParams {
+            sort: Some(SortKey::CreatedAt),
+            cursor: params.cursor.clone(),
+            ..Default::default()
+        },
+    ];
+    for params in invalid {
+        assert!(Dataset::page_filtered(&mut test_db.db, &filter, &params)
+            .await
+            .is_err());
+    }
+
+    let dataset = Dataset::find(&mut test_db.db, "sorted_a").await.unwrap();
+    for (name, size) in &[
+        ("part_1", 300),
+        ("part_2", 100),
+        ("part_3", 200),
+        ("other", 50),
+    ] {
+        dataset
+            .register_partition(
+                &mut test_db.db,
+                name,
+                format!("gs://sorted/{}", name),
+                *size,
+            )
+            .await
+            .unwrap();
+    }
+
+    let mut params = RangeParams {
+        count: Some(1),
+        sort: Some(SortKey::Size),
+        order: Some(SortOrder::Asc),
+        ..Default::default()
+    };
+    let filter = PartitionFilter {
+        name_prefix: Some("part_".to_string()),
+        min_size: Some(100),
+        max_size: Some(250),
+        ..Default::default()
+    };
+    let mut names = vec![];
+    loop {
+        let page = dataset
+            .partitions_page_filtered(&mut test_db.db, &filter, &params)
+            .await
+            .unwrap();
+        assert_eq!(page.total, 2);
+        names.extend(page.partitions.into_iter().map(|p| p.name));
+        match page.next {
+            Some(next) => params.cursor = Some(Cursor::decode(next).unwrap()),
+            None => break,
+        }
+    }
+    assert_eq!(names, ["part_2", "part_3"]);
+
+    let page = dataset
+        .partitions_page_filtered(
+            &mut test_db.db,
+            &PartitionFilter::default(),
+            &RangeParams {
+                sort: Some(SortKey::Size),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let sizes = page.partitions.iter().map(|p| p.size).collect::<Vec<_>>();
+    assert_eq!(sizes, [300, 200, 100, 50]);
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_lookup_not_found() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+
+    let manager = testutil::create_manager(&mut test_db).await.unwrap();
+    let dataset = manager
+        .register_dataset(
+            &mut test_db.db,
+            "lookup_dataset",
+            Compression::Uncompressed,
+            Format::Csv,
+            Classification::Internal,
+            DatasetSchema::new(),
+            "lookup dataset",
+        )
+        .await
+        .unwrap();
+
+    // missing records are reported as such, rather than as a failed query
+    assert!(matches!(
+        Dataset::find(&mut test_db.db, "missing_dataset").await,
+        Err(Error::NotFound(_))
+    ));
+    assert!(matches!(
+        Dataset::find_deleted(&mut test_db.db, "lookup_dataset").await,
+        Err(Error::NotFound(_))
+    ));
+    assert!(matches!(
+        dataset
+            .partition(&mut test_db.db, "missing_partition")
+            .await,
+        Err(Error::NotFound(_))
+    ));
+    assert!(matches!(
+        dataset.partition(&mut test_db.db, "latest").await,
+        Err(Error::NotFound(_))
+    ));
+    assert!(matches!(
+        Manager::find(&mut test_db.db, Uuid::new_v4()).await,
+        Err(Error::NotFound(_))
+    ));
+    assert!(matches!(
+        Manager::find_by_id(&mut test_db.db, -1).await,
+        Err(Error::NotFound(_))
+    ));
+    assert!(matches!(
+        Manager::find_by_email(&mut test_db.db, "missing@example.com").await,
+        Err(Error::NotFound(_))
+    ));
+    assert!(matches!(
+        test_db.db.find_transfer(-1).await,
+        Err(Error::NotFound(_))
+    ));
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_register_partitions() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+    let manager = testutil::create_manager(&mut test_db).await.unwrap();
+    let dataset = manager
+        .register_dataset(
+            &mut test_db.db,
+            "backfilled_events",
+            Compression::Gzip,
+            Format::Json,
+            Classification::Internal,
+            DatasetSchema::new(),
+            "backfilled dataset",
+        )
+        .await
+        .unwrap()
+        .set_partition_template(
+            &mut test_db.db,
+            Some("{yyyy}/{mm}/{dd}/{hh}/part-{shard}.{ext}"),
+        )
+        .await
+        .unwrap();
+
+    // two days of hourly partitions are registered at once, with keys and data ranges based on
+    // their names
+    let hourly = (0..48)
+        .map(|i| {
+            let name = format!("2020/03/{:02}/{:02}/part-0.json.tar.gz", 1 + i / 24, i % 24);
+            NewPartition {
+                url: format!("gs://events/{}", name),
+                name,
+                size: 100,
+                data_start: None,
+                data_end: None,
+            }
+        })
+        .collect::<Vec<_>>();
+    let registered = dataset
+        .register_partitions(&mut test_db.db, &hourly)
+        .await
+        .unwrap();
+    assert_eq!(registered.len(), 48);
+    let partition = dataset
+        .partition(&mut test_db.db, "2020/03/02/05/part-0.json.tar.gz")
+        .await
+        .unwrap();
+    assert_eq!(partition.key.date, Some(NaiveDate::from_ymd(2020, 3, 2)));
+    assert_eq!(partition.key.hour, Some(5));
+    assert_eq!(
+        partition.data_start,
+        Some(DateTime::<Utc>::from_utc(
+            NaiveDate::from_ymd(2020, 3, 2).and_hms(5, 0, 0),
+            Utc
+        ))
+    );
+
+    // registering existing partitions again updates them, and a data range can be provided
+    let start = DateTime::<Utc>::from_utc(NaiveDate::from_ymd(2020, 3, 1).and_hms(0, 0, 0), Utc);
+    let updated = NewPartition {
+        size: 200,
+        data_start: Some(start),
+        data_end: Some(start + chrono::Duration::minutes(30)),
+        ..hourly[0].clone()
+    };
+    dataset
+        .register_partitions(&mut test_db.db, std::slice::from_ref(&updated))
+        .await
+        .unwrap();
+    let page = dataset
+        .partitions_page_filtered(
+            &mut test_db.db,
+            &PartitionFilter::default(),
+            &RangeParams::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.total, 48);
+    let partition = dataset
+        .partition(&mut test_db.db, &updated.name)
+        .await
+        .unwrap();
+    assert_eq!(partition.size, 200);
+    assert_eq!(partition.data_end, updated.data_end);
+
+    // a batch with any invalid partition registers none of them
+    let new = |name: &str| NewPartition {
+        name: name.to_string(),
+        url: format!("gs://events/{}", name),
+        size: 10,
+        data_start: None,
+        data_end: None,
+    };
+    for batch in &[
+        vec![new("2020/03/03/00/part-0.json.tar.gz"), new("bad/name")],
+        vec![
+            new("2020/03/03/00/part-0.json.tar.gz"),
+            new("2020/03/03/00/part-0.json.tar.gz"),
+        ],
+        vec![new("2020/03/03/00/part-0.json.tar.gz"), new("latest")],
+        vec![NewPartition {
+            data_start: Some(start),
+            ..new("2020/03/03/00/part-0.json.tar.gz")
+        }],
+    ] {
+        assert!(matches!(
+            dataset.register_partitions(&mut test_db.db, batch).await,
+            Err(Error::InputValidation(_))
+        ));
+    }
+    assert!(matches!(
+        dataset
+            .partition(&mut test_db.db, "2020/03/03/00/part-0.json.tar.gz")
+            .await,
+        Err(Error::NotFound(_))
+    ));
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_transaction() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+    let manager = testutil::create_manager(&mut test_db).await.unwrap();
+    let api_key = manager.api_key;
+
+    // a failure rolls back every operation of the transaction, and runs its compensations
+    let compensated = Arc::new(AtomicBool::new(false));
+    let compensation = compensated.clone();
+    let result: Result<Dataset, Error> = test_db
+        .db
+        .transaction(move |tx| {
+            Box::pin(async move {
+                let dataset = manager
+                    .register_dataset(
+                        tx,
+                        "tx_dataset",
+                        Compression::Uncompressed,
+                        Format::Csv,
+                        Classification::Internal,
+                        DatasetSchema::new(),
+                        "transaction dataset",
+                    )
+                    .await?;
+                tx.on_rollback(async move {
+                    compensation.store(true, Ordering::SeqCst);
+                    Ok(())
+                });
+
+                let mut labels = DatasetLabels::new();
+                labels.insert("team".to_string(), "billing".to_string());
+                dataset.set_labels(tx, &labels).await?;
+
+                Err(Error::InputValidation("aborted".into()))
+            })
+        })
+        .await;
+    assert!(matches!(result, Err(Error::InputValidation(_))));
+    assert!(compensated.load(Ordering::SeqCst));
+    assert!(matches!(
+        Dataset::find(&mut test_db.db, "tx_dataset").await,
+        Err(Error::NotFound(_))
+    ));
+
+    // on success, every operation is committed and no compensation runs
+    let manager = Manager::find(&mut test_db.db, api_key).await.unwrap();
+    let compensated = Arc::new(AtomicBool::new(false));
+    let compensation = compensated.clone();
+    let dataset = test_db
+        .db
+        .transaction(move |tx| {
+            Box::pin(async move {
+                let dataset = manager
+                    .register_dataset(
+                        tx,
+                        "tx_dataset",
+                        Compression::Uncompressed,
+                        Format::Csv,
+                        Classification::Internal,
+                        DatasetSchema::new(),
+                        "transaction dataset",
+                    )
+                    .await?;
+                tx.on_rollback(async move {
+                    compensation.store(true, Ordering::SeqCst);
+                    Ok(())
+                });
+
+                let mut labels = DatasetLabels::new();
+                labels.insert("team".to_string(), "billing".to_string());
+                dataset.set_labels(tx, &labels).await
+            })
+        })
+        .await
+        .unwrap();
+    assert!(!compensated.load(Ordering::SeqCst));
+    let found = Dataset::find(&mut test_db.db, "tx_dataset").await.unwrap();
+    assert_eq!(found.id, dataset.id);
+    assert_eq!(
+        found.labels.get("team").map(|v| v.as_str()),
+        Some("billing")
+    );
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_catalog_stats() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+    let manager = testutil::create_manager(&mut test_db).await.unwrap();
+
+    let mut datasets = vec![];
+    for (name, classification) in &[
+        ("stats_internal", Classification::Internal),
+        ("stats_public", Classification::Public),
+        ("stats_public_deleted", Classification::Public),
+    ] {
+        let dataset = manager
+            .register_dataset(
+                &mut test_db.db,
+                name,
+                Compression::Uncompressed,
+                Format::Csv,
+                classification.clone(),
+                DatasetSchema::new(),
+                "stats dataset",
+            )
+            .await
+            .unwrap();
+        datasets.push(dataset);
+    }
+    for (name, size) in &[("2020/06/01.csv", 100), ("2020/06/02.csv", 250)] {
+        datasets[1]
+            .register_partition(&mut test_db.db, name, "gs://stats/public", *size)
+            .await
+            .unwrap();
+    }
+    datasets[1]
+        .register_partition(&mut test_db.db, "2020/06/03.csv", "gs://stats/public", 50)
+        .await
+        .unwrap();
+    datasets[1]
+        .delete_partition(&mut test_db.db, "2020/06/03.csv")
+        .await
+        .unwrap();
+    datasets[2]
+        .register_partition(
+            &mut test_db.db,
+            "2020/06/01.csv",
+            "gs://stats/deleted",
+            1000,
+        )
+        .await
+        .unwrap();
+    datasets
+        .pop()
+        .unwrap()
+        .delete(&mut test_db.db)
+        .await
+        .unwrap();
+
+    // deleted datasets and partitions aren't counted
+    let mut stats = CatalogStats::list(&mut test_db.db).await.unwrap();
+    stats.sort_by_key(|stats| stats.classification.to_string());
+    assert_eq!(
+        stats,
+        vec![
+            CatalogStats {
+                classification: Classification::Internal,
+                datasets: 1,
+                partitions: 0,
+                bytes: 0,
+            },
+            CatalogStats {
+                classification: Classification::Public,
+                datasets: 1,
+                partitions: 2,
+                bytes: 350,
+            },
+        ]
+    );
+
     testutil::drop_test_db(test_db).await.unwrap();
 }
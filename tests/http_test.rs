@@ -0,0 +1,217 @@
+use std::sync::Arc;
+
+#[allow(dead_code)]
+mod testutil;
+
+use data_dictionary::api::{self, Server};
+use data_dictionary::bucket::BucketManager;
+use data_dictionary::config::Config;
+use data_dictionary::dict::{Classification, Compression, DatasetSchema, Format};
+use data_dictionary::gcp_client::GcpClient;
+
+use actix_web::{http::StatusCode, test, App};
+
+// signed URLs are signed locally, so the server only needs a service account key to sign them
+fn test_server(test_db: &testutil::TestDb) -> Server {
+    let mut config = Config::default();
+    config.gcp.credentials = Some("tests/json/service_account.json".into());
+    let client = GcpClient::new(config.gcp.credentials.as_deref()).unwrap();
+    Server {
+        db: test_db.db.clone(),
+        bucket_manager: Arc::new(BucketManager::new(&config, client)),
+        pubsub: None,
+    }
+}
+
+#[actix_rt::test]
+async fn test_download_partition_ttl() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+    let manager = testutil::create_manager(&mut test_db).await.unwrap();
+    let dataset = manager
+        .register_dataset(
+            &mut test_db.db,
+            "signed_dataset",
+            Compression::Uncompressed,
+            Format::Json,
+            Classification::Public,
+            DatasetSchema::new(),
+            "signed dataset",
+        )
+        .await
+        .unwrap();
+    dataset
+        .register_partition(
+            &mut test_db.db,
+            "2020/01/01.json",
+            "gs://signed/01.json",
+            10,
+        )
+        .await
+        .unwrap();
+
+    let mut app = test::init_service(
+        App::new()
+            .data(test_server(&test_db))
+            .configure(api::routes::configure),
+    )
+    .await;
+    let download = |ttl: &str| {
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/v1/dataset/signed_dataset/2020/01/01.json/download?ttl={}",
+                ttl
+            ))
+            .to_request()
+    };
+
+    // a ttl too large for a Duration is rejected, rather than panicking
+    let resp = test::call_service(&mut app, download("9223372036854775807")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = test::call_service(&mut app, download("-1")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = test::call_service(&mut app, download("3600")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[actix_rt::test]
+async fn test_upload_partition_ttl() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+    let manager = testutil::create_manager(&mut test_db).await.unwrap();
+    manager
+        .register_dataset(
+            &mut test_db.db,
+            "uploaded_dataset",
+            Compression::Uncompressed,
+            Format::Json,
+            Classification::Restricted,
+            DatasetSchema::new(),
+            "uploaded dataset",
+        )
+        .await
+        .unwrap();
+
+    let mut app = test::init_service(
+        App::new()
+            .data(test_server(&test_db))
+            .configure(api::routes::configure),
+    )
+    .await;
+    let upload = |ttl: &str| {
+        test::TestRequest::post()
+            .uri(&format!(
+                "/api/v1/upload/uploaded_dataset/2020/01/01.json?ttl={}",
+                ttl
+            ))
+            .header("Authorization", format!("Bearer {}", manager.api_key))
+            .to_request()
+    };
+
+    // restricted datasets are only signed for 15 minutes at most
+    let resp = test::call_service(&mut app, upload("9223372036854775807")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = test::call_service(&mut app, upload("-1")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = test::call_service(&mut app, upload("3600")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = test::call_service(&mut app, upload("900")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[actix_rt::test]
+async fn test_error_kinds() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+    let owner = testutil::create_manager(&mut test_db).await.unwrap();
+    let recipient = testutil::create_manager(&mut test_db).await.unwrap();
+    owner
+        .register_dataset(
+            &mut test_db.db,
+            "transferred_dataset",
+            Compression::Uncompressed,
+            Format::Json,
+            Classification::Internal,
+            DatasetSchema::new(),
+            "transferred dataset",
+        )
+        .await
+        .unwrap();
+
+    let mut app = test::init_service(
+        App::new()
+            .data(test_server(&test_db))
+            .configure(api::routes::configure),
+    )
+    .await;
+    let request_transfer = |email: &str| {
+        test::TestRequest::post()
+            .uri("/api/v1/transfers/transferred_dataset")
+            .header("Authorization", format!("Bearer {}", owner.api_key))
+            .set_json(&serde_json::json!({ "email": email }))
+            .to_request()
+    };
+
+    // errors from the service keep their kind, rather than all being internal
+    let resp = test::call_service(&mut app, request_transfer(&recipient.email)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = test::call_service(&mut app, request_transfer(&owner.email)).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
+
+#[actix_rt::test]
+async fn test_search_datasets_paging() {
+    let mut test_db = testutil::new_test_db().await.unwrap();
+    let manager = testutil::create_manager(&mut test_db).await.unwrap();
+    for name in &["searched_one", "searched_two"] {
+        manager
+            .register_dataset(
+                &mut test_db.db,
+                name,
+                Compression::Uncompressed,
+                Format::Json,
+                Classification::Public,
+                DatasetSchema::new(),
+                "searched dataset",
+            )
+            .await
+            .unwrap();
+    }
+
+    let mut app = test::init_service(
+        App::new()
+            .data(test_server(&test_db))
+            .configure(api::routes::configure),
+    )
+    .await;
+    let search = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+    let resp = test::call_service(&mut app, search("/api/v1/datasets/search?count=-1")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = test::call_service(&mut app, search("/api/v1/datasets/search?offset=-1")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    // a count larger than a page is clamped to one
+    let results: serde_json::Value = test::read_response_json(
+        &mut app,
+        search("/api/v1/datasets/search?term=searched&count=2147483647"),
+    )
+    .await;
+    assert_eq!(results["datasets"].as_array().unwrap().len(), 2);
+    let results: serde_json::Value = test::read_response_json(
+        &mut app,
+        search("/api/v1/datasets/search?term=searched&count=1&offset=1"),
+    )
+    .await;
+    assert_eq!(results["datasets"].as_array().unwrap().len(), 1);
+
+    // the unversioned API is sent only the matching datasets
+    let results: serde_json::Value =
+        test::read_response_json(&mut app, search("/api/datasets/search?term=searched")).await;
+    assert_eq!(results.as_array().unwrap().len(), 2);
+
+    testutil::drop_test_db(test_db).await.unwrap();
+}
@@ -1,4 +1,12 @@
 DROP TABLE IF EXISTS refinery_schema_history CASCADE;
+DROP TABLE IF EXISTS audit_events CASCADE;
+DROP TABLE IF EXISTS access_log CASCADE;
+DROP TABLE IF EXISTS dataset_read_grants CASCADE;
+DROP TABLE IF EXISTS dataset_labels CASCADE;
+DROP TABLE IF EXISTS dataset_schema_fields CASCADE;
+DROP TABLE IF EXISTS dataset_schema_versions CASCADE;
+DROP TABLE IF EXISTS dataset_transfers CASCADE;
+DROP TABLE IF EXISTS dataset_maintainers CASCADE;
 DROP TABLE IF EXISTS partitions CASCADE;
 DROP TABLE IF EXISTS datasets CASCADE;
 DROP TABLE IF EXISTS managers CASCADE;
@@ -7,4 +15,7 @@ DROP TYPE IF EXISTS encoding_t CASCADE;
 DROP TYPE IF EXISTS classification_t CASCADE;
 DROP FUNCTION IF EXISTS on_update_set_timestamp CASCADE;
 DROP FUNCTION IF EXISTS on_partition_create_update_dataset CASCADE;
+DROP FUNCTION IF EXISTS on_audit_event_modify_reject CASCADE;
+DROP FUNCTION IF EXISTS dataset_search_document CASCADE;
+DROP FUNCTION IF EXISTS on_dataset_schema_change_add_version CASCADE;
 DROP EXTENSION IF EXISTS hstore CASCADE;
\ No newline at end of file
@@ -1,3 +1,4 @@
+use data_dictionary::config::Config;
 use data_dictionary::db::{rand, Db, CHARACTER_SET};
 use data_dictionary::dict::{Classification, Compression, DatasetSchema, FileExt, Format, Manager};
 use data_dictionary::error::Error;
@@ -20,11 +21,15 @@ pub async fn clear_db(conn: &mut TestDb) -> Result<(), Error> {
         .await?
         .batch_execute(include_str!("sql/drop_all.sql"))
         .await
-        .map_err(|e| Error::Generic(Box::new(e)))
+        .map_err(Error::from)
 }
 
 pub async fn new_test_db() -> Result<TestDb, Error> {
-    let db = Db::connect(None, None).await?;
+    // the schema is set on a single connection, so the pool must only have the one
+    let mut config = Config::from_env()?.database;
+    config.min_idle = 1;
+    config.max_size = 1;
+    let db = Db::connect(&config).await?;
     // create random schema name and use it for the current connection (used by single test)
     let mut test_db = TestDb {
         db,